@@ -54,5 +54,5 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Press Ctrl+C to exit");
     
     // Show the instrument with the command stream
-    instrument.show_with_commands(receiver)
+    Ok(instrument.show_with_commands(receiver)?)
 }
@@ -5,17 +5,28 @@
 // External crate imports
 use bon::Builder;
 use pixels::{Pixels, SurfaceTexture};
-use rusttype::{Font, Scale};
 
 // Standard library imports
+use std::collections::HashMap;
 use std::sync::mpsc::Receiver;
-use std::time::Instant;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
 
 // Window management imports
 use winit::dpi::LogicalSize;
-use winit::event::{Event, WindowEvent};
+use winit::event::{ElementState, Event, MouseButton, WindowEvent};
 use winit::event_loop::{ControlFlow, EventLoop};
-use winit::window::WindowBuilder;
+use winit::keyboard::{Key, NamedKey};
+use winit::window::{WindowBuilder, WindowLevel};
+
+// Enters a `tracing` span until the end of the enclosing block; compiles to nothing
+// without the `tracing` feature
+macro_rules! trace_span {
+    ($($span:tt)*) => {
+        #[cfg(feature = "tracing")]
+        let _span = tracing::trace_span!($($span)*).entered();
+    };
+}
 
 // ============================================================================
 // COLOR CONFIGURATION
@@ -40,928 +51,10534 @@ impl Color {
 }
 
 // ============================================================================
-// PUBLIC API - MAIN INTERFACE
+// SCALE CONFIGURATION
 // ============================================================================
 
-/// Command enum for type-safe instrument updates
+/// A major tick at an explicit scale value, optionally with its own label
 #[derive(Debug, Clone)]
-pub enum InstrumentCommand {
-    SetPrimaryNeedle(f64),
-    SetSecondaryNeedle(f64),
-    SetChronograph(f64),
-    SetSecondaryChronograph(f64),
-    SetReadout(f64),
-    SetHighlightBounds(f64, f64),
-    SetBothNeedles(f64, f64),          // primary, secondary
-    SetAllNeedles(f64, f64, f64, f64), // primary, secondary, chronograph, secondary_chronograph
-    SetBothChronographs(f64, f64),     // chronograph, secondary_chronograph
+pub struct Tick {
+    pub value: f64,
+    pub label: Option<String>,
 }
 
-/// Main instrument struct - the primary public interface
+impl Tick {
+    pub fn new(value: f64) -> Self {
+        Self { value, label: None }
+    }
+
+    pub fn labelled(value: f64, label: impl Into<String>) -> Self {
+        Self {
+            value,
+            label: Some(label.into()),
+        }
+    }
+}
+
+/// An RGBA8 image, `width * height * 4` bytes, row-major
 #[derive(Debug, Clone)]
-pub struct Instrument {
-    config: InstrumentConfig,
-    state: InstrumentState,
+pub struct Bitmap {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
 }
 
-#[derive(Debug, Clone, Builder)]
-pub struct InstrumentConfig {
-    #[builder(default = "".to_string())]
-    pub title: String,
-    #[builder(default = (0.0, 100.0))]
-    pub range: (f64, f64),
-    pub highlight_band: Option<(f64, f64, Color)>,
+#[derive(Debug, Clone)]
+pub enum Icon {
+    Bitmap(std::sync::Arc<Bitmap>),
+    /// A character drawn with the configured fonts, e.g. from an icon font in `fallback_fonts`
+    Glyph(char),
+}
 
-    // Window configuration
-    #[builder(default = 300)]
-    pub window_width: usize,
-    #[builder(default = 300)]
-    pub window_height: usize,
-    #[builder(default = 60.0)]
-    pub max_framerate: f64,
+/// An icon drawn at a scale value, at the same radius as the tick labels
+#[derive(Debug, Clone)]
+pub struct ScaleIcon {
+    pub value: f64,
+    pub icon: Icon,
+    /// Glyph font size or bitmap height in pixels; defaults to the dial number size
+    pub size: Option<f32>,
+    /// Suppress the numeric label of a major tick at the same value
+    pub replaces_label: bool,
+}
 
-    // Main dial configuration
-    #[builder(default = 45)]
-    pub dial_margin: i32,
-    #[builder(default = 4)]
-    pub dial_thickness: i32,
-    #[builder(default = 30.0)]
-    pub dial_numbers_font_size: f32,
-    #[builder(default = 30.0)]
-    pub dial_ticks_to_numbers_distance: f64,
+impl ScaleIcon {
+    pub fn glyph(value: f64, glyph: char) -> Self {
+        Self {
+            value,
+            icon: Icon::Glyph(glyph),
+            size: None,
+            replaces_label: false,
+        }
+    }
 
-    // Tick configuration
-    #[builder(default = 11)]
-    pub ticks_count: usize,
-    #[builder(default = 5)]
-    pub minor_ticks_per_interval: usize,
-    #[builder(default = 40)]
-    pub major_tick_length: i32,
-    #[builder(default = 25)]
-    pub minor_tick_length: i32,
-    #[builder(default = 2.0)]
-    pub major_tick_thickness: f32,
-    #[builder(default = 0.5)]
-    pub minor_tick_thickness: f32,
+    pub fn bitmap(value: f64, bitmap: std::sync::Arc<Bitmap>) -> Self {
+        Self {
+            value,
+            icon: Icon::Bitmap(bitmap),
+            size: None,
+            replaces_label: false,
+        }
+    }
 
-    // Needle configuration
-    #[builder(default = 1.05)]
-    pub needle_length_factor: f64,
-    #[builder(default = 80.0)]
-    pub needle_back_length: f64,
-    #[builder(default = 4.0)]
-    pub needle_width: f32,
-    #[builder(default = 0.1)]
-    pub needle_lerp_factor: f64,
+    pub fn sized(self, size: f32) -> Self {
+        Self {
+            size: Some(size),
+            ..self
+        }
+    }
 
-    // Chronograph configuration
-    #[builder(default = (0.0, 60.0))]
-    pub chronograph_range: (f64, f64),
-    #[builder(default = 130)]
-    pub chronograph_dial_shift: i32,
-    #[builder(default = 7.0)]
-    pub chronograph_dial_size: f64,
-    #[builder(default = 5)]
-    pub chronograph_ticks_count: usize,
-    #[builder(default = 10)]
-    pub chronograph_tick_length: i32,
-    #[builder(default = 15)]
-    pub chronograph_dial_margin: i32,
-    #[builder(default = 2)]
-    pub chronograph_dial_thickness: i32,
-    #[builder(default = 1.0)]
-    pub chronograph_needle_length_factor: f64,
-    #[builder(default = 4.0)]
-    pub chronograph_needle_width: f32,
-    #[builder(default = 30.0)]
-    pub chronograph_needle_back_length: f64,
-    #[builder(default = 30.0)]
-    pub chronograph_dial_numbers_font_size: f32,
-    #[builder(default = 30.0)]
-    pub chronograph_dial_ticks_to_numbers_distance: f64,
-    #[builder(default = 8)]
-    pub chronograph_dial_dot_radius: i32,
-    #[builder(default = 0)]
-    pub chronograph_minor_ticks_per_interval: usize,
-    #[builder(default = 4)]
-    pub chronograph_minor_tick_length: i32,
-    #[builder(default = 2.0)]
-    pub chronograph_major_tick_thickness: f32,
-    #[builder(default = 0.5)]
-    pub chronograph_minor_tick_thickness: f32,
+    pub fn replacing_label(self) -> Self {
+        Self {
+            replaces_label: true,
+            ..self
+        }
+    }
+}
 
-    // Secondary Chronograph configuration
-    #[builder(default = (0.0, 60.0))]
-    pub secondary_chronograph_range: (f64, f64),
-    #[builder(default = 90)]
-    pub secondary_chronograph_dial_shift: i32,
-    #[builder(default = 7.0)]
-    pub secondary_chronograph_dial_size: f64,
-    #[builder(default = 5)]
-    pub secondary_chronograph_ticks_count: usize,
-    #[builder(default = 10)]
-    pub secondary_chronograph_tick_length: i32,
-    #[builder(default = 15)]
-    pub secondary_chronograph_dial_margin: i32,
-    #[builder(default = 2)]
-    pub secondary_chronograph_dial_thickness: i32,
-    #[builder(default = 1.0)]
-    pub secondary_chronograph_needle_length_factor: f64,
-    #[builder(default = 4.0)]
-    pub secondary_chronograph_needle_width: f32,
-    #[builder(default = 30.0)]
-    pub secondary_chronograph_needle_back_length: f64,
-    #[builder(default = 30.0)]
-    pub secondary_chronograph_dial_numbers_font_size: f32,
-    #[builder(default = 30.0)]
-    pub secondary_chronograph_dial_ticks_to_numbers_distance: f64,
-    #[builder(default = 8)]
-    pub secondary_chronograph_dial_dot_radius: i32,
-    #[builder(default = 0)]
-    pub secondary_chronograph_minor_ticks_per_interval: usize,
-    #[builder(default = 4)]
-    pub secondary_chronograph_minor_tick_length: i32,
-    #[builder(default = 2.0)]
-    pub secondary_chronograph_major_tick_thickness: f32,
-    #[builder(default = 0.5)]
-    pub secondary_chronograph_minor_tick_thickness: f32,
+/// How tick labels and the readout spell out large numbers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NumberFormat {
+    #[default]
+    Plain,
+    /// Thousands and up get an SI prefix: 1.5k, 2M
+    SiPrefix,
+    /// Thousands and up as a mantissa and a multiple-of-three exponent: 1.5e3, 2e6
+    Engineering,
+}
 
-    // Readout configuration
-    #[builder(default = 0.69)]
-    pub readout_x_factor: f64,
-    #[builder(default = 0.75)]
-    pub readout_y_factor: f64,
-    #[builder(default = 54.0)]
-    pub readout_big_font_size: f32,
-    #[builder(default = 28.0)]
-    pub readout_small_font_size: f32,
-    #[builder(default = 30)]
-    pub readout_box_padding: i32,
-    #[builder(default = 4.0)]
-    pub readout_box_thickness: f32,
+const SI_PREFIXES: [&str; 7] = ["", "k", "M", "G", "T", "P", "E"];
 
-    // Curved text configuration
-    #[builder(default = "".to_string())]
-    pub curved_text: String,
-    #[builder(default = 30.0)]
-    pub curved_text_font_size: f32,
-    #[builder(default = 15.0)]
-    pub curved_text_radius_offset: f64,
-    #[builder(default = std::f64::consts::PI * 0.23)]
-    pub curved_text_arc_span: f64,
-    #[builder(default = 3.0 * std::f64::consts::PI / 2.0)]
-    pub curved_text_angle: f64,
+impl NumberFormat {
+    /// Splits `value` into a mantissa and the suffix that restores its magnitude
+    fn split(self, value: f64) -> (f64, String) {
+        if self == NumberFormat::Plain || !value.is_finite() || value.abs() < 1000.0 {
+            return (value, String::new());
+        }
+        let group = ((value.abs().log10() / 3.0).floor() as usize).min(SI_PREFIXES.len() - 1);
+        let mantissa = value / 1000f64.powi(group as i32);
+        let suffix = match self {
+            NumberFormat::Engineering => format!("e{}", group * 3),
+            _ => SI_PREFIXES[group].to_string(),
+        };
+        (mantissa, suffix)
+    }
 
-    // Labels
-    #[builder(default = "Primary".to_string())]
-    pub primary_label: String,
-    #[builder(default = "Secondary".to_string())]
-    pub secondary_label: String,
+    /// Formats with `decimals` in plain mode; prefixed mantissas keep up to three
+    /// decimals with trailing zeros trimmed
+    fn format(self, value: f64, decimals: usize) -> String {
+        let (mantissa, suffix) = self.split(value);
+        if suffix.is_empty() {
+            return format!("{:.*}", decimals, value);
+        }
+        let digits = format!("{:.3}", mantissa);
+        let digits = digits.trim_end_matches('0').trim_end_matches('.');
+        format!("{digits}{suffix}")
+    }
+}
 
-    // Highlight band configuration
-    #[builder(default = 20)]
-    pub highlight_band_width: i32,
-    #[builder(default = Color::new(0xff, 0x00, 0x00))]
-    pub highlight_band_color: Color,
-    #[builder(default = 1.0)]
-    pub highlight_band_alpha: f64,
-    #[builder(default = 0.005)]
-    pub highlight_band_edge_softness: f64,
+/// A coloured stretch of the main scale, such as a green normal or red danger range
+#[derive(Debug, Clone, Copy)]
+pub struct Zone {
+    pub from: f64,
+    pub to: f64,
+    pub color: Color,
+}
 
-    // Colors
-    pub background_color: Option<Color>,
-    pub text_color: Option<Color>,
-    pub needle_color: Option<Color>,
-    pub dial_color: Option<Color>,
+impl Zone {
+    pub fn new(from: f64, to: f64, color: Color) -> Self {
+        Self { from, to, color }
+    }
 
-    // Font configuration
-    #[builder(default = include_bytes!("BerkeleyMono-Regular.otf"))]
-    pub font_data: &'static [u8],
-    #[builder(default = 50.0)]
-    pub exclamation_mark_size: f32,
-    #[builder(default = 6)]
-    pub dot_radius: i32,
+    fn contains(&self, value: f64) -> bool {
+        value >= self.from.min(self.to) && value <= self.from.max(self.to)
+    }
 }
 
-// ============================================================================
-// CONFIGURATION TYPES (INTERNAL)
-// ============================================================================
+// Colour of the last zone containing `value`, so later zones paint over earlier ones;
+// a zone fading in or out blends with `fallback` by its opacity
+fn zone_color(zones: &[(Zone, f64)], value: f64, fallback: (u8, u8, u8)) -> (u8, u8, u8) {
+    zones
+        .iter()
+        .rev()
+        .find(|(zone, _)| zone.contains(value))
+        .map_or(fallback, |(zone, alpha)| {
+            blend(fallback, zone.color.as_tuple(), *alpha)
+        })
+}
 
-#[derive(Debug, Clone)]
-struct InstrumentState {
-    primary_value: f64,
-    secondary_value: Option<f64>,
-    chronograph_value: Option<f64>,
-    secondary_chronograph_value: Option<f64>,
-    readout_value: Option<f64>,
+fn blend(from: (u8, u8, u8), to: (u8, u8, u8), t: f64) -> (u8, u8, u8) {
+    let mix = |a: u8, b: u8| (a as f64 + (b as f64 - a as f64) * t.clamp(0.0, 1.0)).round() as u8;
+    (mix(from.0, to.0), mix(from.1, to.1), mix(from.2, to.2))
 }
 
-impl Instrument {
-    pub fn set_value(&mut self, value: f64) {
-        self.state.primary_value = value.clamp(self.config.range.0, self.config.range.1);
-        self.state.readout_value = Some(self.state.primary_value);
+/// A stretch of the main scale squeezed into a short gap marked with a break symbol,
+/// e.g. 0–10 in detail and then straight on to 100
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScaleBreak {
+    pub from: f64,
+    pub to: f64,
+    /// Share of the arc the skipped stretch still occupies; 0 skips it outright
+    pub gap: f64,
+}
+
+impl ScaleBreak {
+    pub fn new(from: f64, to: f64) -> Self {
+        Self {
+            from,
+            to,
+            gap: 0.05,
+        }
     }
 
-    pub fn set_primary_value(&mut self, value: f64) {
-        self.state.primary_value = value.clamp(self.config.range.0, self.config.range.1);
+    pub fn gap(self, gap: f64) -> Self {
+        Self { gap, ..self }
     }
 
-    pub fn set_secondary_value(&mut self, value: f64) {
-        let clamped_value = value.clamp(self.config.range.0, self.config.range.1);
-        self.state.secondary_value = Some(clamped_value);
+    // Position along the arc, 0 at `min` and 1 at `max`; the rest of the range shares the
+    // arc outside the gap evenly
+    fn fraction(&self, value: f64, (min, max): (f64, f64)) -> f64 {
+        let (from, to) = (self.from.clamp(min, max), self.to.clamp(min, max));
+        let kept = (from - min) + (max - to);
+        if to <= from || kept <= 0.0 {
+            return (value - min) / (max - min);
+        }
+        let gap = self.gap.clamp(0.0, 1.0);
+        let per_unit = (1.0 - gap) / kept;
+        if value <= from {
+            (value - min) * per_unit
+        } else if value < to {
+            (from - min) * per_unit + gap * (value - from) / (to - from)
+        } else {
+            (from - min) * per_unit + gap + (value - to) * per_unit
+        }
     }
 
-    pub fn set_chronograph_value(&mut self, value: f64) {
-        let clamped_value = value.clamp(
-            self.config.chronograph_range.0,
-            self.config.chronograph_range.1,
-        );
-        self.state.chronograph_value = Some(clamped_value);
+    fn hides(&self, value: f64) -> bool {
+        value > self.from && value < self.to
     }
+}
 
-    pub fn set_secondary_chronograph_value(&mut self, value: f64) {
-        let clamped_value = value.clamp(
-            self.config.secondary_chronograph_range.0,
-            self.config.secondary_chronograph_range.1,
-        );
-        self.state.secondary_chronograph_value = Some(clamped_value);
+// Linear unless the scale has a break
+fn scale_fraction(value: f64, range: (f64, f64), scale_break: Option<&ScaleBreak>) -> f64 {
+    match scale_break {
+        Some(scale_break) => scale_break.fraction(value, range),
+        None => (value - range.0) / (range.1 - range.0),
     }
+}
 
-    pub fn show(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let title = self.config.title.clone();
-        let range = self.config.range;
-        let highlight_range = self
-            .config
-            .highlight_band
-            .map(|(min, max, _color)| (min, max));
+/// Which side of the dial arc a set of ticks or labels sits on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ScalePlacement {
+    #[default]
+    Inside,
+    /// Beyond the dial arc
+    Outside,
+}
 
-        self.run_window(title, range, highlight_range, None)
-    }
+// ============================================================================
+// NEEDLE CONFIGURATION
+// ============================================================================
 
-    pub fn show_with_commands(
-        &mut self,
-        receiver: Receiver<InstrumentCommand>,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let title = self.config.title.clone();
-        let range = self.config.range;
-        let highlight_range = self
-            .config
-            .highlight_band
-            .map(|(min, max, _color)| (min, max));
+/// How a needle travels towards a newly commanded value
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NeedleMotion {
+    /// Continuous exponential easing
+    #[default]
+    Smooth,
+    /// Discrete jump with a short damped overshoot, like a quartz movement
+    Quartz,
+}
 
-        self.run_window(title, range, highlight_range, Some(receiver))
-    }
+/// How the chronograph hand advances while the built-in stopwatch is running
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ChronographSweep {
+    /// Glides continuously between whole seconds
+    #[default]
+    Continuous,
+    /// Advances in this many discrete steps per second
+    Stepped(u32),
+}
 
-    fn run_window(
-        &self,
-        title: String,
-        range: (f64, f64),
-        highlight_range: Option<(f64, f64)>,
-        receiver: Option<Receiver<InstrumentCommand>>,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        let logical_width: usize = self.config.window_width;
-        let logical_height: usize = self.config.window_height;
+// ============================================================================
+// TEXT CONFIGURATION
+// ============================================================================
 
-        let event_loop = EventLoop::new()?;
-        let window = WindowBuilder::new()
-            .with_title(&title)
-            .with_inner_size(LogicalSize::new(
-                logical_width as f64,
-                logical_height as f64,
-            ))
-            .with_resizable(false)
-            .build(&event_loop)?;
+/// How curved text is fitted to its configured arc span
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CurvedTextFit {
+    /// Natural glyph advances plus letter spacing
+    #[default]
+    Natural,
+    /// Scale the font (and spacing) so the text exactly fills the arc span
+    ScaleFont,
+    /// Keep the font size and distribute the leftover arc between the letters
+    ExpandSpacing,
+}
 
-        let window = std::sync::Arc::new(window);
+/// Which edge of an element sits on its horizontal anchor point
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HorizontalAnchor {
+    Left,
+    #[default]
+    Center,
+    Right,
+}
 
-        let mut app_state = AppState::new(range.0, range.1);
-        if let Some((lower, upper)) = highlight_range {
-            app_state.set_highlight_override(lower, upper);
-        }
+/// Which edge of an element sits on its vertical anchor point
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum VerticalAnchor {
+    Top,
+    #[default]
+    Middle,
+    Bottom,
+}
 
-        // Initialize app_state with current instrument state
-        app_state.set_primary_value(self.state.primary_value);
-        if let Some(secondary) = self.state.secondary_value {
-            app_state.set_secondary_value(secondary);
-        }
-        if let Some(chronograph) = self.state.chronograph_value {
-            app_state.set_chronograph_value(chronograph);
-        }
-        if let Some(secondary_chronograph) = self.state.secondary_chronograph_value {
-            app_state.set_secondary_chronograph_value(secondary_chronograph);
-        }
-        if let Some(readout) = self.state.readout_value {
-            app_state.set_readout_value(readout);
+/// Placement of the integer digits within the readout's reserved integer field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReadoutAlignment {
+    Left,
+    Center,
+    /// Digits hug the fraction, like a decimal-aligned column
+    #[default]
+    Right,
+}
+
+// ============================================================================
+// ORIENTATION CONFIGURATION
+// ============================================================================
+
+/// Clockwise rotation applied to the whole rendered gauge
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rotation {
+    #[default]
+    None,
+    Cw90,
+    Cw180,
+    Cw270,
+}
+
+impl Rotation {
+    fn swaps_axes(self) -> bool {
+        matches!(self, Rotation::Cw90 | Rotation::Cw270)
+    }
+}
+
+// ============================================================================
+// LAYER CONFIGURATION
+// ============================================================================
+
+/// Named groups of draw commands, composited back to front
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Layer {
+    Background,
+    Zones,
+    Ticks,
+    Needles,
+    Subdials,
+    Readout,
+    Overlay,
+}
+
+impl Layer {
+    pub const ALL: [Layer; 7] = [
+        Layer::Background,
+        Layer::Zones,
+        Layer::Ticks,
+        Layer::Needles,
+        Layer::Subdials,
+        Layer::Readout,
+        Layer::Overlay,
+    ];
+}
+
+// Draw order for `order`, with unlisted layers following in their default position
+// and hidden layers left out
+fn visible_layers(order: &[Layer], hidden: &[Layer]) -> Vec<Layer> {
+    let mut layers: Vec<Layer> = Vec::with_capacity(Layer::ALL.len());
+    for &layer in order.iter().chain(Layer::ALL.iter()) {
+        if !layers.contains(&layer) {
+            layers.push(layer);
         }
+    }
+    layers.retain(|layer| !hidden.contains(layer));
+    layers
+}
 
-        let window_clone = window.clone();
-        let size = window.inner_size();
-        let mut fb_width = size.width as usize;
-        let mut fb_height = size.height as usize;
-        let surface_texture = SurfaceTexture::new(size.width, size.height, &window);
-        let mut pixels = Pixels::new(size.width, size.height, surface_texture)?;
+// ============================================================================
+// ANIMATION
+// ============================================================================
 
-        let target_fps = self.config.max_framerate;
-        let frame_duration = std::time::Duration::from_secs_f64(1.0 / target_fps);
-        let mut last_frame = Instant::now();
+/// What an animation track drives
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationTarget {
+    Primary,
+    Secondary,
+    Chronograph,
+    SecondaryChronograph,
+    Readout,
+}
 
-        event_loop.run(move |event, window_target| {
-            window_target.set_control_flow(ControlFlow::Poll);
-            match event {
-                Event::WindowEvent { event, .. } => match event {
-                    WindowEvent::CloseRequested => {
-                        window_target.exit();
-                    }
-                    WindowEvent::Resized(new_size) => {
-                        fb_width = new_size.width as usize;
-                        fb_height = new_size.height as usize;
-                        let _ = pixels.resize_buffer(new_size.width, new_size.height);
-                        let _ = pixels.resize_surface(new_size.width, new_size.height);
-                    }
-                    WindowEvent::RedrawRequested => {
-                        if let Some(ref receiver) = receiver {
-                            app_state.update_with_commands(receiver);
-                        } else {
-                            app_state.update();
-                        }
+/// Shape of the interpolation into a keyframe from the one before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Easing {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    /// Hold the previous value, then jump on arrival
+    Step,
+}
 
-                        let frame = pixels.frame_mut();
-                        let mut canvas = Canvas::new(frame, fb_width, fb_height);
-                        render_instrument(&mut canvas, &app_state, &self.config);
-                        let _ = pixels.render();
-                    }
-                    _ => {}
-                },
-                Event::AboutToWait => {
-                    if last_frame.elapsed() >= frame_duration {
-                        window_clone.request_redraw();
-                        last_frame = Instant::now();
-                    }
+impl Easing {
+    fn apply(self, t: f64) -> f64 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t).powi(3),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+            Easing::Step => {
+                if t >= 1.0 {
+                    1.0
+                } else {
+                    0.0
                 }
-                _ => {}
             }
-        })?;
-
-        Ok(())
+        }
     }
 }
 
-impl Instrument {
-    pub fn new(config: InstrumentConfig) -> Self {
-        let state = InstrumentState {
-            primary_value: config.range.0,
-            secondary_value: None,
-            chronograph_value: None,
-            secondary_chronograph_value: None,
-            readout_value: None,
+#[derive(Debug, Clone, Copy)]
+struct Keyframe {
+    time: f64,
+    value: f64,
+    easing: Easing,
+}
+
+/// A scripted sequence of keyframes, played with `InstrumentCommand::PlayAnimation`.
+/// Times are seconds from the start of playback; each target is held at its
+/// first keyframe until that keyframe's time and at its last one afterwards.
+#[derive(Debug, Clone, Default)]
+pub struct Animation {
+    tracks: Vec<(AnimationTarget, Vec<Keyframe>)>,
+    looping: bool,
+}
+
+impl Animation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn keyframe(self, target: AnimationTarget, time: f64, value: f64) -> Self {
+        self.keyframe_eased(target, time, value, Easing::Linear)
+    }
+
+    pub fn keyframe_eased(
+        mut self,
+        target: AnimationTarget,
+        time: f64,
+        value: f64,
+        easing: Easing,
+    ) -> Self {
+        let keyframe = Keyframe {
+            time: time.max(0.0),
+            value,
+            easing,
         };
+        match self.tracks.iter_mut().find(|(t, _)| *t == target) {
+            Some((_, keyframes)) => {
+                let index = keyframes.partition_point(|k| k.time <= keyframe.time);
+                keyframes.insert(index, keyframe);
+            }
+            None => self.tracks.push((target, vec![keyframe])),
+        }
+        self
+    }
 
-        Self { config, state }
+    /// Restart from the beginning when the last keyframe is reached
+    pub fn looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    pub fn duration(&self) -> f64 {
+        self.tracks
+            .iter()
+            .filter_map(|(_, keyframes)| keyframes.last())
+            .map(|k| k.time)
+            .fold(0.0, f64::max)
+    }
+
+    fn sample(keyframes: &[Keyframe], time: f64) -> Option<f64> {
+        let next = keyframes.partition_point(|k| k.time <= time);
+        match (
+            next.checked_sub(1).map(|i| keyframes[i]),
+            keyframes.get(next),
+        ) {
+            (None, next) => next.map(|k| k.value),
+            (Some(prev), None) => Some(prev.value),
+            (Some(prev), Some(next)) => {
+                let t = next
+                    .easing
+                    .apply((time - prev.time) / (next.time - prev.time));
+                Some(prev.value + (next.value - prev.value) * t)
+            }
+        }
     }
 }
 
 // ============================================================================
-// INTERNAL IMPLEMENTATION (from main.rs)
+// CALIBRATION
 // ============================================================================
 
+/// Correction for a slightly-off sensor, applied to its values as they arrive: first
+/// the lookup table, then `value * scale + offset`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Calibration {
+    pub offset: f64,
+    pub scale: f64,
+    /// `(raw, corrected)` points sorted by raw value, interpolated linearly and
+    /// extrapolated along the end segments. Empty passes values through.
+    pub table: Vec<(f64, f64)>,
+}
+
+impl Default for Calibration {
+    fn default() -> Self {
+        Self {
+            offset: 0.0,
+            scale: 1.0,
+            table: Vec::new(),
+        }
+    }
+}
+
+impl Calibration {
+    pub fn new(offset: f64, scale: f64) -> Self {
+        Self {
+            offset,
+            scale,
+            table: Vec::new(),
+        }
+    }
+
+    /// Sorts the points and drops any whose raw value repeats an earlier one
+    pub fn with_table(mut self, mut table: Vec<(f64, f64)>) -> Self {
+        table.sort_by(|a, b| a.0.total_cmp(&b.0));
+        table.dedup_by(|a, b| a.0 == b.0);
+        self.table = table;
+        self
+    }
+
+    pub fn apply(&self, raw: f64) -> f64 {
+        let value = match self.table.as_slice() {
+            [] => raw,
+            [(x, y)] => raw - x + y,
+            table => {
+                let i = table
+                    .partition_point(|&(x, _)| x < raw)
+                    .clamp(1, table.len() - 1);
+                let ((x0, y0), (x1, y1)) = (table[i - 1], table[i]);
+                y0 + (raw - x0) * (y1 - y0) / (x1 - x0)
+            }
+        };
+        value * self.scale + self.offset
+    }
+}
+
 // ============================================================================
-// RETAINED MODE ABSTRACTIONS
+// DERIVED CHANNELS
 // ============================================================================
 
-#[derive(Clone, Debug)]
-enum DrawCommand {
-    Clear((u8, u8, u8)),
-    Arc {
-        cx: i32,
-        cy: i32,
-        r: i32,
-        thickness: i32,
-        start_angle: f64,
-        arc_span: f64,
-        color: (u8, u8, u8),
-    },
-    HighlightBand {
-        cx: i32,
-        cy: i32,
-        r: i32,
-        start_angle: f64,
-        end_angle: f64,
-        inner_radius: f64,
-        outer_radius: f64,
-    },
-    Tick {
-        cx: i32,
-        cy: i32,
-        r: i32,
-        angle: f64,
-        length: i32,
-        thickness: f32,
-        color: (u8, u8, u8),
-    },
-    Text {
-        x: i32,
-        y: i32,
-        text: String,
-        font_size: f32,
-        color: (u8, u8, u8),
-    },
-    CurvedText {
-        cx: i32,
-        cy: i32,
-        radius: f64,
-        text: String,
-        font_size: f32,
-        arc_span: f64,
-        start_angle: f64,
-        color: (u8, u8, u8),
-    },
-    NeedleLine {
-        x0: i32,
-        y0: i32,
-        x1: i32,
-        y1: i32,
-        thickness: f32,
-        tapered: bool,
-        color: (u8, u8, u8),
-    },
-    Circle {
-        cx: i32,
-        cy: i32,
-        radius: i32,
-        color: (u8, u8, u8),
-    },
+/// A value computed from other inputs each time one of them arrives, such as
+/// `power = volts * amps`.
+///
+/// Inputs are named by their `SetMany` key, after calibration, and a channel may use
+/// the channels defined before it. Naming a channel `primary`, `secondary`, `chrono1`,
+/// `chrono2` or `readout` routes it there, e.g. `readout = power`. Expressions support
+/// `+ - * / % ^`, parentheses and `abs`, `sqrt`, `ln`, `log10`, `exp`, `round`, `floor`,
+/// `ceil`, `min` and `max`; `^` binds tighter than a leading minus, so `-2^2` is -4. A
+/// channel whose inputs haven't all arrived, or whose result isn't a finite number (say
+/// after dividing by zero), is skipped.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DerivedChannel {
+    key: String,
+    source: String,
+    expression: Expression,
 }
 
-struct Scene {
-    commands: Vec<DrawCommand>,
+impl DerivedChannel {
+    pub fn new(key: &str, expression: &str) -> Result<Self, InstrumentError> {
+        let invalid =
+            |message: String| InstrumentError::InvalidConfig(format!("channel {key:?}: {message}"));
+        if key.is_empty() || !key.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            return Err(invalid(
+                "names may only use letters, digits and _".to_string(),
+            ));
+        }
+        Ok(Self {
+            key: key.to_string(),
+            source: expression.trim().to_string(),
+            expression: Expression::parse(expression).map_err(invalid)?,
+        })
+    }
+
+    /// Reads a `key = expression` definition
+    pub fn parse(definition: &str) -> Result<Self, InstrumentError> {
+        let (key, expression) = definition.split_once('=').ok_or_else(|| {
+            InstrumentError::InvalidConfig(format!("expected key = expression, got {definition:?}"))
+        })?;
+        Self::new(key.trim(), expression)
+    }
+
+    pub fn key(&self) -> &str {
+        &self.key
+    }
+
+    /// The expression as written
+    pub fn expression(&self) -> &str {
+        &self.source
+    }
 }
 
-impl Scene {
-    fn new(_width: usize, _height: usize) -> Self {
-        Self {
-            commands: Vec::new(),
+impl std::fmt::Display for DerivedChannel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} = {}", self.key, self.source)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expression {
+    Number(f64),
+    Input(String),
+    Negate(Box<Expression>),
+    Binary(char, Box<Expression>, Box<Expression>),
+    Call(String, Vec<Expression>),
+}
+
+impl Expression {
+    fn parse(text: &str) -> Result<Self, String> {
+        let mut parser = ExpressionParser {
+            chars: text.chars().peekable(),
+        };
+        let expression = parser.sum()?;
+        match parser.next_token() {
+            None => Ok(expression),
+            Some(c) => Err(format!("unexpected {c:?}")),
         }
     }
 
-    fn add_command(&mut self, command: DrawCommand) {
-        self.commands.push(command);
+    fn evaluate(&self, values: &HashMap<String, f64>) -> Option<f64> {
+        Some(match self {
+            Expression::Number(number) => *number,
+            Expression::Input(key) => *values.get(key)?,
+            Expression::Negate(operand) => -operand.evaluate(values)?,
+            Expression::Binary(operator, left, right) => {
+                let (a, b) = (left.evaluate(values)?, right.evaluate(values)?);
+                match operator {
+                    '+' => a + b,
+                    '-' => a - b,
+                    '*' => a * b,
+                    '/' => a / b,
+                    '%' => a % b,
+                    _ => a.powf(b),
+                }
+            }
+            Expression::Call(name, arguments) => {
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| argument.evaluate(values))
+                    .collect::<Option<Vec<f64>>>()?;
+                call_function(name, &arguments)
+            }
+        })
+    }
+}
+
+// How many arguments an expression function takes, or `None` if there's no such function
+fn function_arity(name: &str) -> Option<usize> {
+    match name {
+        "abs" | "sqrt" | "ln" | "log10" | "exp" | "round" | "floor" | "ceil" => Some(1),
+        "min" | "max" => Some(2),
+        _ => None,
     }
+}
 
-    fn render(&self, canvas: &mut Canvas, config: &InstrumentConfig) {
-        for command in &self.commands {
-            match command {
-                DrawCommand::Clear(color) => {
-                    canvas.clear(*color);
+fn call_function(name: &str, x: &[f64]) -> f64 {
+    match name {
+        "abs" => x[0].abs(),
+        "sqrt" => x[0].sqrt(),
+        "ln" => x[0].ln(),
+        "log10" => x[0].log10(),
+        "exp" => x[0].exp(),
+        "round" => x[0].round(),
+        "floor" => x[0].floor(),
+        "ceil" => x[0].ceil(),
+        "min" => x[0].min(x[1]),
+        _ => x[0].max(x[1]),
+    }
+}
+
+// Recursive descent over sum := product (('+' | '-') product)*,
+// product := unary (('*' | '/' | '%') unary)*, unary := '-' unary | power,
+// power := atom ('^' unary)?. A leading minus applies after `^`, so -2^2 is -4, while
+// an exponent may still carry its own sign, as in 2^-1.
+struct ExpressionParser<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+}
+
+impl ExpressionParser<'_> {
+    fn next_token(&mut self) -> Option<char> {
+        while self.chars.next_if(|c| c.is_whitespace()).is_some() {}
+        self.chars.peek().copied()
+    }
+
+    fn sum(&mut self) -> Result<Expression, String> {
+        let mut left = self.product()?;
+        while let Some(operator @ ('+' | '-')) = self.next_token() {
+            self.chars.next();
+            left = Expression::Binary(operator, Box::new(left), Box::new(self.product()?));
+        }
+        Ok(left)
+    }
+
+    fn product(&mut self) -> Result<Expression, String> {
+        let mut left = self.unary()?;
+        while let Some(operator @ ('*' | '/' | '%')) = self.next_token() {
+            self.chars.next();
+            left = Expression::Binary(operator, Box::new(left), Box::new(self.unary()?));
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expression, String> {
+        if self.next_token() == Some('-') {
+            self.chars.next();
+            return Ok(Expression::Negate(Box::new(self.unary()?)));
+        }
+        self.power()
+    }
+
+    fn power(&mut self) -> Result<Expression, String> {
+        let base = self.atom()?;
+        if self.next_token() == Some('^') {
+            self.chars.next();
+            // Right associative, so 2^3^2 is 2^9
+            return Ok(Expression::Binary(
+                '^',
+                Box::new(base),
+                Box::new(self.unary()?),
+            ));
+        }
+        Ok(base)
+    }
+
+    fn atom(&mut self) -> Result<Expression, String> {
+        match self.next_token() {
+            Some('(') => {
+                self.chars.next();
+                let inner = self.sum()?;
+                self.expect(')')?;
+                Ok(inner)
+            }
+            Some(c) if c.is_ascii_digit() || c == '.' => {
+                let mut number = String::new();
+                while let Some(c) = self.chars.next_if(|c| c.is_ascii_digit() || *c == '.') {
+                    number.push(c);
                 }
-                DrawCommand::Arc {
-                    cx,
-                    cy,
-                    r,
-                    thickness,
-                    start_angle,
-                    arc_span,
-                    color,
-                } => {
-                    render_arc_immediate(
-                        canvas,
-                        *cx,
-                        *cy,
-                        *r,
-                        *thickness,
-                        *start_angle,
-                        *arc_span,
-                        *color,
-                    );
+                // An exponent, as in 1e-3
+                if let Some(e) = self.chars.next_if(|c| matches!(c, 'e' | 'E')) {
+                    number.push(e);
+                    if let Some(sign) = self.chars.next_if(|c| matches!(c, '+' | '-')) {
+                        number.push(sign);
+                    }
+                    while let Some(c) = self.chars.next_if(char::is_ascii_digit) {
+                        number.push(c);
+                    }
+                }
+                number
+                    .parse()
+                    .map(Expression::Number)
+                    .map_err(|_| format!("bad number {number:?}"))
+            }
+            Some(c) if c.is_alphabetic() || c == '_' => {
+                let mut name = String::new();
+                while let Some(c) = self.chars.next_if(|c| c.is_alphanumeric() || *c == '_') {
+                    name.push(c);
+                }
+                if self.next_token() != Some('(') {
+                    return Ok(Expression::Input(name));
+                }
+                self.chars.next();
+                let mut arguments = vec![self.sum()?];
+                while self.next_token() == Some(',') {
+                    self.chars.next();
+                    arguments.push(self.sum()?);
+                }
+                self.expect(')')?;
+                let arity =
+                    function_arity(&name).ok_or_else(|| format!("unknown function {name:?}"))?;
+                if arguments.len() != arity {
+                    return Err(format!("{name} takes {arity} argument(s)"));
+                }
+                Ok(Expression::Call(name, arguments))
+            }
+            Some(c) => Err(format!("unexpected {c:?}")),
+            None => Err("expression ends early".to_string()),
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        match self.next_token() {
+            Some(c) if c == expected => {
+                self.chars.next();
+                Ok(())
+            }
+            _ => Err(format!("expected {expected:?}")),
+        }
+    }
+}
+
+/// A rhai script turning incoming values into displayed ones, run after calibration
+/// and any `DerivedChannel`s.
+///
+/// Every input seen so far is a variable named by its `SetMany` key, and assigning to
+/// `primary`, `secondary`, `chrono1`, `chrono2` or `readout` sets what's shown, e.g.
+/// `primary = celsius * 1.8 + 32;` or `readout = volts * amps;`. Keys the script leaves
+/// alone pass through. If a run fails, say because an input hasn't arrived yet, the
+/// values pass through unchanged.
+#[cfg(feature = "scripting")]
+#[derive(Clone)]
+pub struct ValueScript {
+    source: String,
+    ast: std::sync::Arc<rhai::AST>,
+}
+
+#[cfg(feature = "scripting")]
+impl std::fmt::Debug for ValueScript {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("ValueScript").field(&self.source).finish()
+    }
+}
+
+#[cfg(feature = "scripting")]
+impl ValueScript {
+    pub fn new(source: &str) -> Result<Self, InstrumentError> {
+        let ast = script_engine()
+            .compile(source)
+            .map_err(|err| InstrumentError::InvalidConfig(format!("value script: {err}")))?;
+        Ok(Self {
+            source: source.to_string(),
+            ast: std::sync::Arc::new(ast),
+        })
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    // Every variable in scope after a run, as a number where it is one
+    fn run(&self, inputs: &HashMap<String, f64>) -> Option<HashMap<String, f64>> {
+        let mut scope = rhai::Scope::new();
+        for (key, value) in inputs {
+            scope.push(key.clone(), *value);
+        }
+        // Declared so scripts can assign them before any value has arrived
+        let displayed = NeedleId::ALL.iter().map(|id| id.name()).chain(["readout"]);
+        for key in displayed.filter(|key| !inputs.contains_key(*key)) {
+            scope.push_dynamic(key, rhai::Dynamic::UNIT);
+        }
+        script_engine()
+            .run_ast_with_scope(&mut scope, &self.ast)
+            .ok()?;
+        let outputs = scope
+            .iter()
+            .filter_map(|(name, _, value)| {
+                let number = value
+                    .as_float()
+                    .ok()
+                    .or_else(|| value.as_int().ok().map(|int| int as f64))?;
+                Some((name.to_string(), number))
+            })
+            .collect();
+        Some(outputs)
+    }
+}
+
+#[cfg(feature = "scripting")]
+fn script_engine() -> &'static rhai::Engine {
+    static ENGINE: OnceLock<rhai::Engine> = OnceLock::new();
+    ENGINE.get_or_init(|| {
+        let mut engine = rhai::Engine::new();
+        // Scripts run on the render thread, so a runaway loop mustn't freeze the gauge
+        engine.set_max_operations(100_000);
+        engine
+    })
+}
+
+// ============================================================================
+// ALERTS
+// ============================================================================
+
+/// Flashes per second of an alert's bezel flash
+const ALERT_FLASH_RATE: f64 = 2.0;
+
+/// Test applied to a needle's commanded value
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlertCondition {
+    Above(f64),
+    Below(f64),
+    /// Below the first bound or above the second
+    Outside(f64, f64),
+}
+
+impl AlertCondition {
+    fn holds(self, value: f64) -> bool {
+        match self {
+            AlertCondition::Above(threshold) => value > threshold,
+            AlertCondition::Below(threshold) => value < threshold,
+            AlertCondition::Outside(low, high) => value < low || value > high,
+        }
+    }
+}
+
+/// A rule evaluated every frame, e.g. "primary above 90 for 5s: flash red and show
+/// OVERTEMP". It fires once its condition has held for `hold` seconds and clears as
+/// soon as the condition stops holding. Rules can also be written as text for
+/// [`parse`](Self::parse), as layout files and the command line do.
+#[derive(Debug, Clone)]
+pub struct AlertRule {
+    pub needle: NeedleId,
+    pub condition: AlertCondition,
+    pub hold: f64,
+    /// Bezel colour, flashed while the alert is active
+    pub flash: Option<Color>,
+    /// Text shown under the pivot while the alert is active
+    pub indicator: Option<String>,
+}
+
+impl AlertRule {
+    pub fn new(needle: NeedleId, condition: AlertCondition) -> Self {
+        Self {
+            needle,
+            condition,
+            hold: 0.0,
+            flash: None,
+            indicator: None,
+        }
+    }
+
+    pub fn above(needle: NeedleId, threshold: f64) -> Self {
+        Self::new(needle, AlertCondition::Above(threshold))
+    }
+
+    pub fn below(needle: NeedleId, threshold: f64) -> Self {
+        Self::new(needle, AlertCondition::Below(threshold))
+    }
+
+    pub fn held_for(mut self, seconds: f64) -> Self {
+        self.hold = seconds;
+        self
+    }
+
+    pub fn flash(mut self, color: Color) -> Self {
+        self.flash = Some(color);
+        self
+    }
+
+    pub fn indicator(mut self, text: impl Into<String>) -> Self {
+        self.indicator = Some(text.into());
+        self
+    }
+
+    /// Reads a rule such as `primary > 90 for 5s: flash red, OVERTEMP`: a needle name,
+    /// then `> value`, `< value` or `outside low..high`, an optional `for <seconds>s`, and
+    /// after a colon any of `flash <colour>` and the indicator text, separated by commas.
+    /// Colours are `#rrggbb` or red, orange, yellow, green, blue or white.
+    pub fn parse(definition: &str) -> Result<Self, InstrumentError> {
+        let invalid = |message: String| {
+            InstrumentError::InvalidConfig(format!("alert {definition:?}: {message}"))
+        };
+        let number = |word: Option<&str>| {
+            word.and_then(|word| word.parse::<f64>().ok())
+                .filter(|number| number.is_finite())
+                .ok_or_else(|| invalid(format!("expected a number, got {:?}", word.unwrap_or(""))))
+        };
+        let (condition, actions) = definition.split_once(':').unwrap_or((definition, ""));
+        let mut words = condition.split_whitespace();
+        let needle = words.next().and_then(NeedleId::from_name).ok_or_else(|| {
+            invalid("expected primary, secondary, chrono1 or chrono2".to_string())
+        })?;
+        let condition = match words.next() {
+            Some(">") => AlertCondition::Above(number(words.next())?),
+            Some("<") => AlertCondition::Below(number(words.next())?),
+            Some("outside") => {
+                let bounds = words.next().unwrap_or("");
+                let (low, high) = bounds
+                    .split_once("..")
+                    .ok_or_else(|| invalid(format!("expected low..high, got {bounds:?}")))?;
+                AlertCondition::Outside(number(Some(low))?, number(Some(high))?)
+            }
+            other => {
+                return Err(invalid(format!(
+                    "expected >, < or outside, got {:?}",
+                    other.unwrap_or("")
+                )))
+            }
+        };
+        let mut rule = Self::new(needle, condition);
+        match (words.next(), words.next(), words.next()) {
+            (None, _, _) => {}
+            (Some("for"), Some(hold), None) => {
+                rule.hold = number(Some(hold.strip_suffix('s').unwrap_or(hold)))?.max(0.0)
+            }
+            _ => {
+                return Err(invalid(
+                    "expected `for <seconds>s` after the condition".to_string(),
+                ))
+            }
+        }
+        for action in actions
+            .split(',')
+            .map(str::trim)
+            .filter(|action| !action.is_empty())
+        {
+            match action.strip_prefix("flash ") {
+                Some(color) => {
+                    let color = color.trim();
+                    rule.flash = Some(
+                        parse_color(color)
+                            .ok_or_else(|| invalid(format!("unknown colour {color:?}")))?,
+                    );
+                }
+                None => rule.indicator = Some(action.to_string()),
+            }
+        }
+        Ok(rule)
+    }
+}
+
+/// The text [`AlertRule::parse`] reads
+impl std::fmt::Display for AlertRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ", self.needle.name())?;
+        match self.condition {
+            AlertCondition::Above(threshold) => write!(f, "> {threshold}")?,
+            AlertCondition::Below(threshold) => write!(f, "< {threshold}")?,
+            AlertCondition::Outside(low, high) => write!(f, "outside {low}..{high}")?,
+        }
+        if self.hold > 0.0 {
+            write!(f, " for {}s", self.hold)?;
+        }
+        let flash = self.flash.map(|color| {
+            let (r, g, b) = color.as_tuple();
+            format!("flash #{r:02x}{g:02x}{b:02x}")
+        });
+        let actions: Vec<String> = flash.into_iter().chain(self.indicator.clone()).collect();
+        if !actions.is_empty() {
+            write!(f, ": {}", actions.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+// `#rrggbb` or one of a few colour names
+fn parse_color(text: &str) -> Option<Color> {
+    let hex = match text {
+        "red" => 0xff0000,
+        "orange" => 0xff8000,
+        "yellow" => 0xffff00,
+        "green" => 0x00ff00,
+        "blue" => 0x0000ff,
+        "white" => 0xffffff,
+        _ => text
+            .strip_prefix('#')
+            .filter(|hex| hex.len() == 6)
+            .and_then(|hex| u32::from_str_radix(hex, 16).ok())?,
+    };
+    Some(Color::new((hex >> 16) as u8, (hex >> 8) as u8, hex as u8))
+}
+
+// ============================================================================
+// STATE PERSISTENCE
+// ============================================================================
+
+/// How often the framebuffer loop, which only ends when the process is killed, saves
+/// `InstrumentConfig::state_file`
+const STATE_SAVE_INTERVAL: Duration = Duration::from_secs(30);
+
+impl AppState {
+    // `key=value` lines, keyed like `SetMany` plus the tell-tales and the total
+    fn save_state(&self, config: &InstrumentConfig) {
+        use std::fmt::Write;
+        let Some(ref path) = config.state_file else {
+            return;
+        };
+        let mut out = String::new();
+        for id in NeedleId::ALL {
+            if let Some(needle) = self.needle(id) {
+                let _ = writeln!(out, "{}={}", id.name(), needle.target_value);
+            }
+        }
+        if let Some(readout) = self.readout_value {
+            let _ = writeln!(out, "readout={readout}");
+        }
+        if let Some((low, high)) = self.tell_tales {
+            let _ = writeln!(out, "telltalelow={low}\ntelltalehigh={high}");
+        }
+        let _ = writeln!(out, "total={}", self.totalizer.total);
+        // Runs as the display shuts down, with nobody left to report a failure to
+        let _ = write_atomically(path, &out);
+    }
+
+    // Missing files and unknown or malformed lines are skipped
+    fn restore_state(&mut self, config: &InstrumentConfig) {
+        let Some(text) = config
+            .state_file
+            .as_ref()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+        else {
+            return;
+        };
+        let values: HashMap<&str, f64> = text
+            .lines()
+            .filter_map(|line| line.split_once('='))
+            .filter_map(|(key, value)| Some((key.trim(), value.trim().parse().ok()?)))
+            .collect();
+        for id in NeedleId::ALL {
+            if let Some(&value) = values.get(id.name()) {
+                self.set_needle_value(id, value);
+            }
+        }
+        if let Some(&readout) = values.get("readout") {
+            self.set_readout_value(readout);
+        }
+        // Overrides the range seen while restoring the primary needle above
+        self.tell_tales = values
+            .get("telltalelow")
+            .zip(values.get("telltalehigh"))
+            .map(|(&low, &high)| (low, high));
+        // A dedicated totalizer file is saved more often, so it wins
+        if config.totalizer_file.is_none() {
+            if let Some(&total) = values.get("total") {
+                self.totalizer.total = total;
+            }
+        }
+        self.settle();
+    }
+}
+
+// ============================================================================
+// VALUE HISTORY
+// ============================================================================
+
+/// Document format for `InstrumentCommand::ExportHistory`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HistoryFormat {
+    /// `time,key,value` rows under a header line
+    #[default]
+    Csv,
+    /// An array of `{"time", "key", "value"}` objects
+    Json,
+}
+
+// One commanded value; `time` is seconds since the gauge started
+struct HistorySample {
+    time: f64,
+    key: &'static str,
+    value: f64,
+}
+
+fn format_history<'a>(
+    samples: impl Iterator<Item = &'a HistorySample>,
+    format: HistoryFormat,
+) -> String {
+    use std::fmt::Write;
+    let mut out = String::new();
+    match format {
+        HistoryFormat::Csv => {
+            out.push_str("time,key,value\n");
+            for sample in samples {
+                let _ = writeln!(out, "{:.3},{},{}", sample.time, sample.key, sample.value);
+            }
+        }
+        HistoryFormat::Json => {
+            out.push('[');
+            for (i, sample) in samples.enumerate() {
+                // JSON has no NaN or infinity
+                let value = if sample.value.is_finite() {
+                    sample.value.to_string()
+                } else {
+                    "null".to_string()
+                };
+                let separator = if i == 0 { "" } else { "," };
+                let _ = write!(
+                    out,
+                    "{separator}{{\"time\":{:.3},\"key\":\"{}\",\"value\":{value}}}",
+                    sample.time, sample.key
+                );
+            }
+            out.push_str("]\n");
+        }
+    }
+    out
+}
+
+// ============================================================================
+// PUBLIC API - MAIN INTERFACE
+// ============================================================================
+
+/// Addresses one needle, by position in `NeedleId::ALL` or by its `SetMany` key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NeedleId {
+    Primary,
+    Secondary,
+    Chronograph,
+    SecondaryChronograph,
+}
+
+impl NeedleId {
+    pub const ALL: [NeedleId; 4] = [
+        NeedleId::Primary,
+        NeedleId::Secondary,
+        NeedleId::Chronograph,
+        NeedleId::SecondaryChronograph,
+    ];
+
+    pub fn from_index(index: usize) -> Option<Self> {
+        Self::ALL.get(index).copied()
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|id| id.name() == name)
+    }
+
+    /// The key used for this needle by `InstrumentCommand::SetMany` and the stdin protocol
+    pub fn name(self) -> &'static str {
+        match self {
+            NeedleId::Primary => "primary",
+            NeedleId::Secondary => "secondary",
+            NeedleId::Chronograph => "chrono1",
+            NeedleId::SecondaryChronograph => "chrono2",
+        }
+    }
+}
+
+/// How a needle draws attention to itself, at `InstrumentConfig::needle_blink_rate`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NeedleBlink {
+    /// Shown for the first half of each cycle and hidden for the second
+    Blink,
+    /// Fades smoothly down to a fifth of its opacity and back
+    Pulse,
+}
+
+/// Outline of the main needles
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NeedleShape {
+    /// A stroke tapering to the tip, with a straight counterweight behind the pivot
+    #[default]
+    Line,
+    /// A filled blade, widest at the pivot and pointed at both ends
+    Sword,
+    /// A straight stroke ending in a filled arrowhead
+    Arrow,
+}
+
+/// Command enum for type-safe instrument updates
+#[derive(Debug, Clone)]
+pub enum InstrumentCommand {
+    SetPrimaryNeedle(f64),
+    SetSecondaryNeedle(f64),
+    SetChronograph(f64),
+    SetSecondaryChronograph(f64),
+    SetReadout(f64),
+    SetHighlightBounds(f64, f64),
+    ClearHighlightBounds,
+    SetHighlightVisible(bool), // hide the band temporarily; its bounds are kept
+    SetRange(f64, f64),        // min, max
+    StartChronograph,          // run the built-in stopwatch on the chronograph dial
+    StopChronograph,
+    ResetChronograph,
+    SetBrightness(f32), // 0.0 (black) to 1.0 (full), scales the whole frame
+    SetLayerVisible(Layer, bool),
+    SetLayerOrder(Vec<Layer>), // back to front; unlisted layers keep their default slot
+    HoldReadout(bool),         // freeze the readout at its current value; needles stay live
+    PlayAnimation(Animation),  // replaces any animation already playing
+    StopAnimation,             // targets keep their current values
+    SetBothNeedles(f64, f64),  // primary, secondary
+    SetAllNeedles(f64, f64, f64, f64), // primary, secondary, chronograph, secondary_chronograph
+    SetBothChronographs(f64, f64), // chronograph, secondary_chronograph
+    ClearSecondaryNeedle,      // remove the needle until it is set again
+    ClearChronograph,          // also stops the built-in stopwatch
+    ClearSecondaryChronograph,
+    ClearReadout, // hides the readout box, releasing any hold
+    /// Sets every value named by a key, as in the stdin protocol: "primary",
+    /// "secondary", "chrono1", "chrono2" and "readout". Unknown keys are ignored.
+    SetMany(HashMap<String, f64>),
+    /// Sets a single needle; the general form of the per-needle variants above
+    SetNeedle {
+        id: NeedleId,
+        value: f64,
+    },
+    /// Marks a point in the command stream. Once a frame showing every command sent
+    /// before it has been presented, the id is passed to `InstrumentConfig::sequence_hook`
+    Sequence(u64),
+    /// Adds to the totalizer; negative amounts count down and non-finite ones are ignored
+    AddToTotal(f64),
+    ResetTotal,
+    ResetTellTales,
+    /// Replaces the calibration for one input, keyed as in `SetMany`;
+    /// `Calibration::default()` removes the correction
+    SetCalibration(String, Calibration),
+    /// Sends the recorded value history (see `InstrumentConfig::history_duration`) to
+    /// the given channel, keyed as in `SetMany`
+    ExportHistory(HistoryFormat, std::sync::mpsc::Sender<String>),
+    /// Replaces the zones, matched to the current ones by position; see
+    /// `InstrumentConfig::zone_transition_duration`
+    SetZones(Vec<Zone>),
+    /// Shows the message over the dial, e.g. when its data source has died, or clears it
+    /// with `None`. The needles keep their last values underneath.
+    SetFailure(Option<String>),
+    /// Blinks or pulses one needle, e.g. while its data comes from a fallback, or with
+    /// `None` draws it normally again
+    SetNeedleBlink(NeedleId, Option<NeedleBlink>),
+    /// Puts a target marker on the main dial's rim at a value on the primary scale, as a
+    /// heading or airspeed bug, or removes it with `None`
+    SetTargetBug(Option<f64>),
+}
+
+/// Main instrument struct - the primary public interface
+#[derive(Debug, Clone)]
+pub struct Instrument {
+    config: InstrumentConfig,
+    state: InstrumentState,
+}
+
+#[derive(Debug, Clone, Builder)]
+pub struct InstrumentConfig {
+    #[builder(default = "".to_string())]
+    pub title: String,
+    #[builder(default = (0.0, 100.0))]
+    pub range: (f64, f64),
+    pub highlight_band: Option<(f64, f64, Color)>,
+
+    // Window configuration
+    #[builder(default = 300)]
+    pub window_width: usize,
+    #[builder(default = 300)]
+    pub window_height: usize,
+    #[builder(default = 60.0)]
+    pub max_framerate: f64,
+    /// Advance animation time by exactly this many seconds per frame instead of reading
+    /// the clock, so the same commands always render the same frames
+    pub fixed_timestep: Option<f64>,
+    /// Seeds the random walk of `Instrument::show_demo`, which then repeats exactly
+    pub demo_seed: Option<u64>,
+    /// No title bar or border, and kept above other windows, so a small gauge can float
+    /// over a game or simulator as a HUD. Drag the dial to move it.
+    #[builder(default = false)]
+    pub borderless: bool,
+    /// Let mouse input fall through to whatever is under the window, where the platform
+    /// supports it. The window can't be dragged then.
+    #[builder(default = false)]
+    pub click_through: bool,
+    /// Show frame rate, render time and per-command cost in the corner; F3 toggles it
+    #[builder(default = false)]
+    pub debug_overlay: bool,
+    /// Flip the dial geometry left-to-right; text stays readable
+    #[builder(default = false)]
+    pub mirror_horizontal: bool,
+    #[builder(default)]
+    pub rotation: Rotation,
+    /// Draw non-square windows as the largest centred square instead of stretching the layout
+    #[builder(default = true)]
+    pub letterbox: bool,
+    #[builder(default = Color::new(0xff, 0xff, 0xff))]
+    pub letterbox_color: Color,
+
+    // Burn-in protection
+    /// Maximum pixel offset of the slow orbit applied to the whole frame; 0 disables
+    #[builder(default = 0)]
+    pub burn_in_shift: i32,
+    /// Seconds for one full orbit of the burn-in shift
+    #[builder(default = 600.0)]
+    pub burn_in_shift_period: f64,
+    /// Invert all colours for every other period of this many seconds
+    pub burn_in_invert_period: Option<f64>,
+
+    // Main dial configuration
+    #[builder(default = 45)]
+    pub dial_margin: i32,
+    #[builder(default = 4)]
+    pub dial_thickness: i32,
+    /// Moves the needle pivot, and the scale drawn around it, off the canvas centre
+    /// (x right, y down) for eccentric gauges. The radius still comes from `dial_margin`.
+    #[builder(default = (0, 0))]
+    pub pivot_offset: (i32, i32),
+    /// Sweep of the main scale in radians
+    #[builder(default = std::f64::consts::PI * 1.5)]
+    pub dial_arc_span: f64,
+    /// Where the main scale starts, in radians clockwise from 3 o'clock
+    #[builder(default = std::f64::consts::FRAC_PI_2)]
+    pub dial_start_angle: f64,
+    /// Treats the main scale as a full circle, as on a compass card: values past either
+    /// end wrap round and the main needles take the short way across the join
+    #[builder(default)]
+    pub wrap_around: bool,
+    #[builder(default = 30.0)]
+    pub dial_numbers_font_size: f32,
+    #[builder(default = 30.0)]
+    pub dial_ticks_to_numbers_distance: f64,
+
+    // Tick configuration
+    #[builder(default = 11)]
+    pub ticks_count: usize,
+    #[builder(default = 5)]
+    pub minor_ticks_per_interval: usize,
+    #[builder(default = 40)]
+    pub major_tick_length: i32,
+    #[builder(default = 25)]
+    pub minor_tick_length: i32,
+    /// `Outside` grows the ticks outward from the arc and puts their labels beyond them
+    #[builder(default)]
+    pub tick_placement: ScalePlacement,
+    /// Compresses part of the main scale; ticks inside the break are dropped
+    pub scale_break: Option<ScaleBreak>,
+    #[builder(default = 2.0)]
+    pub major_tick_thickness: f32,
+    #[builder(default = 0.5)]
+    pub minor_tick_thickness: f32,
+    /// Stroke patterns for the main dial's ring and ticks
+    #[builder(default)]
+    pub dial_line_style: LineStyle,
+    #[builder(default)]
+    pub major_tick_style: LineStyle,
+    #[builder(default)]
+    pub minor_tick_style: LineStyle,
+    /// Applies to generated tick labels, the converted scale and the readout
+    #[builder(default)]
+    pub number_format: NumberFormat,
+    /// Marks positive main scale labels with a plus sign, for scales centred on zero
+    #[builder(default)]
+    pub signed_labels: bool,
+    /// Label only every Nth major tick, counting from the first
+    #[builder(default = 1)]
+    pub label_every: usize,
+    /// Widen the label stride further until neighbouring labels no longer overlap
+    #[builder(default = false)]
+    pub label_decimation: bool,
+    /// Label minor ticks too, in a smaller font; dropped entirely if they would overlap
+    #[builder(default = false)]
+    pub minor_tick_labels: bool,
+    #[builder(default = 16.0)]
+    pub minor_tick_labels_font_size: f32,
+    /// Pick major ticks at "nice" 1/2/5×10ⁿ steps instead of `ticks_count` even divisions
+    #[builder(default = false)]
+    pub auto_ticks: bool,
+    /// Approximate number of intervals the auto-tick mode aims for
+    #[builder(default = 10)]
+    pub auto_ticks_target: usize,
+    /// Explicit major ticks; takes precedence over both `auto_ticks` and `ticks_count`
+    pub custom_ticks: Option<Vec<Tick>>,
+    #[builder(default)]
+    pub scale_icons: Vec<ScaleIcon>,
+
+    // Secondary scale configuration
+    /// Adds a second ring of labels in units of `value * factor + offset`,
+    /// e.g. 1.609 for mph to km/h or 1.8 with an offset of 32 for °C to °F
+    pub secondary_scale_factor: Option<f64>,
+    #[builder(default = 0.0)]
+    pub secondary_scale_offset: f64,
+    #[builder(default)]
+    pub secondary_scale_placement: ScalePlacement,
+    #[builder(default = Color::new(0x00, 0x7f, 0xff))]
+    pub secondary_scale_color: Color,
+    #[builder(default = 20.0)]
+    pub secondary_scale_font_size: f32,
+    /// Gap between the secondary labels and the primary labels (inside) or the arc (outside)
+    #[builder(default = 25.0)]
+    pub secondary_scale_distance: f64,
+    /// Approximate number of intervals between converted labels
+    #[builder(default = 10)]
+    pub secondary_scale_ticks_target: usize,
+    /// Fixed decimals for converted labels; by default just enough to tell them apart
+    pub secondary_scale_decimals: Option<usize>,
+    /// Gives the secondary needle its own scale, spread over the same arc as `range` and
+    /// labelled in the secondary scale's ring. Takes precedence over `secondary_scale_factor`.
+    pub secondary_range: Option<(f64, f64)>,
+
+    // Needle configuration
+    #[builder(default = 1.05)]
+    pub needle_length_factor: f64,
+    #[builder(default = 80.0)]
+    pub needle_back_length: f64,
+    #[builder(default = 4.0)]
+    pub needle_width: f32,
+    /// Colour of the outer end of the main needles, as on speedometer and tachometer pointers
+    pub needle_tip_color: Option<Color>,
+    /// Share of the needle length drawn in `needle_tip_color`
+    #[builder(default = 0.2)]
+    pub needle_tip_fraction: f64,
+    /// Stroke width of the coloured tip; falls back to `needle_width`
+    pub needle_tip_width: Option<f32>,
+    /// Outline of the main needles; chronograph needles are always lines
+    #[builder(default)]
+    pub needle_shape: NeedleShape,
+    /// Fill of the marker placed by `InstrumentCommand::SetTargetBug`
+    #[builder(default = Color::new(0xff, 0x80, 0x00))]
+    pub target_bug_color: Color,
+    /// Width of the target bug along the rim; it reaches in from the rim 3/4 as far
+    #[builder(default = 24)]
+    pub target_bug_size: i32,
+    #[builder(default = 0.1)]
+    pub needle_lerp_factor: f64,
+    /// Smoothing factor for highlight band changes; falls back to `needle_lerp_factor`
+    pub highlight_lerp_factor: Option<f64>,
+    /// Seconds taken to glide between scales when the range changes; 0 snaps
+    #[builder(default = 0.5)]
+    pub range_transition_duration: f64,
+    #[builder(default)]
+    pub needle_motion: NeedleMotion,
+    #[builder(default)]
+    pub secondary_needle_motion: NeedleMotion,
+    #[builder(default)]
+    pub chronograph_needle_motion: NeedleMotion,
+    #[builder(default)]
+    pub secondary_chronograph_needle_motion: NeedleMotion,
+    /// Fastest the needle may travel, in scale units per second
+    pub needle_max_rate: Option<f64>,
+    pub secondary_needle_max_rate: Option<f64>,
+    pub chronograph_needle_max_rate: Option<f64>,
+    pub secondary_chronograph_needle_max_rate: Option<f64>,
+    /// Smoothing factor while the value rises; falls back to `needle_lerp_factor`
+    pub needle_attack: Option<f64>,
+    /// Smoothing factor while the value falls; falls back to `needle_lerp_factor`
+    pub needle_release: Option<f64>,
+    pub secondary_needle_attack: Option<f64>,
+    pub secondary_needle_release: Option<f64>,
+    pub chronograph_needle_attack: Option<f64>,
+    pub chronograph_needle_release: Option<f64>,
+    pub secondary_chronograph_needle_attack: Option<f64>,
+    pub secondary_chronograph_needle_release: Option<f64>,
+    /// Seconds a quartz step takes to jump and settle
+    #[builder(default = 0.15)]
+    pub quartz_step_duration: f64,
+    /// Sweep every needle to full scale and back when the window opens, then settle on
+    /// the live values
+    #[builder(default = false)]
+    pub power_on_sweep: bool,
+    #[builder(default = 2.0)]
+    pub power_on_sweep_duration: f64,
+
+    // Chronograph configuration
+    #[builder(default = (0.0, 60.0))]
+    pub chronograph_range: (f64, f64),
+    #[builder(default = 130)]
+    pub chronograph_dial_shift: i32,
+    #[builder(default = 7.0)]
+    pub chronograph_dial_size: f64,
+    #[builder(default = 5)]
+    pub chronograph_ticks_count: usize,
+    #[builder(default = 10)]
+    pub chronograph_tick_length: i32,
+    #[builder(default)]
+    pub chronograph_tick_placement: ScalePlacement,
+    #[builder(default = 15)]
+    pub chronograph_dial_margin: i32,
+    #[builder(default = 2)]
+    pub chronograph_dial_thickness: i32,
+    /// Sweep of the subdial's scale in radians, e.g. 120° for a fan-shaped register
+    #[builder(default = std::f64::consts::PI * 1.5)]
+    pub chronograph_arc_span: f64,
+    /// Where the scale starts, in radians clockwise from 3 o'clock
+    #[builder(default = std::f64::consts::FRAC_PI_2)]
+    pub chronograph_start_angle: f64,
+    #[builder(default = 1.0)]
+    pub chronograph_needle_length_factor: f64,
+    #[builder(default = 4.0)]
+    pub chronograph_needle_width: f32,
+    #[builder(default = 30.0)]
+    pub chronograph_needle_back_length: f64,
+    #[builder(default = 30.0)]
+    pub chronograph_dial_numbers_font_size: f32,
+    #[builder(default = 30.0)]
+    pub chronograph_dial_ticks_to_numbers_distance: f64,
+    #[builder(default = 8)]
+    pub chronograph_dial_dot_radius: i32,
+    #[builder(default = 0)]
+    pub chronograph_minor_ticks_per_interval: usize,
+    #[builder(default = 4)]
+    pub chronograph_minor_tick_length: i32,
+    #[builder(default = 2.0)]
+    pub chronograph_major_tick_thickness: f32,
+    #[builder(default = 0.5)]
+    pub chronograph_minor_tick_thickness: f32,
+    #[builder(default)]
+    pub chronograph_sweep: ChronographSweep,
+    /// Formats the subdial's tick labels, e.g. as minutes or hours; whole numbers if unset
+    pub chronograph_label_formatter: Option<LabelFormatter>,
+    /// Label only every Nth tick, counting from the first
+    #[builder(default = 1)]
+    pub chronograph_label_every: usize,
+    #[builder(default = true)]
+    pub chronograph_labels: bool,
+    /// Marks the highest subdial value of the last this many seconds, e.g. a gust peak
+    pub chronograph_peak_hold: Option<f64>,
+
+    // Secondary Chronograph configuration
+    #[builder(default = (0.0, 60.0))]
+    pub secondary_chronograph_range: (f64, f64),
+    #[builder(default = 90)]
+    pub secondary_chronograph_dial_shift: i32,
+    #[builder(default = 7.0)]
+    pub secondary_chronograph_dial_size: f64,
+    #[builder(default = 5)]
+    pub secondary_chronograph_ticks_count: usize,
+    #[builder(default = 10)]
+    pub secondary_chronograph_tick_length: i32,
+    #[builder(default)]
+    pub secondary_chronograph_tick_placement: ScalePlacement,
+    #[builder(default = 15)]
+    pub secondary_chronograph_dial_margin: i32,
+    #[builder(default = 2)]
+    pub secondary_chronograph_dial_thickness: i32,
+    #[builder(default = std::f64::consts::PI * 1.5)]
+    pub secondary_chronograph_arc_span: f64,
+    #[builder(default = std::f64::consts::FRAC_PI_2)]
+    pub secondary_chronograph_start_angle: f64,
+    #[builder(default = 1.0)]
+    pub secondary_chronograph_needle_length_factor: f64,
+    #[builder(default = 4.0)]
+    pub secondary_chronograph_needle_width: f32,
+    #[builder(default = 30.0)]
+    pub secondary_chronograph_needle_back_length: f64,
+    #[builder(default = 30.0)]
+    pub secondary_chronograph_dial_numbers_font_size: f32,
+    #[builder(default = 30.0)]
+    pub secondary_chronograph_dial_ticks_to_numbers_distance: f64,
+    #[builder(default = 8)]
+    pub secondary_chronograph_dial_dot_radius: i32,
+    #[builder(default = 0)]
+    pub secondary_chronograph_minor_ticks_per_interval: usize,
+    #[builder(default = 4)]
+    pub secondary_chronograph_minor_tick_length: i32,
+    #[builder(default = 2.0)]
+    pub secondary_chronograph_major_tick_thickness: f32,
+    #[builder(default = 0.5)]
+    pub secondary_chronograph_minor_tick_thickness: f32,
+    pub secondary_chronograph_label_formatter: Option<LabelFormatter>,
+    #[builder(default = 1)]
+    pub secondary_chronograph_label_every: usize,
+    #[builder(default = true)]
+    pub secondary_chronograph_labels: bool,
+
+    // Readout configuration
+    /// Anchor point of the readout box as a fraction of the window size
+    #[builder(default = 0.69)]
+    pub readout_x_factor: f64,
+    #[builder(default = 0.75)]
+    pub readout_y_factor: f64,
+    #[builder(default)]
+    pub readout_horizontal_anchor: HorizontalAnchor,
+    #[builder(default)]
+    pub readout_vertical_anchor: VerticalAnchor,
+    #[builder(default)]
+    pub readout_alignment: ReadoutAlignment,
+    /// Integer digits the readout box is sized for before it has to grow
+    #[builder(default = 3)]
+    pub readout_integer_digits: usize,
+    #[builder(default = 54.0)]
+    pub readout_big_font_size: f32,
+    #[builder(default = 28.0)]
+    pub readout_small_font_size: f32,
+    #[builder(default = 8)]
+    pub readout_box_padding: i32,
+    #[builder(default = 4.0)]
+    pub readout_box_thickness: f32,
+    /// Rounds the readout box's corners; 0 leaves them square
+    #[builder(default = 0.0)]
+    pub readout_box_corner_radius: f64,
+    /// Seconds between readout refreshes, like a digital meter's sample rate; the
+    /// needles keep moving every frame. 0 refreshes every frame.
+    #[builder(default = 0.0)]
+    pub readout_update_interval: f64,
+
+    // Totalizer configuration
+    /// Show an odometer-style running total fed by `InstrumentCommand::AddToTotal`
+    #[builder(default = false)]
+    pub totalizer: bool,
+    /// Whole digits shown; like a mechanical counter it rolls over past them
+    #[builder(default = 6)]
+    pub totalizer_digits: usize,
+    #[builder(default = 1)]
+    pub totalizer_decimals: usize,
+    /// Centre of the counter as a fraction of the window size
+    #[builder(default = 0.5)]
+    pub totalizer_x_factor: f64,
+    #[builder(default = 0.62)]
+    pub totalizer_y_factor: f64,
+    #[builder(default = 24.0)]
+    pub totalizer_font_size: f32,
+    /// Loaded at startup and written back as the total changes, so it survives restarts
+    pub totalizer_file: Option<std::path::PathBuf>,
+
+    // Layout configuration
+    /// Nudge or shrink the readout and subdials so they don't overlap each other,
+    /// tick labels or curved text
+    #[builder(default = false)]
+    pub layout_collision_avoidance: bool,
+    #[builder(default)]
+    pub layout_priorities: LayoutPriorities,
+    /// Hide the readout when the shorter window side is below this many pixels
+    pub readout_min_size: Option<usize>,
+    /// Hide the chronograph subdial when the shorter window side is below this many pixels
+    pub chronograph_min_size: Option<usize>,
+    /// Hide the secondary chronograph subdial when the shorter window side is below this many pixels
+    pub secondary_chronograph_min_size: Option<usize>,
+
+    // Curved text configuration
+    #[builder(default = "".to_string())]
+    pub curved_text: String,
+    #[builder(default = 30.0)]
+    pub curved_text_font_size: f32,
+    #[builder(default = 15.0)]
+    pub curved_text_radius_offset: f64,
+    #[builder(default = std::f64::consts::PI * 0.23)]
+    pub curved_text_arc_span: f64,
+    #[builder(default = 3.0 * std::f64::consts::PI / 2.0)]
+    pub curved_text_angle: f64,
+    #[builder(default = 0.0)]
+    pub curved_text_letter_spacing: f64,
+    #[builder(default)]
+    pub curved_text_fit: CurvedTextFit,
+    #[builder(default = "".to_string())]
+    pub bottom_curved_text: String,
+    #[builder(default = 20.0)]
+    pub bottom_curved_text_font_size: f32,
+    #[builder(default = -60.0)]
+    pub bottom_curved_text_radius_offset: f64,
+    #[builder(default = std::f64::consts::PI * 0.23)]
+    pub bottom_curved_text_arc_span: f64,
+    #[builder(default = std::f64::consts::PI / 2.0)]
+    pub bottom_curved_text_angle: f64,
+    #[builder(default = 0.0)]
+    pub bottom_curved_text_letter_spacing: f64,
+    #[builder(default)]
+    pub bottom_curved_text_fit: CurvedTextFit,
+
+    // Dial text block
+    /// Text printed level inside the dial, a line per `\n`, e.g. "OIL PRESSURE\nPSI"
+    #[builder(default = "".to_string())]
+    pub dial_text: String,
+    #[builder(default = 26.0)]
+    pub dial_text_font_size: f32,
+    /// Size of the lines after the first
+    #[builder(default = 18.0)]
+    pub dial_text_subtitle_font_size: f32,
+    /// Centre of the first line from the dial's centre, as fractions of its radius;
+    /// negative `y` is above
+    #[builder(default = (0.0, -0.4))]
+    pub dial_text_position: (f64, f64),
+
+    // Labels
+    #[builder(default = "Primary".to_string())]
+    pub primary_label: String,
+    #[builder(default = "Secondary".to_string())]
+    pub secondary_label: String,
+
+    // Zone configuration
+    /// Operating ranges on the main scale, later zones on top of earlier ones
+    #[builder(default)]
+    pub zones: Vec<Zone>,
+    /// Draws each zone as a band just inside the dial ring
+    #[builder(default = true)]
+    pub zone_bands: bool,
+    #[builder(default = 10)]
+    pub zone_band_width: i32,
+    /// Ends of the zone bands; round caps reach half a band width past the zone bounds
+    #[builder(default)]
+    pub zone_band_cap: ArcCap,
+    /// Colours the ticks and labels inside each zone, which keeps the zones readable
+    /// when their bands are off
+    #[builder(default = true)]
+    pub zone_ticks: bool,
+    /// Seconds zones and the highlight band take to glide to new bounds, and to fade in
+    /// or out, when changed by command; 0 snaps zones and leaves the highlight band to
+    /// `highlight_lerp_factor`
+    #[builder(default = 0.0)]
+    pub zone_transition_duration: f64,
+
+    // Highlight band configuration
+    #[builder(default = 20)]
+    pub highlight_band_width: i32,
+    #[builder(default = Color::new(0xff, 0x00, 0x00))]
+    pub highlight_band_color: Color,
+    #[builder(default = 1.0)]
+    pub highlight_band_alpha: f64,
+    #[builder(default = 0.005)]
+    pub highlight_band_edge_softness: f64,
+
+    // Colors
+    pub background_color: Option<Color>,
+    pub text_color: Option<Color>,
+    pub needle_color: Option<Color>,
+    pub dial_color: Option<Color>,
+
+    // Font configuration
+    #[builder(default = include_bytes!("BerkeleyMono-Regular.otf"))]
+    pub font_data: &'static [u8],
+    /// Fonts tried in order for text runs the main font has no glyphs for (e.g. CJK)
+    #[builder(default)]
+    pub fallback_fonts: Vec<&'static [u8]>,
+    #[builder(default = 50.0)]
+    pub exclamation_mark_size: f32,
+    /// Corrections for incoming values, keyed as in `InstrumentCommand::SetMany`
+    #[builder(default)]
+    pub calibrations: HashMap<String, Calibration>,
+    /// Evaluated in order whenever values arrive; see `DerivedChannel`
+    #[builder(default)]
+    pub derived_channels: Vec<DerivedChannel>,
+    /// Computes displayed values from the calibrated inputs; see `ValueScript`
+    #[cfg(feature = "scripting")]
+    pub value_script: Option<ValueScript>,
+    /// Changes to the main needles or the readout smaller than this, in scale units,
+    /// are ignored so noisy sensors don't churn the last digit
+    #[builder(default = 0.0)]
+    pub dead_band: f64,
+    /// Evaluated every frame against the commanded needle values; see
+    /// [`AlertRule::parse`] for the text form used by layout files and `--alert`
+    #[builder(default)]
+    pub alert_rules: Vec<AlertRule>,
+    /// Seconds of commanded values kept for `InstrumentCommand::ExportHistory`; 0 keeps none
+    #[builder(default = 300.0)]
+    pub history_duration: f64,
+    #[builder(default = 30.0)]
+    pub alert_indicator_font_size: f32,
+    /// Mark the lowest and highest primary values seen since the last
+    /// `InstrumentCommand::ResetTellTales`, which also clears the subdial peak
+    #[builder(default = false)]
+    pub tell_tales: bool,
+    /// Last values, tell-tales and the total are restored from here when a window or
+    /// framebuffer opens and saved back when it closes
+    pub state_file: Option<std::path::PathBuf>,
+    #[builder(default = 6)]
+    pub dot_radius: i32,
+    /// Draws one hub cap over the main needles' pivot in place of their individual dots
+    pub hub_radius: Option<i32>,
+    #[builder(default = Color::new(0x00, 0x00, 0x00))]
+    pub hub_color: Color,
+    /// Outline around the hub cap, `hub_ring_width` wide
+    pub hub_ring_color: Option<Color>,
+    #[builder(default = 2.0)]
+    pub hub_ring_width: f32,
+    /// Drawn over the hub cap, scaled to its diameter
+    pub hub_image: Option<std::sync::Arc<Bitmap>>,
+    /// Branding drawn on the dial face, by default under 12 o'clock as on a watch
+    pub logo: Option<std::sync::Arc<Bitmap>>,
+    /// Logo height as a fraction of the dial radius; the width keeps its aspect ratio
+    #[builder(default = 0.2)]
+    pub logo_size: f64,
+    /// Centre of the logo from the dial's centre, as fractions of its radius; negative
+    /// `y` is above
+    #[builder(default = (0.0, -0.55))]
+    pub logo_position: (f64, f64),
+
+    /// Multiplies every pixel dimension (margins, lengths, widths, font sizes, offsets)
+    #[builder(default = 1.0)]
+    pub ui_scale: f64,
+
+    // Layers
+    /// Back-to-front draw order; unlisted layers follow in their default position
+    #[builder(default = Layer::ALL.to_vec())]
+    pub layer_order: Vec<Layer>,
+    #[builder(default)]
+    pub hidden_layers: Vec<Layer>,
+
+    // Opacity of parts of the main gauge, from 0.0 (invisible) to 1.0 (opaque)
+    /// The main dial's ring
+    #[builder(default = 1.0)]
+    pub dial_opacity: f64,
+    /// Main dial ticks and their labels, including any tinted by zones
+    #[builder(default = 1.0)]
+    pub tick_opacity: f64,
+    #[builder(default = 1.0)]
+    pub needle_opacity: f64,
+    /// Lets the secondary needle sit back behind the primary
+    #[builder(default = 1.0)]
+    pub secondary_needle_opacity: f64,
+    /// Cycles per second of `InstrumentCommand::SetNeedleBlink`
+    #[builder(default = 2.0)]
+    pub needle_blink_rate: f64,
+    /// The readout box and totalizer
+    #[builder(default = 1.0)]
+    pub readout_opacity: f64,
+    /// Zone bands
+    #[builder(default = 1.0)]
+    pub zone_opacity: f64,
+    /// Extra draw commands added every frame
+    pub custom_layer: Option<CustomLayer>,
+    /// Layer the custom commands are drawn in, after that layer's own commands
+    #[builder(default = Layer::Overlay)]
+    pub custom_layer_z: Layer,
+    /// Called with each finished RGBA frame before it is presented
+    pub frame_hook: Option<FrameHook>,
+    /// Called with the id of the latest `InstrumentCommand::Sequence` once it is on screen
+    pub sequence_hook: Option<SequenceHook>,
+    /// Called with the timings of every frame once it is on screen
+    pub frame_timing_hook: Option<FrameTimingHook>,
+    /// Encode the frame number as a strip of black and white squares in the top left
+    /// corner, for a camera or photodiode in a test rig to read back; see `FrameTiming`
+    #[builder(default = false)]
+    pub timestamp_marker: bool,
+    /// Side of one square of the timestamp marker
+    #[builder(default = 8)]
+    pub timestamp_marker_size: i32,
+    /// Rasterise the background, zone and tick layers once and reuse their pixels until
+    /// they change. Each frame then skips the full-frame clear, restoring and redrawing
+    /// only where the other layers drew last frame or draw now. The frame buffer must
+    /// keep its contents between renders; rotation, burn-in protection, dimming, the
+    /// timestamp marker and a frame hook each force a full copy of the cached pixels.
+    #[builder(default = false)]
+    pub cached_background: bool,
+    /// Layout of the frames passed to `Instrument::render_into`, `Renderer::render_into`
+    /// and the frame hook, so they can be handed straight to a display. Windows always
+    /// use RGBA8, and the Linux framebuffer uses whatever the device reports.
+    #[builder(default)]
+    pub pixel_format: PixelFormat,
+}
+
+/// Geometry of the main dial handed to a custom layer, in unmirrored gauge coordinates
+#[derive(Debug, Clone, Copy)]
+pub struct LayerContext {
+    pub width: usize,
+    pub height: usize,
+    pub cx: i32,
+    pub cy: i32,
+    pub radius: i32,
+}
+
+/// Callback that injects draw commands into each frame; see `InstrumentConfig::custom_layer`
+#[derive(Clone)]
+pub struct CustomLayer(std::sync::Arc<CustomLayerFn>);
+
+type CustomLayerFn = dyn Fn(&mut Scene, &LayerContext) + Send + Sync;
+
+impl CustomLayer {
+    pub fn new(draw: impl Fn(&mut Scene, &LayerContext) + Send + Sync + 'static) -> Self {
+        Self(std::sync::Arc::new(draw))
+    }
+}
+
+impl std::fmt::Debug for CustomLayer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("CustomLayer(..)")
+    }
+}
+
+/// Callback receiving the finished frame, in `InstrumentConfig::pixel_format`, with its
+/// width and height. It may modify
+/// the pixels in place; see `InstrumentConfig::frame_hook`
+#[derive(Clone)]
+pub struct FrameHook(std::sync::Arc<FrameHookFn>);
+
+type FrameHookFn = dyn Fn(&mut [u8], usize, usize) + Send + Sync;
+
+impl FrameHook {
+    pub fn new(hook: impl Fn(&mut [u8], usize, usize) + Send + Sync + 'static) -> Self {
+        Self(std::sync::Arc::new(hook))
+    }
+}
+
+impl std::fmt::Debug for FrameHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("FrameHook(..)")
+    }
+}
+
+/// Turns a tick value into its label; see `InstrumentConfig::chronograph_label_formatter`
+#[derive(Clone)]
+pub struct LabelFormatter(std::sync::Arc<LabelFormatterFn>);
+
+type LabelFormatterFn = dyn Fn(f64) -> String + Send + Sync;
+
+impl LabelFormatter {
+    pub fn new(format: impl Fn(f64) -> String + Send + Sync + 'static) -> Self {
+        Self(std::sync::Arc::new(format))
+    }
+}
+
+impl std::fmt::Debug for LabelFormatter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("LabelFormatter(..)")
+    }
+}
+
+/// Callback acknowledging `InstrumentCommand::Sequence` ids; see
+/// `InstrumentConfig::sequence_hook`
+#[derive(Clone)]
+pub struct SequenceHook(std::sync::Arc<SequenceHookFn>);
+
+type SequenceHookFn = dyn Fn(u64) + Send + Sync;
+
+impl SequenceHook {
+    pub fn new(hook: impl Fn(u64) + Send + Sync + 'static) -> Self {
+        Self(std::sync::Arc::new(hook))
+    }
+}
+
+impl std::fmt::Debug for SequenceHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("SequenceHook(..)")
+    }
+}
+
+/// When one frame was drawn and put on screen, for measuring latency from a sensor
+/// reading to the photons showing it
+#[derive(Debug, Clone, Copy)]
+pub struct FrameTiming {
+    /// Presented frames counted from 0. The timestamp marker shows its low 16 bits: a
+    /// white and a black square, then one square per bit, most significant first and
+    /// white for 1.
+    pub frame: u64,
+    /// The latest `InstrumentCommand::Sequence` id the frame shows
+    pub sequence: Option<u64>,
+    /// When commands had been applied and drawing began
+    pub started: Instant,
+    pub presented: Instant,
+    /// `presented` on the wall clock, to line up with timestamps taken elsewhere
+    pub presented_at: std::time::SystemTime,
+}
+
+/// Callback receiving each presented frame's `FrameTiming`; see
+/// `InstrumentConfig::frame_timing_hook`
+#[derive(Clone)]
+pub struct FrameTimingHook(std::sync::Arc<FrameTimingHookFn>);
+
+type FrameTimingHookFn = dyn Fn(&FrameTiming) + Send + Sync;
+
+impl FrameTimingHook {
+    pub fn new(hook: impl Fn(&FrameTiming) + Send + Sync + 'static) -> Self {
+        Self(std::sync::Arc::new(hook))
+    }
+}
+
+impl std::fmt::Debug for FrameTimingHook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("FrameTimingHook(..)")
+    }
+}
+
+// ============================================================================
+// PRESETS
+// ============================================================================
+
+// Shared styling for the temperature presets: nice ticks, a unit label along the top
+// and a red warning zone
+fn temperature_preset(
+    range: (f64, f64),
+    unit: &str,
+    warning_zone: Option<(f64, f64)>,
+) -> InstrumentConfig {
+    InstrumentConfig::builder()
+        .title(unit.to_string())
+        .range(range)
+        .auto_ticks(true)
+        .auto_ticks_target(8)
+        .minor_ticks_per_interval(4)
+        .curved_text(unit.to_string())
+        .maybe_highlight_band(
+            warning_zone.map(|(low, high)| (low, high, Color::new(0xff, 0x00, 0x00))),
+        )
+        .build()
+}
+
+impl InstrumentConfig {
+    /// Ambient thermometer, -30 to 50 °C with a Fahrenheit ring inside
+    pub fn thermometer_c() -> Self {
+        Self {
+            secondary_scale_factor: Some(1.8),
+            secondary_scale_offset: 32.0,
+            ..temperature_preset((-30.0, 50.0), "°C", None)
+        }
+    }
+
+    /// Ambient thermometer, -20 to 120 °F with a Celsius ring inside
+    pub fn thermometer_f() -> Self {
+        Self {
+            secondary_scale_factor: Some(5.0 / 9.0),
+            secondary_scale_offset: -160.0 / 9.0,
+            ..temperature_preset((-20.0, 120.0), "°F", None)
+        }
+    }
+
+    /// Engine oil temperature, 40 to 160 °C, red above 130
+    pub fn oil_temp() -> Self {
+        temperature_preset((40.0, 160.0), "OIL °C", Some((130.0, 160.0)))
+    }
+
+    /// Engine coolant temperature, 40 to 140 °C, red above 115
+    pub fn coolant_temp() -> Self {
+        temperature_preset((40.0, 140.0), "WATER °C", Some((115.0, 140.0)))
+    }
+
+    /// Exhaust gas temperature, 0 to 1000 °C, red above 800
+    pub fn exhaust_gas_temp() -> Self {
+        temperature_preset((0.0, 1000.0), "EGT °C", Some((800.0, 1000.0)))
+    }
+
+    /// Symmetric scale from `-limit` to `limit` with zero at 12 o'clock, so the needle
+    /// swings left for negative values and right for positive ones, as on trim, balance
+    /// or vario indicators. Positive labels carry a plus sign.
+    pub fn zero_center(limit: f64) -> Self {
+        let arc_span = std::f64::consts::PI * 1.5;
+        Self::builder()
+            .range((-limit.abs(), limit.abs()))
+            .dial_arc_span(arc_span)
+            .dial_start_angle(std::f64::consts::PI * 1.5 - arc_span / 2.0)
+            // Nice steps over a symmetric range always land a tick on zero
+            .auto_ticks(true)
+            .auto_ticks_target(10)
+            .minor_ticks_per_interval(4)
+            .signed_labels(true)
+            // Below the pivot, between the two ends of the scale
+            .readout_x_factor(0.5)
+            .readout_y_factor(0.8)
+            .readout_horizontal_anchor(HorizontalAnchor::Center)
+            .build()
+    }
+
+    /// Variometer, -5 to +5 m/s around a top zero
+    pub fn vario() -> Self {
+        Self {
+            title: "VARIO".to_string(),
+            curved_text: "m/s".to_string(),
+            ..Self::zero_center(5.0)
+        }
+    }
+
+    /// Weather-station wind gauge: direction in degrees on the primary needle, over a
+    /// compass card with north at the top, and speed in m/s on the chronograph subdial
+    /// with the highest gust of the last ten minutes marked
+    pub fn wind() -> Self {
+        let points = ["N", "NE", "E", "SE", "S", "SW", "W", "NW"];
+        let mut card: Vec<Tick> = points
+            .iter()
+            .enumerate()
+            .map(|(i, point)| Tick::labelled(i as f64 * 45.0, *point))
+            .collect();
+        // Closes the last interval so its minor ticks are drawn; north is already labelled
+        card.push(Tick::labelled(360.0, ""));
+        Self::builder()
+            .title("WIND".to_string())
+            .range((0.0, 360.0))
+            .dial_arc_span(std::f64::consts::TAU)
+            .dial_start_angle(std::f64::consts::PI * 1.5)
+            .wrap_around(true)
+            .custom_ticks(card)
+            .minor_ticks_per_interval(3)
+            .chronograph_range((0.0, 30.0))
+            // Speed below the pivot, with the readout above it
+            .chronograph_dial_shift(315)
+            .chronograph_ticks_count(4)
+            .chronograph_minor_ticks_per_interval(2)
+            .chronograph_dial_numbers_font_size(20.0)
+            .chronograph_dial_ticks_to_numbers_distance(20.0)
+            .chronograph_peak_hold(600.0)
+            .readout_x_factor(0.5)
+            .readout_y_factor(0.32)
+            .readout_horizontal_anchor(HorizontalAnchor::Center)
+            .build()
+    }
+}
+
+impl InstrumentConfig {
+    /// Checks for settings that would otherwise render as NaN angles or panic while
+    /// clamping values, describing the first problem found
+    pub fn validate(&self) -> Result<(), InstrumentError> {
+        let invalid = |message: String| Err(InstrumentError::InvalidConfig(message));
+        let ranges = [
+            ("range", self.range),
+            ("chronograph_range", self.chronograph_range),
+            (
+                "secondary_chronograph_range",
+                self.secondary_chronograph_range,
+            ),
+        ];
+        let secondary = self.secondary_range.map(|range| ("secondary_range", range));
+        for (name, (min, max)) in ranges.into_iter().chain(secondary) {
+            if !(min.is_finite() && max.is_finite() && min < max) {
+                return invalid(format!(
+                    "{name} must be finite with min < max, got ({min}, {max})"
+                ));
+            }
+        }
+        if self.custom_ticks.is_none() && !self.auto_ticks && self.ticks_count < 2 {
+            return invalid(format!(
+                "ticks_count must be at least 2, got {}",
+                self.ticks_count
+            ));
+        }
+        if self.auto_ticks && self.auto_ticks_target == 0 {
+            return invalid("auto_ticks_target must be at least 1".to_string());
+        }
+        if self.window_width == 0 || self.window_height == 0 {
+            return invalid(format!(
+                "window size must be positive, got {}x{}",
+                self.window_width, self.window_height
+            ));
+        }
+        let positive = [
+            ("max_framerate", self.max_framerate),
+            ("ui_scale", self.ui_scale),
+            ("dial_numbers_font_size", self.dial_numbers_font_size as f64),
+            ("readout_big_font_size", self.readout_big_font_size as f64),
+            (
+                "readout_small_font_size",
+                self.readout_small_font_size as f64,
+            ),
+            ("dial_arc_span", self.dial_arc_span),
+            ("logo_size", self.logo_size),
+            ("dial_text_font_size", self.dial_text_font_size as f64),
+            (
+                "dial_text_subtitle_font_size",
+                self.dial_text_subtitle_font_size as f64,
+            ),
+            ("needle_blink_rate", self.needle_blink_rate),
+            ("target_bug_size", self.target_bug_size as f64),
+            ("chronograph_arc_span", self.chronograph_arc_span),
+            (
+                "secondary_chronograph_arc_span",
+                self.secondary_chronograph_arc_span,
+            ),
+        ];
+        let optional = [
+            ("fixed_timestep", self.fixed_timestep),
+            ("chronograph_peak_hold", self.chronograph_peak_hold),
+        ];
+        let optional = optional
+            .into_iter()
+            .filter_map(|(name, value)| Some((name, value?)));
+        for (name, value) in positive.into_iter().chain(optional) {
+            if !(value.is_finite() && value > 0.0) {
+                return invalid(format!("{name} must be positive, got {value}"));
+            }
+        }
+        if let Some(ScaleBreak { from, to, gap }) = self.scale_break {
+            let (min, max) = self.range;
+            if !(from < to && from > min && to < max) {
+                return invalid(format!(
+                    "scale_break ({from}, {to}) must be increasing and lie inside range ({min}, {max})"
+                ));
+            }
+            if !(0.0..1.0).contains(&gap) {
+                return invalid(format!("scale_break gap must be in [0, 1), got {gap}"));
+            }
+        }
+        let opacities = [
+            ("dial_opacity", self.dial_opacity),
+            ("tick_opacity", self.tick_opacity),
+            ("needle_opacity", self.needle_opacity),
+            ("secondary_needle_opacity", self.secondary_needle_opacity),
+            ("readout_opacity", self.readout_opacity),
+            ("zone_opacity", self.zone_opacity),
+        ];
+        for (name, value) in opacities {
+            if !(0.0..=1.0).contains(&value) {
+                return invalid(format!("{name} must be in [0, 1], got {value}"));
+            }
+        }
+        if !(self.needle_tip_fraction > 0.0 && self.needle_tip_fraction <= 1.0) {
+            return invalid(format!(
+                "needle_tip_fraction must be in (0, 1], got {}",
+                self.needle_tip_fraction
+            ));
+        }
+        if let Some((lower, upper, _)) = self.highlight_band {
+            let (min, max) = self.range;
+            if lower.min(upper) < min || lower.max(upper) > max {
+                return invalid(format!(
+                    "highlight_band ({lower}, {upper}) must lie inside range ({min}, {max})"
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    // The config with `ui_scale` folded into every pixel dimension
+    fn scaled(&self) -> std::borrow::Cow<'_, InstrumentConfig> {
+        if self.ui_scale == 1.0 {
+            return std::borrow::Cow::Borrowed(self);
+        }
+        let k = self.ui_scale;
+        let px = |v: i32| (v as f64 * k).round() as i32;
+        let pt = |v: f32| v * k as f32;
+        let mut c = self.clone();
+        c.ui_scale = 1.0;
+
+        c.burn_in_shift = px(c.burn_in_shift);
+        c.timestamp_marker_size = px(c.timestamp_marker_size);
+        c.dial_margin = px(c.dial_margin);
+        c.dial_thickness = px(c.dial_thickness);
+        c.pivot_offset = (px(c.pivot_offset.0), px(c.pivot_offset.1));
+        c.dial_numbers_font_size = pt(c.dial_numbers_font_size);
+        c.dial_ticks_to_numbers_distance *= k;
+        c.major_tick_length = px(c.major_tick_length);
+        c.minor_tick_length = px(c.minor_tick_length);
+        c.major_tick_thickness = pt(c.major_tick_thickness);
+        c.secondary_scale_font_size = pt(c.secondary_scale_font_size);
+        c.secondary_scale_distance *= k;
+        for icon in &mut c.scale_icons {
+            icon.size = icon.size.map(pt);
+        }
+        c.minor_tick_thickness = pt(c.minor_tick_thickness);
+        c.dial_line_style = c.dial_line_style.scaled(k);
+        c.major_tick_style = c.major_tick_style.scaled(k);
+        c.minor_tick_style = c.minor_tick_style.scaled(k);
+        c.minor_tick_labels_font_size = pt(c.minor_tick_labels_font_size);
+        c.needle_back_length *= k;
+        c.needle_width = pt(c.needle_width);
+        c.needle_tip_width = c.needle_tip_width.map(pt);
+        c.target_bug_size = px(c.target_bug_size);
+
+        c.chronograph_dial_shift = px(c.chronograph_dial_shift);
+        c.chronograph_tick_length = px(c.chronograph_tick_length);
+        c.chronograph_dial_margin = px(c.chronograph_dial_margin);
+        c.chronograph_dial_thickness = px(c.chronograph_dial_thickness);
+        c.chronograph_needle_width = pt(c.chronograph_needle_width);
+        c.chronograph_needle_back_length *= k;
+        c.chronograph_dial_numbers_font_size = pt(c.chronograph_dial_numbers_font_size);
+        c.chronograph_dial_ticks_to_numbers_distance *= k;
+        c.chronograph_dial_dot_radius = px(c.chronograph_dial_dot_radius);
+        c.chronograph_minor_tick_length = px(c.chronograph_minor_tick_length);
+        c.chronograph_major_tick_thickness = pt(c.chronograph_major_tick_thickness);
+        c.chronograph_minor_tick_thickness = pt(c.chronograph_minor_tick_thickness);
+
+        c.secondary_chronograph_dial_shift = px(c.secondary_chronograph_dial_shift);
+        c.secondary_chronograph_tick_length = px(c.secondary_chronograph_tick_length);
+        c.secondary_chronograph_dial_margin = px(c.secondary_chronograph_dial_margin);
+        c.secondary_chronograph_dial_thickness = px(c.secondary_chronograph_dial_thickness);
+        c.secondary_chronograph_needle_width = pt(c.secondary_chronograph_needle_width);
+        c.secondary_chronograph_needle_back_length *= k;
+        c.secondary_chronograph_dial_numbers_font_size =
+            pt(c.secondary_chronograph_dial_numbers_font_size);
+        c.secondary_chronograph_dial_ticks_to_numbers_distance *= k;
+        c.secondary_chronograph_dial_dot_radius = px(c.secondary_chronograph_dial_dot_radius);
+        c.secondary_chronograph_minor_tick_length = px(c.secondary_chronograph_minor_tick_length);
+        c.secondary_chronograph_major_tick_thickness =
+            pt(c.secondary_chronograph_major_tick_thickness);
+        c.secondary_chronograph_minor_tick_thickness =
+            pt(c.secondary_chronograph_minor_tick_thickness);
+
+        c.readout_big_font_size = pt(c.readout_big_font_size);
+        c.readout_small_font_size = pt(c.readout_small_font_size);
+        c.readout_box_padding = px(c.readout_box_padding);
+        c.totalizer_font_size = pt(c.totalizer_font_size);
+        c.readout_box_thickness = pt(c.readout_box_thickness);
+        c.readout_box_corner_radius *= k;
+
+        c.curved_text_font_size = pt(c.curved_text_font_size);
+        c.curved_text_radius_offset *= k;
+        c.curved_text_letter_spacing *= k;
+        c.bottom_curved_text_font_size = pt(c.bottom_curved_text_font_size);
+        c.bottom_curved_text_radius_offset *= k;
+        c.bottom_curved_text_letter_spacing *= k;
+        c.dial_text_font_size = pt(c.dial_text_font_size);
+        c.dial_text_subtitle_font_size = pt(c.dial_text_subtitle_font_size);
+
+        c.highlight_band_width = px(c.highlight_band_width);
+        c.zone_band_width = px(c.zone_band_width);
+        c.exclamation_mark_size = pt(c.exclamation_mark_size);
+        c.alert_indicator_font_size = pt(c.alert_indicator_font_size);
+        c.dot_radius = px(c.dot_radius);
+        c.hub_radius = c.hub_radius.map(px);
+        c.hub_ring_width = pt(c.hub_ring_width);
+        std::borrow::Cow::Owned(c)
+    }
+}
+
+// ============================================================================
+// CONFIGURATION TYPES (INTERNAL)
+// ============================================================================
+
+#[derive(Debug, Clone)]
+struct InstrumentState {
+    primary_value: f64,
+    secondary_value: Option<f64>,
+    chronograph_value: Option<f64>,
+    secondary_chronograph_value: Option<f64>,
+    readout_value: Option<f64>,
+}
+
+impl Instrument {
+    // Main dial values are kept unclamped: the needle stops at the end of the scale,
+    // while the readout and the out-of-range warning see what was actually commanded
+    pub fn set_value(&mut self, value: f64) {
+        self.state.primary_value = value;
+        self.state.readout_value = Some(value);
+    }
+
+    pub fn set_primary_value(&mut self, value: f64) {
+        self.state.primary_value = value;
+    }
+
+    pub fn set_secondary_value(&mut self, value: f64) {
+        self.state.secondary_value = Some(value);
+    }
+
+    pub fn set_chronograph_value(&mut self, value: f64) {
+        let clamped_value = value.clamp(
+            self.config.chronograph_range.0,
+            self.config.chronograph_range.1,
+        );
+        self.state.chronograph_value = Some(clamped_value);
+    }
+
+    pub fn set_secondary_chronograph_value(&mut self, value: f64) {
+        let clamped_value = value.clamp(
+            self.config.secondary_chronograph_range.0,
+            self.config.secondary_chronograph_range.1,
+        );
+        self.state.secondary_chronograph_value = Some(clamped_value);
+    }
+
+    pub fn clear_secondary_value(&mut self) {
+        self.state.secondary_value = None;
+    }
+
+    pub fn clear_chronograph_value(&mut self) {
+        self.state.chronograph_value = None;
+    }
+
+    pub fn clear_secondary_chronograph_value(&mut self) {
+        self.state.secondary_chronograph_value = None;
+    }
+
+    pub fn clear_readout_value(&mut self) {
+        self.state.readout_value = None;
+    }
+
+    pub fn show(&mut self) -> Result<(), InstrumentError> {
+        let title = self.config.title.clone();
+        self.run_window(EventLoop::new()?, title, Input::None)
+    }
+
+    pub fn show_with_commands(
+        &mut self,
+        receiver: Receiver<InstrumentCommand>,
+    ) -> Result<(), InstrumentError> {
+        let title = self.config.title.clone();
+        self.run_window(EventLoop::new()?, title, Input::Channel(receiver))
+    }
+
+    /// Like [`show_with_commands`](Self::show_with_commands), or [`show`](Self::show)
+    /// without a receiver, on an event loop the caller built. Android apps need this,
+    /// since winit only runs there on a loop built from the `AndroidApp` passed to
+    /// `android_main`, e.g. `EventLoopBuilder::new().with_android_app(app).build()`.
+    pub fn show_in_event_loop(
+        &mut self,
+        event_loop: EventLoop<()>,
+        receiver: Option<Receiver<InstrumentCommand>>,
+    ) -> Result<(), InstrumentError> {
+        let title = self.config.title.clone();
+        let input = receiver.map_or(Input::None, Input::Channel);
+        self.run_window(event_loop, title, input)
+    }
+
+    /// Draws straight to a Linux fbdev device such as `/dev/fb0`, without a window
+    /// system, until rendering fails. Only 32-bit (BGRX) and 16-bit (RGB565) modes are
+    /// supported.
+    #[cfg(target_os = "linux")]
+    pub fn show_on_framebuffer(
+        &mut self,
+        device: impl AsRef<std::path::Path>,
+    ) -> Result<(), InstrumentError> {
+        self.run_framebuffer(device.as_ref(), Input::None)
+    }
+
+    #[cfg(target_os = "linux")]
+    pub fn show_on_framebuffer_with_commands(
+        &mut self,
+        device: impl AsRef<std::path::Path>,
+        receiver: Receiver<InstrumentCommand>,
+    ) -> Result<(), InstrumentError> {
+        self.run_framebuffer(device.as_ref(), Input::Channel(receiver))
+    }
+
+    /// Opens the window and reads the latest values from a shared-memory block each
+    /// frame instead of from a channel; see [`SHARED_VALUES_LEN`] for the layout.
+    /// Producers write in place, so nothing queues up however fast they update.
+    #[cfg(feature = "shared-memory")]
+    pub fn show_with_shared_memory(
+        &mut self,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<(), InstrumentError> {
+        let values = MappedValues::open(path.as_ref())?;
+        let title = self.config.title.clone();
+        self.run_window(EventLoop::new()?, title, Input::Mapped(values))
+    }
+
+    /// Opens the window with every needle that has a value (and the readout, if shown)
+    /// following a random walk, for previewing a config without a data source. With a
+    /// `demo_seed` the walk is a fixed minute-long loop, played as an animation.
+    pub fn show_demo(&mut self) -> Result<(), InstrumentError> {
+        use rand::{Rng, SeedableRng};
+
+        struct Walk {
+            command: fn(f64) -> InstrumentCommand,
+            target: AnimationTarget,
+            range: (f64, f64),
+            value: f64,
+        }
+        impl Walk {
+            // Bounce off the ends of the scale instead of sticking to them
+            fn step(&mut self, rng: &mut impl Rng) {
+                let (min, max) = self.range;
+                let step = rng.random_range(-0.05..=0.05) * (max - min);
+                self.value += step;
+                if self.value < min || self.value > max {
+                    self.value -= 2.0 * step;
+                }
+                self.value = self.value.clamp(min, max);
+            }
+        }
+        let walk = |command, target, range, value| Walk {
+            command,
+            target,
+            range,
+            value,
+        };
+
+        let mut walks = vec![walk(
+            InstrumentCommand::SetPrimaryNeedle,
+            AnimationTarget::Primary,
+            self.config.range,
+            self.state.primary_value,
+        )];
+        if let Some(value) = self.state.secondary_value {
+            walks.push(walk(
+                InstrumentCommand::SetSecondaryNeedle,
+                AnimationTarget::Secondary,
+                self.config.secondary_range.unwrap_or(self.config.range),
+                value,
+            ));
+        }
+        if let Some(value) = self.state.chronograph_value {
+            walks.push(walk(
+                InstrumentCommand::SetChronograph,
+                AnimationTarget::Chronograph,
+                self.config.chronograph_range,
+                value,
+            ));
+        }
+        if let Some(value) = self.state.secondary_chronograph_value {
+            walks.push(walk(
+                InstrumentCommand::SetSecondaryChronograph,
+                AnimationTarget::SecondaryChronograph,
+                self.config.secondary_chronograph_range,
+                value,
+            ));
+        }
+        if let Some(value) = self.state.readout_value {
+            walks.push(walk(
+                InstrumentCommand::SetReadout,
+                AnimationTarget::Readout,
+                self.config.range,
+                value,
+            ));
+        }
+
+        const STEP: f64 = 0.1;
+        let (sender, receiver) = std::sync::mpsc::channel();
+        if let Some(seed) = self.config.demo_seed {
+            // Keyframes instead of a thread, so the walk runs on the animation clock
+            const STEPS: usize = 600;
+            let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+            let mut animation = Animation::new().looping(true);
+            for walk in &mut walks {
+                let start = walk.value;
+                for step in 0..STEPS {
+                    animation = animation.keyframe(walk.target, step as f64 * STEP, walk.value);
+                    walk.step(&mut rng);
+                }
+                animation = animation.keyframe(walk.target, STEPS as f64 * STEP, start);
+            }
+            let _ = sender.send(InstrumentCommand::PlayAnimation(animation));
+        } else {
+            std::thread::spawn(move || {
+                let mut rng = rand::rng();
+                loop {
+                    for walk in &mut walks {
+                        walk.step(&mut rng);
+                        if sender.send((walk.command)(walk.value)).is_err() {
+                            return;
+                        }
+                    }
+                    std::thread::sleep(std::time::Duration::from_secs_f64(STEP));
+                }
+            });
+        }
+        self.show_with_commands(receiver)
+    }
+
+    /// Renders the current values as a standalone SVG document of the given size
+    pub fn to_svg(&self, width: usize, height: usize) -> String {
+        let mut app_state = self.initial_app_state();
+        app_state.settle();
+        let config = self.config.scaled();
+        let scene = build_scene(width, height, &app_state, &config);
+        let mut svg = SvgRasterizer::new(width, height, &config);
+        scene.render(&mut svg, &config);
+        svg.finish()
+    }
+
+    pub fn save_svg(
+        &self,
+        path: impl AsRef<std::path::Path>,
+        width: usize,
+        height: usize,
+    ) -> std::io::Result<()> {
+        std::fs::write(path, self.to_svg(width, height))
+    }
+
+    /// Renders the current values into a buffer in `pixel_format` without opening a
+    /// window. Needles are settled on their targets and time-based effects are frozen at
+    /// zero, so the same config and values always produce the same pixels.
+    pub fn render_offscreen(&self, width: usize, height: usize) -> Vec<u8> {
+        let mut frame = vec![0u8; width * height * self.config.pixel_format.bytes_per_pixel()];
+        self.render_into(&mut frame, width, height);
+        frame
+    }
+
+    /// Like [`render_offscreen`](Self::render_offscreen), but into a caller-owned buffer
+    /// of exactly `width * height` pixels in `pixel_format`, e.g. a texture mapping
+    pub fn render_into(&self, frame: &mut [u8], width: usize, height: usize) {
+        check_frame_size(frame, width, height, &self.config);
+        let mut app_state = self.initial_app_state();
+        app_state.settle();
+        render_frame(frame, width, height, &app_state, &self.config, 0.0, None);
+        if let Some(ref hook) = self.config.frame_hook {
+            (hook.0)(frame, width, height);
+        }
+    }
+
+    /// A live renderer for embedding the gauge in another program's frame loop: needles
+    /// move, animations play and commands apply exactly as they do in the window
+    pub fn renderer(&self) -> Renderer {
+        Renderer {
+            config: self.config.clone(),
+            state: self.initial_app_state(),
+        }
+    }
+
+    fn initial_app_state(&self) -> AppState {
+        let mut app_state = AppState::new(&self.config);
+        if let Some((lower, upper, _color)) = self.config.highlight_band {
+            app_state.set_highlight_override(lower, upper);
+        }
+
+        // Initialize app_state with current instrument state, calibrated like commands
+        let state = &self.state;
+        let mut commands = vec![InstrumentCommand::SetPrimaryNeedle(state.primary_value)];
+        commands.extend(
+            state
+                .secondary_value
+                .map(InstrumentCommand::SetSecondaryNeedle),
+        );
+        commands.extend(
+            state
+                .chronograph_value
+                .map(InstrumentCommand::SetChronograph),
+        );
+        commands.extend(
+            state
+                .secondary_chronograph_value
+                .map(InstrumentCommand::SetSecondaryChronograph),
+        );
+        commands.extend(state.readout_value.map(InstrumentCommand::SetReadout));
+        for command in commands {
+            app_state.apply_command(command);
+        }
+        app_state
+    }
+
+    fn run_window(
+        &self,
+        event_loop: EventLoop<()>,
+        title: String,
+        input: Input,
+    ) -> Result<(), InstrumentError> {
+        self.config.validate()?;
+        try_text_shaper(&self.config)?;
+        let logical_width: usize = self.config.window_width;
+        let logical_height: usize = self.config.window_height;
+
+        let window = WindowBuilder::new()
+            .with_title(&title)
+            .with_inner_size(LogicalSize::new(
+                logical_width as f64,
+                logical_height as f64,
+            ))
+            .with_resizable(false)
+            .with_decorations(!self.config.borderless)
+            .with_window_level(if self.config.borderless {
+                WindowLevel::AlwaysOnTop
+            } else {
+                WindowLevel::Normal
+            })
+            .build(&event_loop)?;
+        if self.config.click_through {
+            // Not every platform can do this; the window still works, it just takes clicks
+            let _ = window.set_cursor_hittest(false);
+        }
+
+        let window = std::sync::Arc::new(window);
+
+        let mut app_state = self.initial_app_state();
+        app_state.restore_state(&self.config);
+
+        let window_clone = window.clone();
+        let mut surface = SurfaceSize::of(&window);
+        let mut config = surface.config_for(&self.config);
+        // Mobile platforms only hand out a surface between `Resumed` and `Suspended`, and
+        // may take it away again at any time, so the pixel buffer follows that lifecycle.
+        // Desktops get a single `Resumed` at startup.
+        let mut pixels: Option<Pixels> = None;
+        let mut surface_error = None;
+        let surface_error_slot = &mut surface_error;
+        // Borrowed from outside the handler so the surface can outlive any one event
+        let window_ref = &window;
+
+        let target_fps = self.config.max_framerate;
+        let frame_duration = std::time::Duration::from_secs_f64(1.0 / target_fps);
+        let mut last_frame = Instant::now();
+        let mut show_overlay = self.config.debug_overlay;
+        let mut stats = FrameStats::default();
+
+        event_loop.run(move |event, window_target| {
+            window_target.set_control_flow(ControlFlow::Poll);
+            match event {
+                Event::Resumed if pixels.is_none() => {
+                    surface = SurfaceSize::of(window_ref);
+                    config = surface.config_for(&self.config);
+                    match new_pixels(window_ref) {
+                        Ok(created) => pixels = Some(created),
+                        Err(error) => {
+                            *surface_error_slot = Some(error);
+                            window_target.exit();
+                        }
+                    }
+                }
+                Event::Suspended => {
+                    pixels = None;
+                }
+                Event::WindowEvent { event, .. } => match event {
+                    WindowEvent::CloseRequested => {
+                        window_target.exit();
+                    }
+                    WindowEvent::Resized(new_size) => {
+                        surface.resize(new_size, pixels.as_mut());
+                    }
+                    WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                        surface.scale_factor = scale_factor;
+                        config = surface.config_for(&self.config);
+                    }
+                    WindowEvent::KeyboardInput { event, .. }
+                        if event.state == ElementState::Pressed
+                            && event.logical_key == Key::Named(NamedKey::F3) =>
+                    {
+                        show_overlay = !show_overlay;
+                    }
+                    WindowEvent::MouseInput {
+                        state: ElementState::Pressed,
+                        button: MouseButton::Left,
+                        ..
+                    } if self.config.borderless => {
+                        let _ = window_ref.drag_window();
+                    }
+                    WindowEvent::RedrawRequested => {
+                        input.apply_pending(&mut app_state);
+                        app_state.update();
+
+                        let Some(ref mut pixels) = pixels else {
+                            return;
+                        };
+                        let frame = pixels.frame_mut();
+                        let (fb_width, fb_height) = (surface.width, surface.height);
+                        let elapsed = app_state.elapsed();
+                        let render_started = Instant::now();
+                        render_frame(
+                            frame,
+                            fb_width,
+                            fb_height,
+                            &app_state,
+                            &config,
+                            elapsed,
+                            show_overlay.then_some(&mut stats.command_costs),
+                        );
+                        if let Some(ref hook) = config.frame_hook {
+                            (hook.0)(frame, fb_width, fb_height);
+                        }
+                        if show_overlay {
+                            stats.record_frame(render_started.elapsed());
+                            draw_debug_overlay(frame, fb_width, fb_height, &stats, &config);
+                        }
+                        trace_span!("present");
+                        let _ = pixels.render();
+                        app_state.acknowledge_presented(&config);
+                    }
+                    _ => {}
+                },
+                Event::LoopExiting => app_state.save_state(&config),
+                Event::AboutToWait => {
+                    if last_frame.elapsed() >= frame_duration {
+                        window_clone.request_redraw();
+                        last_frame = Instant::now();
+                    }
+                }
+                _ => {}
+            }
+        })?;
+
+        match surface_error {
+            Some(error) => Err(error.into()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Instrument {
+    /// Like [`new`](Self::new), but rejects configs that fail
+    /// [`InstrumentConfig::validate`]. The `show` methods validate before opening a
+    /// window either way.
+    pub fn try_new(config: InstrumentConfig) -> Result<Self, InstrumentError> {
+        config.validate()?;
+        Ok(Self::new(config))
+    }
+
+    pub fn new(config: InstrumentConfig) -> Self {
+        let state = InstrumentState {
+            primary_value: config.range.0,
+            secondary_value: None,
+            chronograph_value: None,
+            secondary_chronograph_value: None,
+            readout_value: None,
+        };
+
+        Self { config, state }
+    }
+}
+
+/// Stable 64-bit FNV-1a hash of a frame, for comparing renders across runs
+pub fn frame_hash(frame: &[u8]) -> u64 {
+    frame.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+// ============================================================================
+// ERRORS
+// ============================================================================
+
+/// Why an instrument couldn't be shown
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum InstrumentError {
+    EventLoop(winit::error::EventLoopError),
+    Window(winit::error::OsError),
+    /// The GPU surface or its pixel buffer couldn't be created
+    Surface(pixels::Error),
+    /// The primary or a fallback font couldn't be parsed
+    Font(&'static str),
+    InvalidConfig(String),
+    /// A framebuffer device or shared-memory file couldn't be used
+    Io(std::io::Error),
+}
+
+impl std::fmt::Display for InstrumentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InstrumentError::EventLoop(error) => {
+                write!(f, "couldn't start the event loop: {error}")
+            }
+            InstrumentError::Window(error) => write!(f, "couldn't create the window: {error}"),
+            InstrumentError::Surface(error) => write!(f, "couldn't create the surface: {error}"),
+            InstrumentError::Font(message) => write!(f, "couldn't load a font: {message}"),
+            InstrumentError::InvalidConfig(message) => write!(f, "invalid config: {message}"),
+            InstrumentError::Io(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for InstrumentError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            InstrumentError::EventLoop(error) => Some(error),
+            InstrumentError::Window(error) => Some(error),
+            InstrumentError::Surface(error) => Some(error),
+            InstrumentError::Io(error) => Some(error),
+            InstrumentError::Font(_) | InstrumentError::InvalidConfig(_) => None,
+        }
+    }
+}
+
+impl From<winit::error::EventLoopError> for InstrumentError {
+    fn from(error: winit::error::EventLoopError) -> Self {
+        InstrumentError::EventLoop(error)
+    }
+}
+
+impl From<winit::error::OsError> for InstrumentError {
+    fn from(error: winit::error::OsError) -> Self {
+        InstrumentError::Window(error)
+    }
+}
+
+impl From<pixels::Error> for InstrumentError {
+    fn from(error: pixels::Error) -> Self {
+        InstrumentError::Surface(error)
+    }
+}
+
+impl From<std::io::Error> for InstrumentError {
+    fn from(error: std::io::Error) -> Self {
+        InstrumentError::Io(error)
+    }
+}
+
+// ============================================================================
+// INPUT SOURCES
+// ============================================================================
+
+// Where a window's updates come from
+enum Input {
+    None,
+    Channel(Receiver<InstrumentCommand>),
+    #[cfg(feature = "shared-memory")]
+    Mapped(MappedValues),
+}
+
+impl Input {
+    fn apply_pending(&self, state: &mut AppState) {
+        trace_span!("process_commands");
+        match self {
+            Input::None => {}
+            Input::Channel(receiver) => {
+                // Try to get the latest command without blocking
+                while let Ok(command) = receiver.try_recv() {
+                    state.apply_command(command);
+                }
+            }
+            #[cfg(feature = "shared-memory")]
+            Input::Mapped(values) => {
+                if let Some(values) = values.poll() {
+                    state.apply_shared_values(values);
+                }
+            }
+        }
+    }
+}
+
+/// Size in bytes of a shared-memory value block: six little-endian 64-bit words.
+///
+/// Word 0 is a sequence counter the writer makes odd before changing the values and
+/// even again afterwards. Words 1 to 5 are the primary, secondary, chronograph,
+/// secondary chronograph and readout values as `f64`; NaN hides all but the primary.
+pub const SHARED_VALUES_LEN: usize = 48;
+
+#[cfg(feature = "shared-memory")]
+struct MappedValues {
+    map: memmap2::Mmap,
+    last_sequence: std::cell::Cell<u64>,
+}
+
+#[cfg(feature = "shared-memory")]
+impl MappedValues {
+    fn open(path: &std::path::Path) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path)?;
+        // SAFETY: the producer may change the bytes underneath us at any time, which is
+        // the point; they're only ever read with volatile loads and validated by the
+        // sequence counter
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+        if map.len() < SHARED_VALUES_LEN {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!(
+                    "shared value block is {} bytes, expected {SHARED_VALUES_LEN}",
+                    map.len()
+                ),
+            ));
+        }
+        Ok(Self {
+            map,
+            last_sequence: std::cell::Cell::new(0),
+        })
+    }
+
+    fn word(&self, index: usize) -> u64 {
+        // SAFETY: the length was checked on open and mappings are page aligned
+        let word = unsafe { std::ptr::read_volatile(self.map.as_ptr().cast::<u64>().add(index)) };
+        u64::from_le(word)
+    }
+
+    /// The values, if the writer has published new ones since the last call and wasn't
+    /// halfway through changing them while we read
+    fn poll(&self) -> Option<[f64; 5]> {
+        let sequence = self.word(0);
+        if sequence == self.last_sequence.get() || sequence % 2 == 1 {
+            return None;
+        }
+        std::sync::atomic::fence(std::sync::atomic::Ordering::Acquire);
+        let values = [1, 2, 3, 4, 5].map(|index| f64::from_bits(self.word(index)));
+        std::sync::atomic::fence(std::sync::atomic::Ordering::Acquire);
+        if self.word(0) != sequence {
+            return None;
+        }
+        self.last_sequence.set(sequence);
+        Some(values)
+    }
+}
+
+/// Bytes X-Plane reserves for the dataref path in an `RREF` request
+#[cfg(feature = "xplane")]
+const XPLANE_DATAREF_LEN: usize = 400;
+
+/// Streams datarefs from X-Plane over its UDP interface.
+///
+/// Each `(dataref, key)` pair subscribes to one dataref, e.g.
+/// `("sim/cockpit2/gauges/indicators/airspeed_kts_pilot", "primary")`, whose values
+/// then arrive as `InstrumentCommand::SetMany` updates under `key`. Unit conversions
+/// belong in `InstrumentCommand::SetCalibration`. Subscriptions are cancelled on drop.
+#[cfg(feature = "xplane")]
+pub struct XPlaneSource {
+    socket: std::net::UdpSocket,
+    subscriptions: Vec<(String, String)>,
+    rate: u32,
+}
+
+#[cfg(feature = "xplane")]
+impl XPlaneSource {
+    /// Subscribes `rate` times a second through X-Plane's UDP port, 127.0.0.1:49000 on
+    /// the same machine
+    pub fn connect(
+        address: impl std::net::ToSocketAddrs,
+        datarefs: &[(&str, &str)],
+        rate: u32,
+    ) -> std::io::Result<Self> {
+        if let Some((dataref, _)) = datarefs
+            .iter()
+            .find(|(dataref, _)| dataref.len() >= XPLANE_DATAREF_LEN)
+        {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("dataref {dataref:?} is longer than X-Plane accepts"),
+            ));
+        }
+        let socket = std::net::UdpSocket::bind(("0.0.0.0", 0))?;
+        socket.connect(address)?;
+        // Wakes `recv` now and then to re-subscribe, in case X-Plane wasn't running yet
+        socket.set_read_timeout(Some(Duration::from_secs(2)))?;
+        let source = Self {
+            socket,
+            subscriptions: datarefs
+                .iter()
+                .map(|(dataref, key)| (dataref.to_string(), key.to_string()))
+                .collect(),
+            rate,
+        };
+        source.subscribe(rate)?;
+        Ok(source)
+    }
+
+    // "RREF\0", the rate (0 cancels), our index for the dataref, then its NUL-padded path
+    fn subscribe(&self, rate: u32) -> std::io::Result<()> {
+        for (index, (dataref, _)) in self.subscriptions.iter().enumerate() {
+            let mut request = b"RREF\0".to_vec();
+            request.extend_from_slice(&(rate as i32).to_le_bytes());
+            request.extend_from_slice(&(index as i32).to_le_bytes());
+            let mut path = [0u8; XPLANE_DATAREF_LEN];
+            path[..dataref.len()].copy_from_slice(dataref.as_bytes());
+            request.extend_from_slice(&path);
+            self.socket.send(&request)?;
+        }
+        Ok(())
+    }
+
+    /// Blocks until X-Plane sends values for at least one subscribed dataref
+    pub fn recv(&self) -> std::io::Result<InstrumentCommand> {
+        let mut packet = [0u8; 1500];
+        loop {
+            match self.socket.recv(&mut packet) {
+                Ok(len) => {
+                    let values = self.parse(&packet[..len]);
+                    if !values.is_empty() {
+                        return Ok(InstrumentCommand::SetMany(values));
+                    }
+                }
+                Err(error)
+                    if matches!(
+                        error.kind(),
+                        std::io::ErrorKind::WouldBlock
+                            | std::io::ErrorKind::TimedOut
+                            | std::io::ErrorKind::ConnectionRefused
+                    ) =>
+                {
+                    // Still unreachable; the next timeout tries again
+                    let _ = self.subscribe(self.rate);
+                }
+                Err(error) => return Err(error),
+            }
+        }
+    }
+
+    // "RREF", a separator byte, then an index and an `f32` value per dataref
+    fn parse(&self, packet: &[u8]) -> HashMap<String, f64> {
+        let Some(records) = packet.strip_prefix(b"RREF").and_then(|rest| rest.get(1..)) else {
+            return HashMap::new();
+        };
+        records
+            .chunks_exact(8)
+            .filter_map(|record| {
+                let (index, value) = record.split_at(4);
+                let index = i32::from_le_bytes(index.try_into().ok()?);
+                let value = f32::from_le_bytes(value.try_into().ok()?);
+                let (_, key) = self.subscriptions.get(usize::try_from(index).ok()?)?;
+                Some((key.clone(), value as f64))
+            })
+            .collect()
+    }
+
+    /// Forwards values from a background thread, e.g. into
+    /// `Instrument::show_with_commands`, until the socket fails or the receiver is dropped
+    pub fn spawn(self) -> Receiver<InstrumentCommand> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            while let Ok(command) = self.recv() {
+                if sender.send(command).is_err() {
+                    break;
+                }
+            }
+        });
+        receiver
+    }
+}
+
+#[cfg(feature = "xplane")]
+impl Drop for XPlaneSource {
+    fn drop(&mut self) {
+        let _ = self.subscribe(0);
+    }
+}
+
+// `SIMCONNECT_RECV_ID_QUIT` and `SIMCONNECT_RECV_ID_SIMOBJECT_DATA` from SimConnect.h
+#[cfg(feature = "msfs")]
+const SIMCONNECT_RECV_ID_QUIT: u32 = 3;
+#[cfg(feature = "msfs")]
+const SIMCONNECT_RECV_ID_SIMOBJECT_DATA: u32 = 8;
+
+/// Bytes before the first value of a packed `SIMCONNECT_RECV_SIMOBJECT_DATA`
+#[cfg(feature = "msfs")]
+const SIMCONNECT_DATA_OFFSET: usize = 40;
+
+/// How long `SimConnectSource::recv` waits before asking for messages again
+#[cfg(feature = "msfs")]
+const SIMCONNECT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+#[cfg(feature = "msfs")]
+type SimConnectHandle = *mut std::ffi::c_void;
+
+/// Reads simulation variables from Microsoft Flight Simulator through SimConnect.
+///
+/// Each `(variable, units, key)` triple requests one simulation variable of the user
+/// aircraft in the given units, e.g. `("AIRSPEED INDICATED", "knots", "primary")`, whose
+/// values then arrive as `InstrumentCommand::SetMany` updates under `key` whenever they
+/// change. `SimConnect.dll` from the MSFS SDK is loaded at runtime, so it must be next to
+/// the executable or on the `PATH`; it only exists on Windows.
+#[cfg(feature = "msfs")]
+pub struct SimConnectSource {
+    handle: SimConnectHandle,
+    get_next_dispatch: unsafe extern "system" fn(SimConnectHandle, *mut *const u8, *mut u32) -> i32,
+    close: unsafe extern "system" fn(SimConnectHandle) -> i32,
+    keys: Vec<String>,
+    // Declared last so it is unloaded after `Drop` has closed the connection
+    _library: libloading::Library,
+}
+
+// SAFETY: a SimConnect handle may be used from any thread as long as calls don't overlap,
+// which holding the source by value guarantees
+#[cfg(feature = "msfs")]
+unsafe impl Send for SimConnectSource {}
+
+#[cfg(feature = "msfs")]
+impl SimConnectSource {
+    /// Opens a connection named `name`, which fails unless the simulator is running
+    pub fn connect(name: &str, variables: &[(&str, &str, &str)]) -> std::io::Result<Self> {
+        type Open = unsafe extern "system" fn(
+            *mut SimConnectHandle,
+            *const std::ffi::c_char,
+            *mut std::ffi::c_void,
+            u32,
+            *mut std::ffi::c_void,
+            u32,
+        ) -> i32;
+        type AddToDataDefinition = unsafe extern "system" fn(
+            SimConnectHandle,
+            u32,
+            *const std::ffi::c_char,
+            *const std::ffi::c_char,
+            u32,
+            f32,
+            u32,
+        ) -> i32;
+        type RequestDataOnSimObject = unsafe extern "system" fn(
+            SimConnectHandle,
+            u32,
+            u32,
+            u32,
+            u32,
+            u32,
+            u32,
+            u32,
+            u32,
+        ) -> i32;
+        const FLOAT64: u32 = 4;
+        const UNUSED: u32 = u32::MAX;
+        const OBJECT_ID_USER: u32 = 0;
+        const PERIOD_SIM_FRAME: u32 = 3;
+        const FLAG_CHANGED: u32 = 1;
+
+        let c_string = |text: &str| {
+            std::ffi::CString::new(text).map_err(|_| {
+                std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    format!("{text:?} contains a NUL byte"),
+                )
+            })
+        };
+        let failed = |call: &str, result: i32| {
+            std::io::Error::other(format!("{call} failed ({:#010x})", result as u32))
+        };
+
+        // SAFETY: loading runs the DLL's initialisers, which is what SimConnect expects,
+        // and each symbol is given the signature declared in SimConnect.h
+        let (library, open, add_to_data_definition, request_data, get_next_dispatch, close) = unsafe {
+            let library =
+                libloading::Library::new("SimConnect.dll").map_err(std::io::Error::other)?;
+            let open = *library
+                .get::<Open>(b"SimConnect_Open\0")
+                .map_err(std::io::Error::other)?;
+            let add = *library
+                .get::<AddToDataDefinition>(b"SimConnect_AddToDataDefinition\0")
+                .map_err(std::io::Error::other)?;
+            let request = *library
+                .get::<RequestDataOnSimObject>(b"SimConnect_RequestDataOnSimObject\0")
+                .map_err(std::io::Error::other)?;
+            let next = *library
+                .get(b"SimConnect_GetNextDispatch\0")
+                .map_err(std::io::Error::other)?;
+            let close = *library
+                .get(b"SimConnect_Close\0")
+                .map_err(std::io::Error::other)?;
+            (library, open, add, request, next, close)
+        };
+
+        let name = c_string(name)?;
+        let mut handle: SimConnectHandle = std::ptr::null_mut();
+        // SAFETY: `handle` and `name` outlive the call; no window or event is passed
+        let result = unsafe {
+            open(
+                &mut handle,
+                name.as_ptr(),
+                std::ptr::null_mut(),
+                0,
+                std::ptr::null_mut(),
+                0,
+            )
+        };
+        if result < 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::ConnectionRefused,
+                format!("SimConnect_Open failed ({:#010x})", result as u32),
+            ));
+        }
+        // Closes the connection again if a variable is rejected below
+        let source = Self {
+            handle,
+            get_next_dispatch,
+            close,
+            keys: variables
+                .iter()
+                .map(|(_, _, key)| key.to_string())
+                .collect(),
+            _library: library,
+        };
+
+        // Everything goes into definition 0, answered by request 0
+        for (variable, units, _) in variables {
+            let (variable, units) = (c_string(variable)?, c_string(units)?);
+            // SAFETY: the strings outlive the call, which copies them
+            let result = unsafe {
+                add_to_data_definition(
+                    source.handle,
+                    0,
+                    variable.as_ptr(),
+                    units.as_ptr(),
+                    FLOAT64,
+                    0.0,
+                    UNUSED,
+                )
+            };
+            if result < 0 {
+                return Err(failed("SimConnect_AddToDataDefinition", result));
+            }
+        }
+        // SAFETY: plain values only
+        let result = unsafe {
+            request_data(
+                source.handle,
+                0,
+                0,
+                OBJECT_ID_USER,
+                PERIOD_SIM_FRAME,
+                FLAG_CHANGED,
+                0,
+                0,
+                0,
+            )
+        };
+        if result < 0 {
+            return Err(failed("SimConnect_RequestDataOnSimObject", result));
+        }
+        Ok(source)
+    }
+
+    /// Blocks until the simulator sends changed values, or fails once it quits
+    pub fn recv(&self) -> std::io::Result<InstrumentCommand> {
+        loop {
+            let mut data: *const u8 = std::ptr::null();
+            let mut len = 0u32;
+            // SAFETY: SimConnect owns the message, which stays valid until the next call
+            let message = unsafe {
+                if (self.get_next_dispatch)(self.handle, &mut data, &mut len) < 0 || data.is_null()
+                {
+                    None
+                } else {
+                    Some(std::slice::from_raw_parts(data, len as usize))
+                }
+            };
+            let Some(message) = message else {
+                // Nothing queued yet
+                std::thread::sleep(SIMCONNECT_POLL_INTERVAL);
+                continue;
+            };
+            match simconnect_word(message, 2) {
+                Some(SIMCONNECT_RECV_ID_QUIT) => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::ConnectionAborted,
+                        "the simulator closed the connection",
+                    ));
+                }
+                Some(SIMCONNECT_RECV_ID_SIMOBJECT_DATA) => {
+                    let values = self.parse(message);
+                    if !values.is_empty() {
+                        return Ok(InstrumentCommand::SetMany(values));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // A packed header whose fourth word is the request id, then one `f64` per variable
+    // in definition order
+    fn parse(&self, message: &[u8]) -> HashMap<String, f64> {
+        if simconnect_word(message, 3) != Some(0) {
+            return HashMap::new();
+        }
+        let Some(values) = message.get(SIMCONNECT_DATA_OFFSET..) else {
+            return HashMap::new();
+        };
+        values
+            .chunks_exact(8)
+            .zip(&self.keys)
+            .filter_map(|(value, key)| {
+                let value = f64::from_le_bytes(value.try_into().ok()?);
+                value.is_finite().then(|| (key.clone(), value))
+            })
+            .collect()
+    }
+
+    /// Forwards values from a background thread, e.g. into
+    /// `Instrument::show_with_commands`, until the simulator quits or the receiver is
+    /// dropped
+    pub fn spawn(self) -> Receiver<InstrumentCommand> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            while let Ok(command) = self.recv() {
+                if sender.send(command).is_err() {
+                    break;
+                }
+            }
+        });
+        receiver
+    }
+}
+
+#[cfg(feature = "msfs")]
+impl Drop for SimConnectSource {
+    fn drop(&mut self) {
+        // SAFETY: the handle came from a successful `SimConnect_Open` and isn't used again
+        unsafe {
+            (self.close)(self.handle);
+        }
+    }
+}
+
+// The `index`th little-endian `DWORD` of a SimConnect message
+#[cfg(feature = "msfs")]
+fn simconnect_word(message: &[u8], index: usize) -> Option<u32> {
+    let bytes = message.get(index * 4..index * 4 + 4)?;
+    Some(u32::from_le_bytes(bytes.try_into().ok()?))
+}
+
+/// A mode 01 OBD-II parameter, decoded into the units given
+#[cfg(feature = "obd")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObdPid {
+    /// Percent
+    EngineLoad,
+    /// °C
+    CoolantTemp,
+    /// kPa, absolute
+    IntakePressure,
+    /// Revolutions per minute
+    EngineRpm,
+    /// km/h
+    VehicleSpeed,
+    /// °C
+    IntakeTemp,
+    /// Percent
+    Throttle,
+    /// Percent
+    FuelLevel,
+}
+
+#[cfg(feature = "obd")]
+impl ObdPid {
+    fn code(self) -> u8 {
+        match self {
+            ObdPid::EngineLoad => 0x04,
+            ObdPid::CoolantTemp => 0x05,
+            ObdPid::IntakePressure => 0x0b,
+            ObdPid::EngineRpm => 0x0c,
+            ObdPid::VehicleSpeed => 0x0d,
+            ObdPid::IntakeTemp => 0x0f,
+            ObdPid::Throttle => 0x11,
+            ObdPid::FuelLevel => 0x2f,
+        }
+    }
+
+    // Formulas from SAE J1979, on the data bytes after the mode and PID echo
+    fn decode(self, data: &[u8]) -> Option<f64> {
+        let a = f64::from(*data.first()?);
+        Some(match self {
+            ObdPid::EngineLoad | ObdPid::Throttle | ObdPid::FuelLevel => a * 100.0 / 255.0,
+            ObdPid::CoolantTemp | ObdPid::IntakeTemp => a - 40.0,
+            ObdPid::IntakePressure | ObdPid::VehicleSpeed => a,
+            ObdPid::EngineRpm => (a * 256.0 + f64::from(*data.get(1)?)) / 4.0,
+        })
+    }
+}
+
+/// Polls an ELM327 OBD-II adapter for vehicle data.
+///
+/// Each `(pid, key)` pair is read once per poll and sent under `key`, as in
+/// `InstrumentCommand::SetMany`; PIDs the car doesn't answer are left out. Serial and
+/// Bluetooth adapters appear as a device such as `/dev/ttyUSB0` or `/dev/rfcomm0`, set to
+/// the adapter's baud rate beforehand (e.g. with `stty`); Wi-Fi adapters listen on TCP.
+#[cfg(feature = "obd")]
+pub struct ObdSource<P> {
+    port: P,
+    pids: Vec<(ObdPid, String)>,
+}
+
+/// How long an adapter may stay silent before a command fails
+#[cfg(feature = "obd")]
+const OBD_READ_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[cfg(feature = "obd")]
+impl ObdSource<std::fs::File> {
+    /// Switches a serial device to raw mode with reads that time out, keeping its baud rate
+    pub fn open(
+        path: impl AsRef<std::path::Path>,
+        pids: &[(ObdPid, &str)],
+    ) -> std::io::Result<Self> {
+        let port = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)?;
+        #[cfg(unix)]
+        set_serial_timeout(&port, OBD_READ_TIMEOUT)?;
+        Self::new(port, pids)
+    }
+}
+
+// Raw mode, so the `>` prompt arrives without waiting for a newline, and reads that
+// return nothing after `timeout` rather than blocking on a silent adapter. Anything but a
+// terminal is left as it is.
+#[cfg(all(feature = "obd", unix))]
+fn set_serial_timeout(port: &std::fs::File, timeout: Duration) -> std::io::Result<()> {
+    use std::os::fd::AsRawFd;
+    let fd = port.as_raw_fd();
+    // SAFETY: `termios` is plain data, filled in by `tcgetattr` before it is used, and
+    // `fd` stays open for both calls
+    unsafe {
+        let mut termios = std::mem::zeroed::<libc::termios>();
+        if libc::tcgetattr(fd, &mut termios) != 0 {
+            let error = std::io::Error::last_os_error();
+            return match error.raw_os_error() {
+                Some(libc::ENOTTY) => Ok(()),
+                _ => Err(error),
+            };
+        }
+        libc::cfmakeraw(&mut termios);
+        termios.c_cc[libc::VMIN] = 0;
+        // In tenths of a second
+        termios.c_cc[libc::VTIME] = (timeout.as_millis() / 100).clamp(1, 255) as libc::cc_t;
+        if libc::tcsetattr(fd, libc::TCSANOW, &termios) != 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+#[cfg(feature = "obd")]
+impl ObdSource<std::net::TcpStream> {
+    /// For Wi-Fi adapters, which usually listen on 192.168.0.10:35000
+    pub fn connect(
+        address: impl std::net::ToSocketAddrs,
+        pids: &[(ObdPid, &str)],
+    ) -> std::io::Result<Self> {
+        let port = std::net::TcpStream::connect(address)?;
+        port.set_read_timeout(Some(OBD_READ_TIMEOUT))?;
+        Self::new(port, pids)
+    }
+}
+
+#[cfg(feature = "obd")]
+impl<P: std::io::Read + std::io::Write> ObdSource<P> {
+    /// Resets the adapter on `port` and lets it pick the car's protocol
+    pub fn new(port: P, pids: &[(ObdPid, &str)]) -> std::io::Result<Self> {
+        let mut source = Self {
+            port,
+            pids: pids
+                .iter()
+                .map(|(pid, key)| (*pid, key.to_string()))
+                .collect(),
+        };
+        // Reset, then echo, linefeeds and headers off, and automatic protocol
+        for command in ["ATZ", "ATE0", "ATL0", "ATH0", "ATSP0"] {
+            source.command(command)?;
+        }
+        Ok(source)
+    }
+
+    // Sends one command and collects the reply up to the adapter's `>` prompt
+    fn command(&mut self, command: &str) -> std::io::Result<String> {
+        self.port.write_all(format!("{command}\r").as_bytes())?;
+        self.port.flush()?;
+        let mut reply = Vec::new();
+        let mut byte = [0u8];
+        loop {
+            // End of stream, or a serial port's read timeout
+            if self.port.read(&mut byte)? == 0 {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::UnexpectedEof,
+                    "the adapter stopped answering",
+                ));
+            }
+            if byte[0] == b'>' {
+                break;
+            }
+            reply.push(byte[0]);
+        }
+        Ok(String::from_utf8_lossy(&reply).into_owned())
+    }
+
+    // The data bytes of the first line answering `pid`, skipping "SEARCHING...",
+    // "NO DATA" and other status lines
+    fn query(&mut self, pid: ObdPid) -> std::io::Result<Option<f64>> {
+        let reply = self.command(&format!("01{:02X}", pid.code()))?;
+        let echo = format!("41{:02X}", pid.code());
+        let value = reply.split(['\r', '\n']).find_map(|line| {
+            let hex: String = line.split_whitespace().collect();
+            let data = hex.to_ascii_uppercase().strip_prefix(&echo)?.to_string();
+            // Line noise rejects the line rather than being misread as a digit
+            let digit = |byte: u8| (byte as char).to_digit(16);
+            let bytes = data
+                .as_bytes()
+                .chunks_exact(2)
+                .map(|pair| Some((digit(pair[0])? * 16 + digit(pair[1])?) as u8))
+                .collect::<Option<Vec<u8>>>()?;
+            pid.decode(&bytes)
+        });
+        Ok(value)
+    }
+
+    /// Reads every PID once
+    pub fn poll(&mut self) -> std::io::Result<InstrumentCommand> {
+        let mut values = HashMap::new();
+        for (pid, key) in self.pids.clone() {
+            if let Some(value) = self.query(pid)? {
+                values.insert(key, value);
+            }
+        }
+        Ok(InstrumentCommand::SetMany(values))
+    }
+
+    /// Polls from a background thread every `interval`, or as fast as the adapter
+    /// answers, until the port fails or the receiver is dropped
+    pub fn spawn(mut self, interval: Duration) -> Receiver<InstrumentCommand>
+    where
+        P: Send + 'static,
+    {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || loop {
+            let started = Instant::now();
+            let Ok(command) = self.poll() else {
+                break;
+            };
+            if sender.send(command).is_err() {
+                break;
+            }
+            std::thread::sleep(interval.saturating_sub(started.elapsed()));
+        });
+        receiver
+    }
+}
+
+/// A figure about the machine the instrument runs on
+#[cfg(feature = "system-metrics")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SystemMetric {
+    /// Percent, averaged over all cores
+    CpuLoad,
+    /// Percent of physical memory in use
+    MemoryUsed,
+    /// °C at the hottest sensor
+    Temperature,
+    /// kB/s received and sent over all interfaces
+    NetworkThroughput,
+}
+
+#[cfg(feature = "system-metrics")]
+impl SystemMetric {
+    pub const ALL: [SystemMetric; 4] = [
+        SystemMetric::CpuLoad,
+        SystemMetric::MemoryUsed,
+        SystemMetric::Temperature,
+        SystemMetric::NetworkThroughput,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|metric| metric.name() == name)
+    }
+
+    /// The name used by the command line's `--source`
+    pub fn name(self) -> &'static str {
+        match self {
+            SystemMetric::CpuLoad => "cpu",
+            SystemMetric::MemoryUsed => "memory",
+            SystemMetric::Temperature => "temperature",
+            SystemMetric::NetworkThroughput => "network",
+        }
+    }
+
+    pub fn unit(self) -> &'static str {
+        match self {
+            SystemMetric::CpuLoad => "CPU %",
+            SystemMetric::MemoryUsed => "MEMORY %",
+            SystemMetric::Temperature => "°C",
+            SystemMetric::NetworkThroughput => "kB/s",
+        }
+    }
+}
+
+/// Samples CPU, memory, temperature and network figures of this machine.
+///
+/// Each `(metric, key)` pair is read on every poll and sent under `key`, as in
+/// `InstrumentCommand::SetMany`. Temperature is left out where no sensor is readable.
+#[cfg(feature = "system-metrics")]
+pub struct SystemSource {
+    system: sysinfo::System,
+    components: sysinfo::Components,
+    networks: sysinfo::Networks,
+    metrics: Vec<(SystemMetric, String)>,
+    last_poll: Instant,
+}
+
+#[cfg(feature = "system-metrics")]
+impl SystemSource {
+    pub fn new(metrics: &[(SystemMetric, &str)]) -> Self {
+        // CPU load and network traffic are measured between refreshes, so take a first
+        // sample now
+        let mut system = sysinfo::System::new();
+        system.refresh_cpu_usage();
+        Self {
+            system,
+            components: sysinfo::Components::new_with_refreshed_list(),
+            networks: sysinfo::Networks::new_with_refreshed_list(),
+            metrics: metrics
+                .iter()
+                .map(|(metric, key)| (*metric, key.to_string()))
+                .collect(),
+            last_poll: Instant::now(),
+        }
+    }
+
+    /// Reads every metric, with CPU load and throughput averaged since the last poll
+    pub fn poll(&mut self) -> InstrumentCommand {
+        let seconds = self.last_poll.elapsed().as_secs_f64();
+        self.last_poll = Instant::now();
+        self.system.refresh_cpu_usage();
+        self.system.refresh_memory();
+        self.components.refresh(false);
+        self.networks.refresh(true);
+        let values = self
+            .metrics
+            .iter()
+            .filter_map(|(metric, key)| Some((key.clone(), self.read(*metric, seconds)?)))
+            .collect();
+        InstrumentCommand::SetMany(values)
+    }
+
+    fn read(&self, metric: SystemMetric, seconds: f64) -> Option<f64> {
+        match metric {
+            SystemMetric::CpuLoad => Some(f64::from(self.system.global_cpu_usage())),
+            SystemMetric::MemoryUsed => {
+                let total = self.system.total_memory();
+                (total > 0).then(|| self.system.used_memory() as f64 * 100.0 / total as f64)
+            }
+            SystemMetric::Temperature => self
+                .components
+                .iter()
+                .filter_map(|component| component.temperature())
+                .filter(|celsius| celsius.is_finite())
+                .reduce(f32::max)
+                .map(f64::from),
+            SystemMetric::NetworkThroughput => {
+                let bytes: u64 = self
+                    .networks
+                    .values()
+                    .map(|data| data.received() + data.transmitted())
+                    .sum();
+                (seconds > 0.0).then(|| bytes as f64 / 1000.0 / seconds)
+            }
+        }
+    }
+
+    /// Polls from a background thread every `interval` until the receiver is dropped
+    pub fn spawn(mut self, interval: Duration) -> Receiver<InstrumentCommand> {
+        // Shorter intervals than this give meaningless CPU figures
+        let interval = interval.max(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || loop {
+            std::thread::sleep(interval);
+            if sender.send(self.poll()).is_err() {
+                break;
+            }
+        });
+        receiver
+    }
+}
+
+/// What an audio meter reads from one input channel, in dBFS
+#[cfg(feature = "audio")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioLevel {
+    /// Average power over the interval, as on a VU meter; a full-scale sine reads -3
+    Rms,
+    /// Loudest single sample over the interval
+    Peak,
+}
+
+/// Meters an audio input device.
+///
+/// Each `(channel, level, key)` is measured over every interval and sent under `key`,
+/// as in `InstrumentCommand::SetMany`, in dBFS down to a floor of -96. A stereo VU meter
+/// puts channel 0 on `"primary"` and channel 1 on `"secondary"` over a -40..3 range.
+#[cfg(feature = "audio")]
+pub struct AudioSource {
+    device: Option<String>,
+    levels: Vec<(usize, AudioLevel, String)>,
+}
+
+#[cfg(feature = "audio")]
+impl AudioSource {
+    /// Listens on the first input device whose name contains `device`, or on the
+    /// system's default input
+    pub fn new(device: Option<&str>, levels: &[(usize, AudioLevel, &str)]) -> Self {
+        Self {
+            device: device.map(str::to_string),
+            levels: levels
+                .iter()
+                .map(|(channel, level, key)| (*channel, *level, key.to_string()))
+                .collect(),
+        }
+    }
+
+    /// Names of the input devices `new` can select
+    pub fn devices() -> std::io::Result<Vec<String>> {
+        use cpal::traits::{DeviceTrait, HostTrait};
+        let devices = cpal::default_host()
+            .input_devices()
+            .map_err(std::io::Error::other)?;
+        Ok(devices.filter_map(|device| device.name().ok()).collect())
+    }
+
+    /// Starts recording and sends the levels every `interval` until the receiver is
+    /// dropped. Fails if the device can't be opened.
+    pub fn spawn(self, interval: Duration) -> std::io::Result<Receiver<InstrumentCommand>> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let (opened, result) = std::sync::mpsc::sync_channel(1);
+        std::thread::spawn(move || {
+            // Streams aren't Send on every platform, so this thread owns it throughout
+            let meters = std::sync::Arc::new(Mutex::new(Vec::new()));
+            let stream = match self.open(meters.clone()) {
+                Ok(stream) => stream,
+                Err(err) => {
+                    let _ = opened.send(Err(err));
+                    return;
+                }
+            };
+            let _ = opened.send(Ok(()));
+            loop {
+                std::thread::sleep(interval);
+                let measured: Vec<ChannelMeter> = std::mem::take(&mut *meters.lock().unwrap());
+                let values = self
+                    .levels
+                    .iter()
+                    .filter_map(|(channel, level, key)| {
+                        Some((key.clone(), measured.get(*channel)?.decibels(*level)))
+                    })
+                    .collect();
+                if sender.send(InstrumentCommand::SetMany(values)).is_err() {
+                    break;
+                }
+            }
+            drop(stream);
+        });
+        result
+            .recv()
+            .map_err(|_| std::io::Error::other("audio thread exited"))??;
+        Ok(receiver)
+    }
+
+    fn open(
+        &self,
+        meters: std::sync::Arc<Mutex<Vec<ChannelMeter>>>,
+    ) -> std::io::Result<cpal::Stream> {
+        use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+        use cpal::SampleFormat;
+        let host = cpal::default_host();
+        let device = match &self.device {
+            Some(name) => host
+                .input_devices()
+                .map_err(std::io::Error::other)?
+                .find(|device| device.name().is_ok_and(|n| n.contains(name.as_str()))),
+            None => host.default_input_device(),
+        }
+        .ok_or_else(|| std::io::Error::new(std::io::ErrorKind::NotFound, "no audio input"))?;
+        let supported = device
+            .default_input_config()
+            .map_err(std::io::Error::other)?;
+        let config = supported.config();
+        let stream = match supported.sample_format() {
+            SampleFormat::F32 => meter_stream::<f32>(&device, &config, meters),
+            SampleFormat::I16 => meter_stream::<i16>(&device, &config, meters),
+            SampleFormat::U16 => meter_stream::<u16>(&device, &config, meters),
+            SampleFormat::I32 => meter_stream::<i32>(&device, &config, meters),
+            SampleFormat::I8 => meter_stream::<i8>(&device, &config, meters),
+            SampleFormat::U8 => meter_stream::<u8>(&device, &config, meters),
+            format => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::Unsupported,
+                    format!("unsupported sample format {format}"),
+                ))
+            }
+        }
+        .map_err(std::io::Error::other)?;
+        stream.play().map_err(std::io::Error::other)?;
+        Ok(stream)
+    }
+}
+
+/// Running totals for one channel since the levels were last sent
+#[cfg(feature = "audio")]
+#[derive(Debug, Clone, Copy, Default)]
+struct ChannelMeter {
+    sum_of_squares: f64,
+    samples: usize,
+    peak: f32,
+}
+
+#[cfg(feature = "audio")]
+impl ChannelMeter {
+    fn add(&mut self, sample: f32) {
+        self.sum_of_squares += f64::from(sample) * f64::from(sample);
+        self.samples += 1;
+        self.peak = self.peak.max(sample.abs());
+    }
+
+    fn decibels(&self, level: AudioLevel) -> f64 {
+        let amplitude = match level {
+            AudioLevel::Rms if self.samples > 0 => {
+                (self.sum_of_squares / self.samples as f64).sqrt()
+            }
+            AudioLevel::Rms => 0.0,
+            AudioLevel::Peak => f64::from(self.peak),
+        };
+        (20.0 * amplitude.log10()).max(-96.0)
+    }
+}
+
+/// Builds an input stream that folds interleaved samples of type `T` into `meters`
+#[cfg(feature = "audio")]
+fn meter_stream<T>(
+    device: &cpal::Device,
+    config: &cpal::StreamConfig,
+    meters: std::sync::Arc<Mutex<Vec<ChannelMeter>>>,
+) -> Result<cpal::Stream, cpal::BuildStreamError>
+where
+    T: cpal::SizedSample,
+    f32: cpal::FromSample<T>,
+{
+    use cpal::traits::DeviceTrait;
+    use cpal::Sample;
+    let channels = usize::from(config.channels).max(1);
+    device.build_input_stream(
+        config,
+        move |data: &[T], _: &cpal::InputCallbackInfo| {
+            let mut meters = meters.lock().unwrap();
+            meters.resize(channels, ChannelMeter::default());
+            for frame in data.chunks(channels) {
+                for (meter, sample) in meters.iter_mut().zip(frame) {
+                    meter.add(f32::from_sample(*sample));
+                }
+            }
+        },
+        // Dropouts show up as a quiet interval, which is all a meter can do about them
+        |_| {},
+        None,
+    )
+}
+
+/// An analog input, read in volts
+#[cfg(feature = "adc")]
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnalogInput {
+    /// Single-ended input 0–3 of an ADS1115 on an I2C bus such as `/dev/i2c-1`, where
+    /// the chip usually sits at address 0x48. Reads up to 4.096 V.
+    Ads1115 {
+        bus: std::path::PathBuf,
+        address: u16,
+        channel: u8,
+    },
+    /// A voltage channel of a Linux IIO driver, e.g. channel 0 of
+    /// `/sys/bus/iio/devices/iio:device0` for its `in_voltage0_raw`
+    Iio {
+        device: std::path::PathBuf,
+        channel: u32,
+    },
+}
+
+/// Reads analog sensors wired to the machine, such as a Raspberry Pi with an ADC.
+///
+/// Each `(input, key)` pair is read on every poll and sent under `key`, as in
+/// `InstrumentCommand::SetMany`, in volts unless `scale` maps it to the sensor's units.
+#[cfg(feature = "adc")]
+pub struct AnalogSource {
+    channels: Vec<AnalogChannel>,
+}
+
+#[cfg(feature = "adc")]
+struct AnalogChannel {
+    reader: AnalogReader,
+    key: String,
+    scale: Option<((f64, f64), (f64, f64))>,
+}
+
+#[cfg(feature = "adc")]
+enum AnalogReader {
+    Ads1115(i2cdev::linux::LinuxI2CDevice, u8),
+    // Volts are (raw + offset) * millivolts / 1000
+    Iio {
+        raw: std::path::PathBuf,
+        offset: f64,
+        millivolts: f64,
+    },
+}
+
+#[cfg(feature = "adc")]
+impl AnalogSource {
+    pub fn open(inputs: &[(AnalogInput, &str)]) -> std::io::Result<Self> {
+        let channels = inputs
+            .iter()
+            .map(|(input, key)| {
+                let reader = match input {
+                    AnalogInput::Ads1115 {
+                        bus,
+                        address,
+                        channel,
+                    } => {
+                        let device = i2cdev::linux::LinuxI2CDevice::new(bus, *address)?;
+                        AnalogReader::Ads1115(device, channel % 4)
+                    }
+                    AnalogInput::Iio { device, channel } => {
+                        // Drivers give either a per-channel or a shared scale and offset
+                        let attribute = |name: &str, default: f64| {
+                            [
+                                format!("in_voltage{channel}_{name}"),
+                                format!("in_voltage_{name}"),
+                            ]
+                            .iter()
+                            .find_map(|file| {
+                                std::fs::read_to_string(device.join(file))
+                                    .ok()?
+                                    .trim()
+                                    .parse()
+                                    .ok()
+                            })
+                            .unwrap_or(default)
+                        };
+                        AnalogReader::Iio {
+                            raw: device.join(format!("in_voltage{channel}_raw")),
+                            offset: attribute("offset", 0.0),
+                            millivolts: attribute("scale", 1.0),
+                        }
+                    }
+                };
+                Ok(AnalogChannel {
+                    reader,
+                    key: key.to_string(),
+                    scale: None,
+                })
+            })
+            .collect::<std::io::Result<_>>()?;
+        Ok(Self { channels })
+    }
+
+    /// Maps the volts sent under `key` linearly from `volts` onto `values`, e.g. a
+    /// 0.5–4.5 V pressure sender with `(0.5, 4.5)` and `(0.0, 100.0)`
+    pub fn scale(mut self, key: &str, volts: (f64, f64), values: (f64, f64)) -> Self {
+        for channel in self
+            .channels
+            .iter_mut()
+            .filter(|channel| channel.key == key)
+        {
+            channel.scale = Some((volts, values));
+        }
+        self
+    }
+
+    /// Reads every input once
+    pub fn poll(&mut self) -> std::io::Result<InstrumentCommand> {
+        let mut values = HashMap::new();
+        for channel in &mut self.channels {
+            let volts = channel.reader.read()?;
+            let value = match channel.scale {
+                Some(((v0, v1), (x0, x1))) if v1 != v0 => x0 + (volts - v0) / (v1 - v0) * (x1 - x0),
+                _ => volts,
+            };
+            values.insert(channel.key.clone(), value);
+        }
+        Ok(InstrumentCommand::SetMany(values))
+    }
+
+    /// Polls from a background thread every `interval` until an input fails or the
+    /// receiver is dropped
+    pub fn spawn(mut self, interval: Duration) -> Receiver<InstrumentCommand> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        std::thread::spawn(move || loop {
+            let started = Instant::now();
+            let Ok(command) = self.poll() else {
+                break;
+            };
+            if sender.send(command).is_err() {
+                break;
+            }
+            std::thread::sleep(interval.saturating_sub(started.elapsed()));
+        });
+        receiver
+    }
+}
+
+#[cfg(feature = "adc")]
+impl AnalogReader {
+    fn read(&mut self) -> std::io::Result<f64> {
+        match self {
+            AnalogReader::Ads1115(device, channel) => read_ads1115(device, *channel),
+            AnalogReader::Iio {
+                raw,
+                offset,
+                millivolts,
+            } => {
+                let raw: f64 = std::fs::read_to_string(raw)?
+                    .trim()
+                    .parse()
+                    .map_err(|_| std::io::Error::from(std::io::ErrorKind::InvalidData))?;
+                Ok((raw + *offset) * *millivolts / 1000.0)
+            }
+        }
+    }
+}
+
+/// Runs one single-shot conversion of `channel` against ground at the ±4.096 V range
+#[cfg(feature = "adc")]
+fn read_ads1115(device: &mut i2cdev::linux::LinuxI2CDevice, channel: u8) -> std::io::Result<f64> {
+    use i2cdev::core::I2CDevice;
+    const CONVERSION: u8 = 0x00;
+    const CONFIG: u8 = 0x01;
+    // Start a conversion, input multiplexer 4 + channel, gain ±4.096 V, single-shot
+    // mode, 128 samples/s and the comparator off
+    let config = 0x8000 | (4 + u16::from(channel)) << 12 | 0x0200 | 0x0100 | 0x0080 | 0x0003;
+    let [high, low] = u16::to_be_bytes(config);
+    device.write(&[CONFIG, high, low])?;
+    let mut register = [0; 2];
+    // A conversion takes about 8 ms at this rate; the top bit of the config register
+    // is set again once it's done
+    for _ in 0..50 {
+        std::thread::sleep(Duration::from_millis(1));
+        device.write(&[CONFIG])?;
+        device.read(&mut register)?;
+        if register[0] & 0x80 != 0 {
+            device.write(&[CONVERSION])?;
+            device.read(&mut register)?;
+            return Ok(f64::from(i16::from_be_bytes(register)) * 4.096 / 32768.0);
+        }
+    }
+    Err(std::io::ErrorKind::TimedOut.into())
+}
+
+// ============================================================================
+// EMBEDDING
+// ============================================================================
+
+/// Drives an instrument without a window; see [`Instrument::renderer`]
+pub struct Renderer {
+    config: InstrumentConfig,
+    state: AppState,
+}
+
+impl Renderer {
+    pub fn apply(&mut self, command: InstrumentCommand) {
+        self.state.apply_command(command);
+    }
+
+    /// The recorded value history, as `InstrumentCommand::ExportHistory` would send it
+    pub fn history(&self, format: HistoryFormat) -> String {
+        format_history(self.state.history.iter(), format)
+    }
+
+    /// Id of the latest `InstrumentCommand::Sequence` applied. The renderer doesn't know
+    /// when the caller presents, so it never calls the sequence hook itself
+    pub fn last_sequence(&self) -> Option<u64> {
+        self.state.sequence
+    }
+
+    /// Advances needles and animations to now and draws into a caller-owned buffer of
+    /// exactly `width * height` pixels in `pixel_format`
+    pub fn render_into(&mut self, frame: &mut [u8], width: usize, height: usize) {
+        check_frame_size(frame, width, height, &self.config);
+        self.state.update();
+        let elapsed = self.state.elapsed();
+        render_frame(
+            frame,
+            width,
+            height,
+            &self.state,
+            &self.config,
+            elapsed,
+            None,
+        );
+        if let Some(ref hook) = self.config.frame_hook {
+            (hook.0)(frame, width, height);
+        }
+    }
+}
+
+/// Size in bytes of the header in front of the pixels of a shared frame: four
+/// little-endian 64-bit words.
+///
+/// Word 0 is a sequence counter, odd while a frame is being written; words 1 and 2 are
+/// the width and height; word 3 is the pixel format: 0 for RGBA8, 1 for BGRA8, 2 for
+/// RGB565 and 3 for Gray8. The pixels follow, row by row.
+pub const SHARED_FRAME_HEADER_LEN: usize = 32;
+
+/// A frame published through a memory-mapped file, for compositors in other processes
+#[cfg(feature = "shared-memory")]
+pub struct SharedFrame {
+    map: memmap2::MmapMut,
+    width: usize,
+    height: usize,
+    format: PixelFormat,
+    sequence: u64,
+}
+
+#[cfg(feature = "shared-memory")]
+impl SharedFrame {
+    /// Creates or truncates `path` to hold one `width` x `height` RGBA8 frame
+    pub fn create(
+        path: impl AsRef<std::path::Path>,
+        width: usize,
+        height: usize,
+    ) -> std::io::Result<Self> {
+        Self::create_with_format(path, width, height, PixelFormat::Rgba8)
+    }
+
+    /// Like [`create`](Self::create), for renderers set to another
+    /// [`InstrumentConfig::pixel_format`]
+    pub fn create_with_format(
+        path: impl AsRef<std::path::Path>,
+        width: usize,
+        height: usize,
+        format: PixelFormat,
+    ) -> std::io::Result<Self> {
+        let file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(path)?;
+        let pixels_len = width * height * format.bytes_per_pixel();
+        file.set_len((SHARED_FRAME_HEADER_LEN + pixels_len) as u64)?;
+        // SAFETY: readers in other processes only ever see this through the sequence
+        // protocol, and nothing in this process aliases the mapping
+        let mut map = unsafe { memmap2::MmapMut::map_mut(&file)? };
+        let format_code: u64 = match format {
+            PixelFormat::Rgba8 => 0,
+            PixelFormat::Bgra8 => 1,
+            PixelFormat::Rgb565 => 2,
+            PixelFormat::Gray8 => 3,
+        };
+        map[8..16].copy_from_slice(&(width as u64).to_le_bytes());
+        map[16..24].copy_from_slice(&(height as u64).to_le_bytes());
+        map[24..32].copy_from_slice(&format_code.to_le_bytes());
+        Ok(Self {
+            map,
+            width,
+            height,
+            format,
+            sequence: 0,
+        })
+    }
+
+    /// Renders the next frame straight into the mapping. Fails, leaving the last frame
+    /// in place, if the renderer draws in a different pixel format than the mapping's.
+    pub fn publish(&mut self, renderer: &mut Renderer) -> std::io::Result<()> {
+        if renderer.config.pixel_format != self.format {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "renderer draws {:?} pixels but the shared frame holds {:?}",
+                    renderer.config.pixel_format, self.format
+                ),
+            ));
+        }
+        self.set_sequence(self.sequence + 1);
+        std::sync::atomic::fence(std::sync::atomic::Ordering::Release);
+        let (width, height) = (self.width, self.height);
+        renderer.render_into(&mut self.map[SHARED_FRAME_HEADER_LEN..], width, height);
+        std::sync::atomic::fence(std::sync::atomic::Ordering::Release);
+        self.set_sequence(self.sequence + 1);
+        Ok(())
+    }
+
+    fn set_sequence(&mut self, sequence: u64) {
+        self.sequence = sequence;
+        self.map[..8].copy_from_slice(&sequence.to_le_bytes());
+    }
+}
+
+// ============================================================================
+// LINUX FRAMEBUFFER
+// ============================================================================
+
+#[cfg(target_os = "linux")]
+struct Framebuffer {
+    file: std::fs::File,
+    width: usize,
+    height: usize,
+    format: PixelFormat,
+    stride: usize,
+    bytes: Vec<u8>,
+}
+
+#[cfg(target_os = "linux")]
+impl Framebuffer {
+    // Geometry comes from sysfs, which spares us the fbdev ioctls
+    fn open(device: &std::path::Path) -> std::io::Result<Self> {
+        let invalid =
+            |message: String| std::io::Error::new(std::io::ErrorKind::InvalidData, message);
+        let name = device
+            .file_name()
+            .and_then(|name| name.to_str())
+            .ok_or_else(|| invalid(format!("{} is not a device path", device.display())))?;
+        let attribute = |key: &str| {
+            std::fs::read_to_string(format!("/sys/class/graphics/{name}/{key}"))
+                .map(|value| value.trim().to_string())
+        };
+        let number = |value: &str| {
+            value
+                .parse::<usize>()
+                .map_err(|_| invalid(format!("unexpected framebuffer attribute {value:?}")))
+        };
+        let size = attribute("virtual_size")?;
+        let (width, height) = size
+            .split_once(',')
+            .ok_or_else(|| invalid(format!("unexpected framebuffer size {size:?}")))?;
+        let (width, height) = (number(width)?, number(height)?);
+        let format = match number(&attribute("bits_per_pixel")?)? {
+            32 => PixelFormat::Bgra8,
+            16 => PixelFormat::Rgb565,
+            bits => return Err(invalid(format!("unsupported {bits}-bit framebuffer"))),
+        };
+        let stride = number(&attribute("stride")?)?;
+        let file = std::fs::OpenOptions::new().write(true).open(device)?;
+        Ok(Self {
+            file,
+            width,
+            height,
+            format,
+            stride,
+            bytes: vec![0; stride * height],
+        })
+    }
+
+    // `frame` is already in the device's format, so only the row padding needs adding
+    fn present(&mut self, frame: &[u8]) -> std::io::Result<()> {
+        use std::os::unix::fs::FileExt;
+        trace_span!("present");
+        let row = self.width * self.format.bytes_per_pixel();
+        if row == self.stride {
+            return self.file.write_all_at(frame, 0);
+        }
+        for (src, dst) in frame
+            .chunks_exact(row)
+            .zip(self.bytes.chunks_exact_mut(self.stride))
+        {
+            dst[..row].copy_from_slice(src);
+        }
+        self.file.write_all_at(&self.bytes, 0)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Instrument {
+    fn run_framebuffer(
+        &self,
+        device: &std::path::Path,
+        input: Input,
+    ) -> Result<(), InstrumentError> {
+        self.config.validate()?;
+        try_text_shaper(&self.config)?;
+        let mut framebuffer = Framebuffer::open(device)?;
+        let (width, height) = (framebuffer.width, framebuffer.height);
+        let mut renderer = self.renderer();
+        renderer.config.pixel_format = framebuffer.format;
+        renderer.state.restore_state(&self.config);
+        let mut frame = vec![0u8; width * height * framebuffer.format.bytes_per_pixel()];
+        let frame_duration = Duration::from_secs_f64(1.0 / self.config.max_framerate);
+        let mut last_saved = Instant::now();
+        loop {
+            let frame_started = Instant::now();
+            if last_saved.elapsed() >= STATE_SAVE_INTERVAL {
+                renderer.state.save_state(&self.config);
+                last_saved = frame_started;
+            }
+            input.apply_pending(&mut renderer.state);
+            renderer.render_into(&mut frame, width, height);
+            framebuffer.present(&frame)?;
+            renderer.state.acknowledge_presented(&renderer.config);
+            // No vsync to lean on, so pace ourselves
+            if let Some(remaining) = frame_duration.checked_sub(frame_started.elapsed()) {
+                std::thread::sleep(remaining);
+            }
+        }
+    }
+}
+
+// ============================================================================
+// DASHBOARD
+// ============================================================================
+
+/// Several instruments sharing one window in a near-square grid, filled left to right
+/// and top to bottom. Cells are sized from the largest `window_width` and
+/// `window_height` among the instruments. `place` pins a gauge to a cell; the rest
+/// fill the free cells in order.
+#[derive(Debug, Clone)]
+pub struct Dashboard {
+    title: String,
+    instruments: Vec<Instrument>,
+    names: Vec<String>,
+    positions: Vec<Option<(usize, usize)>>,
+    link_groups: Vec<Vec<usize>>,
+}
+
+impl Dashboard {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            instruments: Vec::new(),
+            names: Vec::new(),
+            positions: Vec::new(),
+            link_groups: Vec::new(),
+        }
+    }
+
+    /// Adds a gauge and returns the index commands for it are addressed to. Its name is
+    /// the index itself.
+    pub fn add(&mut self, instrument: Instrument) -> usize {
+        let name = self.instruments.len().to_string();
+        self.add_named(name, instrument)
+    }
+
+    /// Adds a gauge under the name input lines route its values by, e.g. `oil` for
+    /// `oil.needle1=95`
+    pub fn add_named(&mut self, name: impl Into<String>, instrument: Instrument) -> usize {
+        self.instruments.push(instrument);
+        self.names.push(name.into());
+        self.positions.push(None);
+        self.instruments.len() - 1
+    }
+
+    /// Pins a gauge to a grid cell, counted from the top left. Unknown indices are ignored.
+    pub fn place(&mut self, index: usize, column: usize, row: usize) {
+        if let Some(position) = self.positions.get_mut(index) {
+            *position = Some((column, row));
+        }
+    }
+
+    /// Gauge names in index order
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// The index of the gauge added under `name`
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.names.iter().position(|other| other == name)
+    }
+
+    /// Keeps the given gauges on one scale, e.g. left and right engine temperatures. They
+    /// start on the range of the first, and a `SetRange` sent to any of them moves all.
+    pub fn link_ranges(&mut self, gauges: impl IntoIterator<Item = usize>) {
+        let group: Vec<usize> = gauges.into_iter().collect();
+        if group.len() > 1 {
+            self.link_groups.push(group);
+        }
+    }
+
+    // The gauge itself and every gauge sharing a link group with it
+    fn linked(&self, index: usize) -> Vec<usize> {
+        let mut linked = vec![index];
+        for group in self
+            .link_groups
+            .iter()
+            .filter(|group| group.contains(&index))
+        {
+            linked.extend(
+                group
+                    .iter()
+                    .filter(|other| !linked.contains(other))
+                    .collect::<Vec<_>>(),
+            );
+        }
+        linked
+    }
+
+    // Each gauge's (column, row), then the grid's (columns, rows). Unplaced gauges take
+    // the free cells of a near-square grid in reading order.
+    fn layout(&self) -> (Vec<(usize, usize)>, (usize, usize)) {
+        let count = self.instruments.len().max(1);
+        let columns = (count as f64).sqrt().ceil() as usize;
+        let placed: Vec<(usize, usize)> = self.positions.iter().flatten().copied().collect();
+        let mut free = (0..)
+            .map(|slot| (slot % columns, slot / columns))
+            .filter(|cell| !placed.contains(cell));
+        let cells: Vec<(usize, usize)> = self
+            .positions
+            .iter()
+            .map(|position| position.unwrap_or_else(|| free.next().unwrap()))
+            .collect();
+        let extent = |axis: fn(&(usize, usize)) -> usize| {
+            cells.iter().map(axis).max().map_or(1, |last| last + 1)
+        };
+        let grid = (extent(|cell| cell.0), extent(|cell| cell.1));
+        (cells, grid)
+    }
+
+    /// Opens the dashboard window with one command channel per gauge, in index order.
+    /// When a channel closes, e.g. because the thread feeding it panicked, that gauge
+    /// shows "NO DATA" and the rest keep running.
+    pub fn show_with_sources(
+        &mut self,
+        sources: Vec<Receiver<InstrumentCommand>>,
+    ) -> Result<(), InstrumentError> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        for (index, source) in sources.into_iter().enumerate() {
+            let sender = sender.clone();
+            std::thread::spawn(move || {
+                for command in source {
+                    if sender.send((index, command)).is_err() {
+                        return;
+                    }
+                }
+                let failure = InstrumentCommand::SetFailure(Some("NO DATA".to_string()));
+                let _ = sender.send((index, failure));
+            });
+        }
+        self.show_with_commands(receiver)
+    }
+
+    /// Opens the dashboard window, routing each `(index, command)` to the gauge `add`
+    /// returned that index for. Commands for unknown indices are dropped. Pass the
+    /// receiver through `DashboardRecording::tap` to record every gauge on one clock.
+    /// A gauge that panics while handling a command or drawing shows "GAUGE FAILED" and the
+    /// rest keep running.
+    pub fn show_with_commands(
+        &mut self,
+        receiver: Receiver<(usize, InstrumentCommand)>,
+    ) -> Result<(), InstrumentError> {
+        self.show_in_event_loop(EventLoop::new()?, receiver)
+    }
+
+    /// Like [`show_with_commands`](Self::show_with_commands), on an event loop the caller
+    /// built, as Android apps must; see [`Instrument::show_in_event_loop`]
+    pub fn show_in_event_loop(
+        &mut self,
+        event_loop: EventLoop<()>,
+        receiver: Receiver<(usize, InstrumentCommand)>,
+    ) -> Result<(), InstrumentError> {
+        let (positions, (columns, rows)) = self.layout();
+        let configs = || self.instruments.iter().map(|instrument| &instrument.config);
+        for config in configs() {
+            config.validate()?;
+            try_text_shaper(config)?;
+        }
+        let cell_width = configs().map(|c| c.window_width).max().unwrap_or(300);
+        let cell_height = configs().map(|c| c.window_height).max().unwrap_or(300);
+        let target_fps = configs().map(|c| c.max_framerate).fold(1.0, f64::max);
+
+        let window = WindowBuilder::new()
+            .with_title(&self.title)
+            .with_inner_size(LogicalSize::new(
+                (columns * cell_width) as f64,
+                (rows * cell_height) as f64,
+            ))
+            .with_resizable(false)
+            .build(&event_loop)?;
+
+        let window = std::sync::Arc::new(window);
+
+        let mut app_states: Vec<AppState> = self
+            .instruments
+            .iter()
+            .map(|instrument| {
+                let mut app_state = instrument.initial_app_state();
+                app_state.restore_state(&instrument.config);
+                app_state
+            })
+            .collect();
+        for group in &self.link_groups {
+            let Some((min, max)) = group
+                .first()
+                .and_then(|&first| self.instruments.get(first))
+                .map(|instrument| instrument.config.range)
+            else {
+                continue;
+            };
+            for &index in &group[1..] {
+                if let Some(app_state) = app_states.get_mut(index) {
+                    app_state.snap_range(min, max);
+                }
+            }
+        }
+        let links: Vec<Vec<usize>> = (0..self.instruments.len())
+            .map(|index| self.linked(index))
+            .collect();
+        let mut cells = vec![Vec::new(); self.instruments.len()];
+        let mut crashed = vec![false; self.instruments.len()];
+
+        let window_clone = window.clone();
+        let mut surface = SurfaceSize::of(&window);
+        let dpi_configs = |surface: &SurfaceSize| -> Vec<InstrumentConfig> {
+            configs().map(|config| surface.config_for(config)).collect()
+        };
+        let mut cell_configs = dpi_configs(&surface);
+        let mut pixels: Option<Pixels> = None;
+        let mut surface_error = None;
+        let surface_error_slot = &mut surface_error;
+        let window_ref = &window;
+
+        let frame_duration = std::time::Duration::from_secs_f64(1.0 / target_fps);
+        let mut last_frame = Instant::now();
+
+        event_loop.run(move |event, window_target| {
+            window_target.set_control_flow(ControlFlow::Poll);
+            match event {
+                Event::Resumed if pixels.is_none() => {
+                    surface = SurfaceSize::of(window_ref);
+                    cell_configs = dpi_configs(&surface);
+                    match new_pixels(window_ref) {
+                        Ok(created) => pixels = Some(created),
+                        Err(error) => {
+                            *surface_error_slot = Some(error);
+                            window_target.exit();
+                        }
+                    }
+                }
+                Event::Suspended => {
+                    pixels = None;
+                }
+                Event::WindowEvent { event, .. } => match event {
+                    WindowEvent::CloseRequested => {
+                        window_target.exit();
+                    }
+                    WindowEvent::Resized(new_size) => {
+                        surface.resize(new_size, pixels.as_mut());
+                    }
+                    WindowEvent::ScaleFactorChanged { scale_factor, .. } => {
+                        surface.scale_factor = scale_factor;
+                        cell_configs = dpi_configs(&surface);
+                    }
+                    WindowEvent::RedrawRequested => {
+                        {
+                            trace_span!("process_commands");
+                            // A gauge that panics on a command is isolated like one that
+                            // panics while drawing, and crashed gauges take no more commands
+                            let mut apply = |index: usize, command: InstrumentCommand| {
+                                let Some(app_state) = app_states.get_mut(index) else {
+                                    return;
+                                };
+                                if !crashed[index] {
+                                    let applied = std::panic::catch_unwind(
+                                        std::panic::AssertUnwindSafe(|| {
+                                            app_state.apply_command(command)
+                                        }),
+                                    );
+                                    crashed[index] = applied.is_err();
+                                }
+                            };
+                            while let Ok((index, command)) = receiver.try_recv() {
+                                // Range changes carry over to linked gauges
+                                if let InstrumentCommand::SetRange(..) = command {
+                                    for &linked in links.get(index).into_iter().flatten() {
+                                        apply(linked, command.clone());
+                                    }
+                                } else {
+                                    apply(index, command);
+                                }
+                            }
+                        }
+
+                        let Some(ref mut pixels) = pixels else {
+                            return;
+                        };
+                        let frame = pixels.frame_mut();
+                        // Cells don't divide the window exactly; the remainder stays white
+                        frame.fill(0xff);
+                        let fb_width = surface.width;
+                        let (width, height) = (fb_width / columns, surface.height / rows);
+                        for (index, (config, app_state)) in
+                            cell_configs.iter().zip(&mut app_states).enumerate()
+                        {
+                            let cell = &mut cells[index];
+                            cell.resize(width * height * 4, 0);
+                            if !crashed[index] {
+                                let drawn =
+                                    std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+                                        app_state.update();
+                                        let elapsed = app_state.elapsed();
+                                        render_frame(
+                                            cell, width, height, app_state, config, elapsed, None,
+                                        );
+                                        if let Some(ref hook) = config.frame_hook {
+                                            (hook.0)(cell, width, height);
+                                        }
+                                    }));
+                                // Its state may be half updated, so it isn't drawn again
+                                crashed[index] = drawn.is_err();
+                            }
+                            if crashed[index] {
+                                render_crashed_cell(cell, width, height, config);
+                            }
+                            let (column, row) = positions[index];
+                            let (left, top) = (column * width, row * height);
+                            for (row, pixels) in cell.chunks_exact(width * 4).enumerate() {
+                                let start = ((top + row) * fb_width + left) * 4;
+                                frame[start..start + width * 4].copy_from_slice(pixels);
+                            }
+                        }
+                        trace_span!("present");
+                        let _ = pixels.render();
+                        for ((config, app_state), _) in cell_configs
+                            .iter()
+                            .zip(&mut app_states)
+                            .zip(&crashed)
+                            .filter(|(_, crashed)| !**crashed)
+                        {
+                            app_state.acknowledge_presented(config);
+                        }
+                    }
+                    _ => {}
+                },
+                Event::LoopExiting => {
+                    for ((config, app_state), _) in cell_configs
+                        .iter()
+                        .zip(&app_states)
+                        .zip(&crashed)
+                        .filter(|(_, crashed)| !**crashed)
+                    {
+                        app_state.save_state(config);
+                    }
+                }
+                Event::AboutToWait if last_frame.elapsed() >= frame_duration => {
+                    window_clone.request_redraw();
+                    last_frame = Instant::now();
+                }
+                _ => {}
+            }
+        })?;
+
+        match surface_error {
+            Some(error) => Err(error.into()),
+            None => Ok(()),
+        }
+    }
+}
+
+impl Dashboard {
+    /// Writes the whole panel as one TOML document: the title, then a `[[gauge]]` table
+    /// per gauge with its name, cell, link groups and main dial settings. Fails, naming
+    /// them, if any gauge has settings the layout can't hold; `to_toml` writes the rest
+    /// regardless.
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(), InstrumentError> {
+        let unsaved = self.unsaved_settings()?;
+        if !unsaved.is_empty() {
+            return Err(InstrumentError::InvalidConfig(format!(
+                "a layout can't store {}",
+                unsaved.join(", ")
+            )));
+        }
+        std::fs::write(path, self.to_toml())?;
+        Ok(())
+    }
+
+    /// The settings `to_toml` would lose, as `<gauge>.<field>`, e.g. `oil.zones`
+    pub fn unsaved_settings(&self) -> Result<Vec<String>, InstrumentError> {
+        let reloaded = Self::from_toml(&self.to_toml())?;
+        let mut unsaved = Vec::new();
+        for (index, (instrument, saved)) in self
+            .instruments
+            .iter()
+            .zip(&reloaded.instruments)
+            .enumerate()
+        {
+            let saved = debug_fields(&saved.config);
+            for (field, value) in debug_fields(&instrument.config) {
+                if !saved.contains(&(field.clone(), value)) {
+                    unsaved.push(format!("{}.{field}", self.names[index]));
+                }
+            }
+        }
+        Ok(unsaved)
+    }
+
+    /// Reads a panel written by `save`, or by hand in the same subset of TOML
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, InstrumentError> {
+        Self::from_toml(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn to_toml(&self) -> String {
+        use std::fmt::Write;
+        let (cells, _) = self.layout();
+        let mut out = format!("title = {}\n", toml_string(&self.title));
+        for (index, instrument) in self.instruments.iter().enumerate() {
+            let config = &instrument.config;
+            let (column, row) = cells[index];
+            let links: Vec<String> = (0..self.link_groups.len())
+                .filter(|&group| self.link_groups[group].contains(&index))
+                .map(|group| group.to_string())
+                .collect();
+            let _ = writeln!(out, "\n[[gauge]]");
+            let _ = writeln!(out, "name = {}", toml_string(&self.names[index]));
+            let _ = writeln!(out, "column = {column}\nrow = {row}");
+            if !links.is_empty() {
+                let _ = writeln!(out, "links = [{}]", links.join(", "));
+            }
+            let _ = writeln!(out, "title = {}", toml_string(&config.title));
+            let _ = writeln!(out, "range = [{}, {}]", config.range.0, config.range.1);
+            if let Some((lower, upper, color)) = config.highlight_band {
+                let _ = writeln!(out, "highlight = [{lower}, {upper}]");
+                let (r, g, b) = color.as_tuple();
+                let _ = writeln!(out, "highlight_color = \"#{r:02x}{g:02x}{b:02x}\"");
+            }
+            let _ = writeln!(out, "ticks_count = {}", config.ticks_count);
+            let _ = writeln!(
+                out,
+                "minor_ticks_per_interval = {}",
+                config.minor_ticks_per_interval
+            );
+            let _ = writeln!(out, "auto_ticks = {}", config.auto_ticks);
+            let _ = writeln!(out, "window_width = {}", config.window_width);
+            let _ = writeln!(out, "window_height = {}", config.window_height);
+            let _ = writeln!(out, "curved_text = {}", toml_string(&config.curved_text));
+            let _ = writeln!(
+                out,
+                "bottom_curved_text = {}",
+                toml_string(&config.bottom_curved_text)
+            );
+            if !config.dial_text.is_empty() {
+                let _ = writeln!(out, "dial_text = {}", toml_string(&config.dial_text));
+            }
+            let (min, max) = config.chronograph_range;
+            let _ = writeln!(out, "chronograph_range = [{min}, {max}]");
+            let _ = writeln!(out, "ui_scale = {}", config.ui_scale);
+            if !config.derived_channels.is_empty() {
+                let definitions: Vec<String> = config
+                    .derived_channels
+                    .iter()
+                    .map(|channel| channel.to_string())
+                    .collect();
+                let _ = writeln!(out, "derived = {}", toml_string(&definitions.join("; ")));
+            }
+            if !config.alert_rules.is_empty() {
+                let rules: Vec<String> = config
+                    .alert_rules
+                    .iter()
+                    .map(|rule| rule.to_string())
+                    .collect();
+                let _ = writeln!(out, "alerts = {}", toml_string(&rules.join("; ")));
+            }
+            #[cfg(feature = "scripting")]
+            if let Some(script) = &config.value_script {
+                let _ = writeln!(out, "script = {}", toml_string(script.source()));
+            }
+        }
+        out
+    }
+
+    /// Parses the document `to_toml` writes. Keys missing from a gauge keep their
+    /// defaults; unknown keys, repeated names and two gauges in one cell are errors so
+    /// typos don't pass silently. `#` starts a comment outside strings.
+    pub fn from_toml(text: &str) -> Result<Self, InstrumentError> {
+        let mut dashboard = Dashboard::new("Dashboard");
+        let mut links: Vec<(usize, usize)> = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let invalid = |message: String| {
+                InstrumentError::InvalidConfig(format!("layout line {}: {message}", number + 1))
+            };
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line.split('#').next().map(str::trim) == Some("[[gauge]]") {
+                let name = dashboard.instruments.len().to_string();
+                dashboard.add_named(name, Instrument::new(InstrumentConfig::builder().build()));
+                continue;
+            }
+            let (key, raw) = line
+                .split_once('=')
+                .ok_or_else(|| invalid(format!("expected key = value, got {line:?}")))?;
+            let (key, value) = (key.trim(), LayoutValue::parse(raw.trim()).map_err(invalid)?);
+            let Some(index) = dashboard.instruments.len().checked_sub(1) else {
+                match key {
+                    "title" => dashboard.title = value.text(key).map_err(invalid)?,
+                    _ => return Err(invalid(format!("unknown key {key:?}"))),
+                }
+                continue;
+            };
+            let config = &mut dashboard.instruments[index].config;
+            match key {
+                "name" => dashboard.names[index] = value.text(key).map_err(invalid)?,
+                "column" | "row" => {
+                    let cell = value.count(key, MAX_LAYOUT_CELL).map_err(invalid)?;
+                    let position = dashboard.positions[index].get_or_insert((0, 0));
+                    if key == "column" {
+                        position.0 = cell;
+                    } else {
+                        position.1 = cell;
+                    }
+                }
+                "links" => {
+                    for group in value.numbers(key).map_err(invalid)? {
+                        let group = LayoutValue::Number(group)
+                            .count(key, MAX_LAYOUT_LINK_GROUP)
+                            .map_err(invalid)?;
+                        links.push((group, index));
+                    }
+                }
+                "title" => config.title = value.text(key).map_err(invalid)?,
+                "range" => config.range = value.pair(key).map_err(invalid)?,
+                "highlight" => {
+                    let (lower, upper) = value.pair(key).map_err(invalid)?;
+                    let color = config
+                        .highlight_band
+                        .map_or(Color::new(0xff, 0x00, 0x00), |(_, _, color)| color);
+                    config.highlight_band = Some((lower, upper, color));
+                }
+                "highlight_color" => {
+                    let color = value.color(key).map_err(invalid)?;
+                    let (lower, upper) = config
+                        .highlight_band
+                        .map_or(config.range, |(lower, upper, _)| (lower, upper));
+                    config.highlight_band = Some((lower, upper, color));
+                }
+                "ticks_count" => {
+                    config.ticks_count = value.count(key, MAX_LAYOUT_TICKS).map_err(invalid)?
+                }
+                "minor_ticks_per_interval" => {
+                    config.minor_ticks_per_interval =
+                        value.count(key, MAX_LAYOUT_TICKS).map_err(invalid)?
+                }
+                "auto_ticks" => config.auto_ticks = value.flag(key).map_err(invalid)?,
+                "window_width" => {
+                    config.window_width = value.count(key, MAX_LAYOUT_WINDOW).map_err(invalid)?
+                }
+                "window_height" => {
+                    config.window_height = value.count(key, MAX_LAYOUT_WINDOW).map_err(invalid)?
+                }
+                "curved_text" => config.curved_text = value.text(key).map_err(invalid)?,
+                "bottom_curved_text" => {
+                    config.bottom_curved_text = value.text(key).map_err(invalid)?
+                }
+                "dial_text" => config.dial_text = value.text(key).map_err(invalid)?,
+                "chronograph_range" => {
+                    config.chronograph_range = value.pair(key).map_err(invalid)?
+                }
+                "ui_scale" => config.ui_scale = value.number(key).map_err(invalid)?,
+                "derived" => {
+                    config.derived_channels = value
+                        .text(key)
+                        .map_err(invalid)?
+                        .split(';')
+                        .filter(|definition| !definition.trim().is_empty())
+                        .map(DerivedChannel::parse)
+                        .collect::<Result<_, _>>()?;
+                }
+                "alerts" => {
+                    config.alert_rules = value
+                        .text(key)
+                        .map_err(invalid)?
+                        .split(';')
+                        .filter(|rule| !rule.trim().is_empty())
+                        .map(AlertRule::parse)
+                        .collect::<Result<_, _>>()?;
+                }
+                #[cfg(feature = "scripting")]
+                "script" => {
+                    let source = value.text(key).map_err(invalid)?;
+                    config.value_script = Some(ValueScript::new(&source)?);
+                }
+                _ => return Err(invalid(format!("unknown key {key:?}"))),
+            }
+        }
+        let invalid =
+            |message: String| InstrumentError::InvalidConfig(format!("layout: {message}"));
+        for (index, name) in dashboard.names.iter().enumerate() {
+            if dashboard.names[..index].contains(name) {
+                return Err(invalid(format!("two gauges are named {name:?}")));
+            }
+            if let Some((column, row)) = dashboard.positions[index] {
+                if dashboard.positions[..index].contains(&Some((column, row))) {
+                    return Err(invalid(format!(
+                        "two gauges are at column {column}, row {row}"
+                    )));
+                }
+            }
+        }
+        let mut groups: Vec<usize> = links.iter().map(|&(group, _)| group).collect();
+        groups.sort_unstable();
+        groups.dedup();
+        for group in groups {
+            dashboard.link_ranges(
+                links
+                    .iter()
+                    .filter(|&&(other, _)| other == group)
+                    .map(|&(_, index)| index),
+            );
+        }
+        for instrument in &dashboard.instruments {
+            instrument.config.validate()?;
+        }
+        Ok(dashboard)
+    }
+}
+
+// Upper bounds on layout counts, so a typo can't ask for a vast grid or window
+const MAX_LAYOUT_CELL: usize = 63;
+const MAX_LAYOUT_LINK_GROUP: usize = 65535;
+const MAX_LAYOUT_TICKS: usize = 1000;
+const MAX_LAYOUT_WINDOW: usize = 16384;
+
+// A TOML basic string: quoted, with control characters written as escapes
+fn toml_string(text: &str) -> String {
+    let mut out = String::from('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\t' => out.push_str("\\t"),
+            '\r' => out.push_str("\\r"),
+            c if c.is_control() => out.push_str(&format!("\\u{:04X}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// Each top-level field of a struct's pretty `Debug` output with its printed value, so
+// two values can be compared field by field
+fn debug_fields(value: &impl std::fmt::Debug) -> Vec<(String, String)> {
+    let mut fields: Vec<(String, String)> = Vec::new();
+    for line in format!("{value:#?}").lines() {
+        let field = line
+            .strip_prefix("    ")
+            .filter(|rest| !rest.starts_with(' '))
+            .and_then(|rest| rest.split_once(':'));
+        match (field, fields.last_mut()) {
+            (Some((name, rest)), _) => fields.push((name.to_string(), rest.to_string())),
+            (None, Some((_, printed))) => printed.push_str(line),
+            (None, None) => {}
+        }
+    }
+    fields
+}
+
+// A value on the right of `key = ` in a layout file
+enum LayoutValue {
+    Text(String),
+    Number(f64),
+    Flag(bool),
+    List(Vec<f64>),
+}
+
+impl LayoutValue {
+    fn parse(raw: &str) -> Result<Self, String> {
+        if let Some(quoted) = raw.strip_prefix('"') {
+            let mut text = String::new();
+            let mut chars = quoted.chars();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' => {
+                        let rest = chars.as_str().trim_start();
+                        if !(rest.is_empty() || rest.starts_with('#')) {
+                            return Err(format!("unexpected {rest:?} after string"));
+                        }
+                        return Ok(Self::Text(text));
+                    }
+                    '\\' => match chars.next() {
+                        Some('n') => text.push('\n'),
+                        Some('t') => text.push('\t'),
+                        Some('r') => text.push('\r'),
+                        Some('b') => text.push('\u{8}'),
+                        Some('f') => text.push('\u{c}'),
+                        Some(escaped @ ('"' | '\\')) => text.push(escaped),
+                        Some(width @ ('u' | 'U')) => {
+                            let digits = if width == 'u' { 4 } else { 8 };
+                            let hex: String = chars.by_ref().take(digits).collect();
+                            let escaped = (hex.len() == digits)
+                                .then(|| u32::from_str_radix(&hex, 16).ok())
+                                .flatten()
+                                .and_then(char::from_u32)
+                                .ok_or_else(|| format!("bad escape \\{width}{hex}"))?;
+                            text.push(escaped);
+                        }
+                        other => return Err(format!("unsupported escape \\{other:?}")),
+                    },
+                    _ => text.push(c),
+                }
+            }
+            return Err(format!("unterminated string {raw:?}"));
+        }
+        let raw = raw.split('#').next().unwrap_or_default().trim();
+        if let Some(items) = raw
+            .strip_prefix('[')
+            .and_then(|rest| rest.strip_suffix(']'))
+        {
+            return items
+                .split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(|item| {
+                    item.parse()
+                        .map_err(|_| format!("expected a number, got {item:?}"))
+                })
+                .collect::<Result<_, _>>()
+                .map(Self::List);
+        }
+        match raw {
+            "true" => Ok(Self::Flag(true)),
+            "false" => Ok(Self::Flag(false)),
+            _ => raw
+                .parse()
+                .map(Self::Number)
+                .map_err(|_| format!("can't read value {raw:?}")),
+        }
+    }
+
+    fn text(self, key: &str) -> Result<String, String> {
+        match self {
+            Self::Text(text) => Ok(text),
+            _ => Err(format!("{key} expects a string")),
+        }
+    }
+
+    fn number(self, key: &str) -> Result<f64, String> {
+        match self {
+            Self::Number(number) => Ok(number),
+            _ => Err(format!("{key} expects a number")),
+        }
+    }
+
+    fn count(self, key: &str, max: usize) -> Result<usize, String> {
+        match self.number(key)? {
+            number if (0.0..=max as f64).contains(&number) && number.fract() == 0.0 => {
+                Ok(number as usize)
+            }
+            _ => Err(format!("{key} expects a whole number from 0 to {max}")),
+        }
+    }
+
+    fn flag(self, key: &str) -> Result<bool, String> {
+        match self {
+            Self::Flag(flag) => Ok(flag),
+            _ => Err(format!("{key} expects true or false")),
+        }
+    }
+
+    fn numbers(self, key: &str) -> Result<Vec<f64>, String> {
+        match self {
+            Self::List(numbers) => Ok(numbers),
+            _ => Err(format!("{key} expects a list of numbers")),
+        }
+    }
+
+    fn pair(self, key: &str) -> Result<(f64, f64), String> {
+        match self.numbers(key)?[..] {
+            [first, second] => Ok((first, second)),
+            _ => Err(format!("{key} expects [first, second]")),
+        }
+    }
+
+    fn color(self, key: &str) -> Result<Color, String> {
+        let text = self.text(key)?;
+        text.strip_prefix('#')
+            .and(parse_color(&text))
+            .ok_or_else(|| format!("{key} expects \"#rrggbb\", got {text:?}"))
+    }
+}
+
+type DashboardCommand = (usize, InstrumentCommand);
+
+/// A dashboard's `(index, command)` stream timed against one clock, so every gauge
+/// replays in step with the others
+#[derive(Debug, Clone, Default)]
+pub struct DashboardRecording {
+    events: Vec<(Duration, DashboardCommand)>,
+}
+
+impl DashboardRecording {
+    /// Passes `receiver` through unchanged, adding each command to the shared recording
+    /// as it goes by. Times count from this call.
+    ///
+    /// Nothing is dropped, so the recording grows for as long as commands arrive; for a
+    /// long-running tap, save and `std::mem::take` it from the mutex now and then.
+    pub fn tap(
+        receiver: Receiver<DashboardCommand>,
+    ) -> (
+        Receiver<DashboardCommand>,
+        std::sync::Arc<Mutex<DashboardRecording>>,
+    ) {
+        let recording = std::sync::Arc::new(Mutex::new(DashboardRecording::default()));
+        let (sender, tapped) = std::sync::mpsc::channel();
+        let shared = recording.clone();
+        let started = Instant::now();
+        std::thread::spawn(move || {
+            for event in receiver {
+                if let Ok(mut recording) = shared.lock() {
+                    recording.events.push((started.elapsed(), event.clone()));
+                }
+                if sender.send(event).is_err() {
+                    break;
+                }
+            }
+        });
+        (tapped, recording)
+    }
+
+    /// Sends the commands to a new channel at their recorded times, scaled by `speed`
+    pub fn replay(self, speed: f64) -> Receiver<DashboardCommand> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let speed = if speed > 0.0 { speed } else { 1.0 };
+        std::thread::spawn(move || {
+            let started = Instant::now();
+            for (time, event) in self.events {
+                let due = time.div_f64(speed);
+                if let Some(wait) = due.checked_sub(started.elapsed()) {
+                    std::thread::sleep(wait);
+                }
+                if sender.send(event).is_err() {
+                    break;
+                }
+            }
+        });
+        receiver
+    }
+
+    /// Writes one `time=<seconds> gauge=<index> key=value ...` line per command, keyed
+    /// like the stdin protocol. Only value, highlight and range commands are written.
+    ///
+    /// Fails without writing anything if a `SetMany` key is `time` or `gauge`, is empty,
+    /// or contains whitespace or `=`, since it could not be read back.
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> Result<(), InstrumentError> {
+        use std::fmt::Write;
+        let mut out = String::new();
+        for (time, (index, command)) in &self.events {
+            let Some(values) = recorded_values(command) else {
+                continue;
+            };
+            if let Some((key, _)) = values.iter().find(|(key, _)| {
+                key.is_empty()
+                    || key == "time"
+                    || key == "gauge"
+                    || key.contains(|c: char| c == '=' || c.is_whitespace())
+            }) {
+                return Err(InstrumentError::InvalidConfig(format!(
+                    "recording key {key:?} can't be saved"
+                )));
+            }
+            let _ = write!(out, "time={} gauge={index}", time.as_secs_f64());
+            for (key, value) in values {
+                let _ = write!(out, " {key}={value}");
+            }
+            out.push('\n');
+        }
+        std::fs::write(path, out)?;
+        Ok(())
+    }
+
+    /// Reads a file written by `save`, skipping malformed lines
+    pub fn load(path: impl AsRef<std::path::Path>) -> Result<Self, InstrumentError> {
+        let text = std::fs::read_to_string(path)?;
+        let events = text
+            .lines()
+            .filter_map(|line| {
+                let mut values: Vec<(&str, f64)> = line
+                    .split_whitespace()
+                    .map(|token| {
+                        let (key, value) = token.split_once('=')?;
+                        Some((key, value.parse().ok()?))
+                    })
+                    .collect::<Option<_>>()?;
+                let mut take = |wanted: &str| {
+                    let at = values.iter().position(|(key, _)| *key == wanted)?;
+                    Some(values.remove(at).1)
+                };
+                let time = Duration::try_from_secs_f64(take("time")?).ok()?;
+                let index = take("gauge")? as usize;
+                let command = match (
+                    take("highlightlower").zip(take("highlightupper")),
+                    take("highlightclear"),
+                    take("rangemin").zip(take("rangemax")),
+                ) {
+                    (Some((lower, upper)), _, _) => {
+                        InstrumentCommand::SetHighlightBounds(lower, upper)
+                    }
+                    (_, Some(_), _) => InstrumentCommand::ClearHighlightBounds,
+                    (_, _, Some((min, max))) => InstrumentCommand::SetRange(min, max),
+                    _ => InstrumentCommand::SetMany(
+                        values
+                            .into_iter()
+                            .map(|(key, value)| (key.to_string(), value))
+                            .collect(),
+                    ),
+                };
+                Some((time, (index, command)))
+            })
+            .collect();
+        Ok(Self { events })
+    }
+}
+
+// The keys a command is saved under, or `None` for commands that only replay in memory
+fn recorded_values(command: &InstrumentCommand) -> Option<Vec<(String, f64)>> {
+    use InstrumentCommand as C;
+    let named = |pairs: &[(NeedleId, f64)]| {
+        pairs
+            .iter()
+            .map(|(id, value)| (id.name().to_string(), *value))
+            .collect()
+    };
+    Some(match command {
+        C::SetPrimaryNeedle(value) => named(&[(NeedleId::Primary, *value)]),
+        C::SetSecondaryNeedle(value) => named(&[(NeedleId::Secondary, *value)]),
+        C::SetChronograph(value) => named(&[(NeedleId::Chronograph, *value)]),
+        C::SetSecondaryChronograph(value) => named(&[(NeedleId::SecondaryChronograph, *value)]),
+        C::SetNeedle { id, value } => named(&[(*id, *value)]),
+        C::SetBothNeedles(primary, secondary) => named(&[
+            (NeedleId::Primary, *primary),
+            (NeedleId::Secondary, *secondary),
+        ]),
+        C::SetBothChronographs(chrono, secondary_chrono) => named(&[
+            (NeedleId::Chronograph, *chrono),
+            (NeedleId::SecondaryChronograph, *secondary_chrono),
+        ]),
+        C::SetAllNeedles(primary, secondary, chrono, secondary_chrono) => named(&[
+            (NeedleId::Primary, *primary),
+            (NeedleId::Secondary, *secondary),
+            (NeedleId::Chronograph, *chrono),
+            (NeedleId::SecondaryChronograph, *secondary_chrono),
+        ]),
+        C::SetReadout(value) => vec![("readout".to_string(), *value)],
+        C::SetMany(values) => {
+            let mut values: Vec<_> = values
+                .iter()
+                .map(|(key, value)| (key.clone(), *value))
+                .collect();
+            values.sort_by(|a, b| a.0.cmp(&b.0));
+            values
+        }
+        C::SetHighlightBounds(lower, upper) => vec![
+            ("highlightlower".to_string(), *lower),
+            ("highlightupper".to_string(), *upper),
+        ],
+        C::ClearHighlightBounds => vec![("highlightclear".to_string(), 1.0)],
+        C::SetRange(min, max) => vec![
+            ("rangemin".to_string(), *min),
+            ("rangemax".to_string(), *max),
+        ],
+        _ => return None,
+    })
+}
+
+// ============================================================================
+// WINDOW SURFACE
+// ============================================================================
+
+/// Layout dimensions are tuned for a framebuffer at twice the window's logical size
+const REFERENCE_SCALE_FACTOR: f64 = 2.0;
+
+/// Physical size and DPI of a window's framebuffer. Every windowed mode tracks one so
+/// the gauge keeps its proportions when the window moves between monitors.
+struct SurfaceSize {
+    width: usize,
+    height: usize,
+    scale_factor: f64,
+}
+
+impl SurfaceSize {
+    fn of(window: &winit::window::Window) -> Self {
+        let size = window.inner_size();
+        Self {
+            width: size.width as usize,
+            height: size.height as usize,
+            scale_factor: window.scale_factor(),
+        }
+    }
+
+    // Keeps the buffer one-to-one with the surface, so nothing is stretched
+    fn resize(&mut self, size: winit::dpi::PhysicalSize<u32>, pixels: Option<&mut Pixels>) {
+        self.width = size.width as usize;
+        self.height = size.height as usize;
+        if let Some(pixels) = pixels {
+            let _ = pixels.resize_buffer(size.width, size.height);
+            let _ = pixels.resize_surface(size.width, size.height);
+        }
+    }
+
+    fn config_for(&self, config: &InstrumentConfig) -> InstrumentConfig {
+        InstrumentConfig {
+            ui_scale: config.ui_scale * self.scale_factor / REFERENCE_SCALE_FACTOR,
+            pixel_format: PixelFormat::Rgba8, // The surface texture
+            ..config.clone()
+        }
+    }
+}
+
+fn new_pixels(window: &winit::window::Window) -> Result<Pixels<'_>, pixels::Error> {
+    let size = window.inner_size();
+    let surface_texture = SurfaceTexture::new(size.width, size.height, window);
+    Pixels::new(size.width, size.height, surface_texture)
+}
+
+// ============================================================================
+// INTERNAL IMPLEMENTATION (from main.rs)
+// ============================================================================
+
+// ============================================================================
+// RETAINED MODE ABSTRACTIONS
+// ============================================================================
+
+/// A differently styled outer section of a [`DrawCommand::NeedleLine`]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NeedleTip {
+    /// Share of the line, measured back from its end, drawn in the tip style
+    pub fraction: f64,
+    pub thickness: f32,
+    pub color: (u8, u8, u8),
+}
+
+/// How the ends of a [`DrawCommand::Arc`] are finished
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ArcCap {
+    /// Cut square across the band at the end angles
+    #[default]
+    Butt,
+    /// Rounded off with a half circle the width of the band beyond each end angle
+    Round,
+}
+
+/// How a stroke is broken up along its length. Lengths are canvas pixels.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum LineStyle {
+    #[default]
+    Solid,
+    Dashed {
+        length: f64,
+        gap: f64,
+    },
+    /// Dots as long as the stroke is thick, `gap` apart
+    Dotted {
+        gap: f64,
+    },
+}
+
+impl LineStyle {
+    fn scaled(self, k: f64) -> Self {
+        match self {
+            LineStyle::Solid => LineStyle::Solid,
+            LineStyle::Dashed { length, gap } => LineStyle::Dashed {
+                length: length * k,
+                gap: gap * k,
+            },
+            LineStyle::Dotted { gap } => LineStyle::Dotted { gap: gap * k },
+        }
+    }
+
+    // Inked length and repeat distance of the pattern, or `None` for a solid stroke
+    fn pattern(self, thickness: f64) -> Option<(f64, f64)> {
+        let (on, off) = match self {
+            LineStyle::Solid => return None,
+            LineStyle::Dashed { length, gap } => (length, gap),
+            LineStyle::Dotted { gap } => (thickness.max(1.0), gap),
+        };
+        (on > 0.0 && off > 0.0).then_some((on, on + off))
+    }
+
+    // How much of the stroke is inked `along` pixels from its start, fading over a
+    // pixel at the ends of each dash
+    fn coverage(self, along: f64, thickness: f64) -> f64 {
+        let Some((on, period)) = self.pattern(thickness) else {
+            return 1.0;
+        };
+        let phase = along.rem_euclid(period);
+        let outside = if phase <= on {
+            0.0
+        } else {
+            (phase - on).min(period - phase)
+        };
+        (1.0 - outside).clamp(0.0, 1.0)
+    }
+}
+
+/// A single retained drawing operation. Coordinates are canvas pixels; angles are radians
+/// clockwise from the positive x axis. New variants may be added in minor releases.
+///
+/// Colours are opaque RGB; there is no per-command alpha. Anything see-through goes in a
+/// [`Translucent`](DrawCommand::Translucent) group instead, which is how settings such as
+/// `InstrumentConfig::needle_opacity` and `zone_opacity` fade their elements.
+#[derive(Clone, Debug)]
+#[non_exhaustive]
+pub enum DrawCommand {
+    Clear((u8, u8, u8)),
+    FillRect {
+        x: i32,
+        y: i32,
+        width: usize,
+        height: usize,
+        color: (u8, u8, u8),
+    },
+    Arc {
+        cx: i32,
+        cy: i32,
+        r: i32,
+        thickness: i32,
+        start_angle: f64,
+        arc_span: f64,
+        color: (u8, u8, u8),
+        style: LineStyle,
+        cap: ArcCap,
+    },
+    HighlightBand {
+        cx: i32,
+        cy: i32,
+        r: i32,
+        start_angle: f64,
+        end_angle: f64,
+        inner_radius: f64,
+        outer_radius: f64,
+        /// Scales `highlight_band_alpha`, for fading the band in and out
+        alpha: f64,
+    },
+    Tick {
+        cx: i32,
+        cy: i32,
+        r: i32,
+        angle: f64,
+        length: i32,
+        thickness: f32,
+        color: (u8, u8, u8),
+        style: LineStyle,
+    },
+    Text {
+        x: i32,
+        y: i32,
+        text: String,
+        font_size: f32,
+        color: (u8, u8, u8),
+    },
+    /// A bitmap scaled to `width` x `height` and centred on (`x`, `y`)
+    Image {
+        x: i32,
+        y: i32,
+        width: usize,
+        height: usize,
+        bitmap: std::sync::Arc<Bitmap>,
+    },
+    CurvedText {
+        cx: i32,
+        cy: i32,
+        radius: f64,
+        text: String,
+        font_size: f32,
+        arc_span: f64,
+        start_angle: f64,
+        inverted: bool, // read counter-clockwise with glyphs flipped, for text below the pivot
+        letter_spacing: f64,
+        fit: CurvedTextFit,
+        color: (u8, u8, u8),
+    },
+    NeedleLine {
+        x0: i32,
+        y0: i32,
+        x1: i32,
+        y1: i32,
+        thickness: f32,
+        tapered: bool,
+        color: (u8, u8, u8),
+        /// Restyles the end of the line; the taper carries on across the join
+        tip: Option<NeedleTip>,
+    },
+    Circle {
+        cx: i32,
+        cy: i32,
+        radius: i32,
+        color: (u8, u8, u8),
+    },
+    /// A box covering the same pixels as the equivalent `FillRect`, with corners rounded
+    /// to `radius` and anti-aliased edges. Filled, or outlined with a `stroke` of that
+    /// width centred on its edge.
+    RoundedRect {
+        x: i32,
+        y: i32,
+        width: usize,
+        height: usize,
+        radius: f64,
+        stroke: Option<f32>,
+        color: (u8, u8, u8),
+    },
+    /// A filled outline with anti-aliased edges, closed back to the first point. Where
+    /// edges cross, the nonzero winding rule decides what is inside.
+    Polygon {
+        points: Vec<(f64, f64)>,
+        color: (u8, u8, u8),
+    },
+    /// Draws `commands` as one group blended over what is beneath, from 0.0 (invisible)
+    /// to 1.0 (opaque). Where commands in the group overlap they don't double up.
+    Translucent {
+        opacity: f64,
+        commands: Vec<DrawCommand>,
+    },
+}
+
+impl DrawCommand {
+    fn kind(&self) -> &'static str {
+        match self {
+            DrawCommand::Clear(_) => "Clear",
+            DrawCommand::FillRect { .. } => "FillRect",
+            DrawCommand::RoundedRect { .. } => "RoundedRect",
+            DrawCommand::Arc { .. } => "Arc",
+            DrawCommand::HighlightBand { .. } => "HighlightBand",
+            DrawCommand::Tick { .. } => "Tick",
+            DrawCommand::Text { .. } => "Text",
+            DrawCommand::CurvedText { .. } => "CurvedText",
+            DrawCommand::NeedleLine { .. } => "NeedleLine",
+            DrawCommand::Circle { .. } => "Circle",
+            DrawCommand::Polygon { .. } => "Polygon",
+            DrawCommand::Image { .. } => "Image",
+            DrawCommand::Translucent { .. } => "Translucent",
+        }
+    }
+
+    fn translate(&mut self, dx: i32, dy: i32) {
+        match self {
+            DrawCommand::Clear(_) => {}
+            DrawCommand::FillRect { x, y, .. }
+            | DrawCommand::RoundedRect { x, y, .. }
+            | DrawCommand::Text { x, y, .. }
+            | DrawCommand::Image { x, y, .. } => {
+                *x += dx;
+                *y += dy;
+            }
+            DrawCommand::Arc { cx, cy, .. }
+            | DrawCommand::HighlightBand { cx, cy, .. }
+            | DrawCommand::Tick { cx, cy, .. }
+            | DrawCommand::CurvedText { cx, cy, .. }
+            | DrawCommand::Circle { cx, cy, .. } => {
+                *cx += dx;
+                *cy += dy;
+            }
+            DrawCommand::NeedleLine { x0, y0, x1, y1, .. } => {
+                *x0 += dx;
+                *y0 += dy;
+                *x1 += dx;
+                *y1 += dy;
+            }
+            DrawCommand::Polygon { points, .. } => {
+                for (x, y) in points {
+                    *x += dx as f64;
+                    *y += dy as f64;
+                }
+            }
+            DrawCommand::Translucent { commands, .. } => {
+                for command in commands {
+                    command.translate(dx, dy);
+                }
+            }
+        }
+    }
+}
+
+/// An ordered list of draw commands, rendered back to front
+// Commands are grouped into sections so a scene kept between frames only rebuilds the
+// sections whose inputs changed; a freshly built scene has a single section
+pub struct Scene {
+    sections: Vec<SceneSection>,
+    layers: Vec<Layer>, // Visible layers, back to front
+}
+
+struct SceneSection {
+    layer: Layer,
+    inputs: u64, // Fingerprint of everything the commands were built from
+    commands: Vec<DrawCommand>,
+}
+
+// Where a section built in gauge coordinates lands on the canvas
+struct Placement {
+    mirror_width: Option<usize>,
+    offset: (i32, i32),
+}
+
+impl Scene {
+    pub fn new(_width: usize, _height: usize) -> Self {
+        Self {
+            sections: vec![SceneSection {
+                layer: Layer::Overlay,
+                inputs: 0,
+                commands: Vec::new(),
+            }],
+            layers: Layer::ALL.to_vec(),
+        }
+    }
+
+    pub fn add_command(&mut self, command: DrawCommand) {
+        self.sections
+            .last_mut()
+            .expect("scene has a section")
+            .commands
+            .push(command);
+    }
+
+    // Adds what `build` draws, grouped in a `Translucent` command unless it is opaque
+    fn add_translucent(&mut self, opacity: f64, build: impl FnOnce(&mut Scene)) {
+        if opacity >= 1.0 {
+            build(self);
+            return;
+        }
+        let mut group = Scene::new(0, 0);
+        build(&mut group);
+        let commands = group.sections.pop().expect("scene has a section").commands;
+        if !commands.is_empty() {
+            self.add_command(DrawCommand::Translucent { opacity, commands });
+        }
+    }
+
+    /// Commands in draw order, skipping hidden layers
+    pub fn commands(&self) -> impl Iterator<Item = &DrawCommand> {
+        self.layer_commands(0..self.layers.len())
+    }
+
+    // Commands of the visible layers at positions `layers` in the draw order
+    fn layer_commands(&self, layers: std::ops::Range<usize>) -> impl Iterator<Item = &DrawCommand> {
+        self.layers[layers]
+            .iter()
+            .flat_map(|&layer| self.sections.iter().filter(move |s| s.layer == layer))
+            .flat_map(|section| &section.commands)
+    }
+
+    // How many visible layers at the back only change when their inputs do: the leading
+    // background, zone and tick layers, up to any holding the rebuilt-every-frame custom layer
+    fn static_layer_count(&self, config: &InstrumentConfig) -> usize {
+        self.layers
+            .iter()
+            .take_while(|&&layer| {
+                matches!(layer, Layer::Background | Layer::Zones | Layer::Ticks)
+                    && !(config.custom_layer.is_some() && config.custom_layer_z == layer)
+            })
+            .count()
+    }
+
+    // Fingerprint of the first `count` visible layers and the sections in them
+    fn layers_fingerprint(&self, count: usize) -> u64 {
+        use std::hash::{Hash, Hasher};
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        for &layer in &self.layers[..count] {
+            layer.hash(&mut hasher);
+            for section in self.sections.iter().filter(|s| s.layer == layer) {
+                section.inputs.hash(&mut hasher);
+            }
+        }
+        hasher.finish()
+    }
+
+    fn commands_mut(&mut self) -> impl Iterator<Item = &mut DrawCommand> {
+        self.sections
+            .iter_mut()
+            .flat_map(|section| &mut section.commands)
+    }
+
+    // Rebuilds section `index` with `build` unless it was last built from the same `inputs`
+    fn update_section(
+        &mut self,
+        index: usize,
+        layer: Layer,
+        inputs: impl std::hash::Hash,
+        placement: Option<&Placement>,
+        build: impl FnOnce(&mut Scene),
+    ) {
+        let inputs = {
+            use std::hash::Hasher;
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            inputs.hash(&mut hasher);
+            hasher.finish()
+        };
+        if self
+            .sections
+            .get(index)
+            .is_some_and(|s| s.inputs == inputs && s.layer == layer)
+        {
+            return;
+        }
+        self.replace_section(index, layer, inputs, placement, build);
+    }
+
+    fn replace_section(
+        &mut self,
+        index: usize,
+        layer: Layer,
+        inputs: u64,
+        placement: Option<&Placement>,
+        build: impl FnOnce(&mut Scene),
+    ) {
+        if index >= self.sections.len() {
+            self.sections.resize_with(index + 1, || SceneSection {
+                layer,
+                inputs: 0,
+                commands: Vec::new(),
+            });
+        }
+
+        let mut fresh = Scene::new(0, 0);
+        build(&mut fresh);
+        if let Some(placement) = placement {
+            if let Some(width) = placement.mirror_width {
+                fresh.mirror_horizontally(width);
+            }
+            if placement.offset != (0, 0) {
+                for command in fresh.commands_mut() {
+                    command.translate(placement.offset.0, placement.offset.1);
+                }
+            }
+        }
+        let section = &mut self.sections[index];
+        section.layer = layer;
+        section.inputs = inputs;
+        section.commands = fresh.sections.pop().expect("scene has a section").commands;
+    }
+
+    fn truncate_sections(&mut self, len: usize) {
+        self.sections.truncate(len);
+    }
+
+    // Reflect every command about the vertical centre line. Angles map θ -> π - θ,
+    // so arcs and bands swap their start and end; glyphs are repositioned, not flipped.
+    fn mirror_horizontally(&mut self, width: usize) {
+        use std::f64::consts::{PI, TAU};
+        let mirror_x = |x: i32| width as i32 - 1 - x;
+        let mut pending: Vec<&mut DrawCommand> = self.commands_mut().collect();
+        while let Some(command) = pending.pop() {
+            match command {
+                DrawCommand::Clear(_) => {}
+                DrawCommand::FillRect { x, width, .. }
+                | DrawCommand::RoundedRect { x, width, .. } => {
+                    *x = mirror_x(*x + *width as i32 - 1);
+                }
+                DrawCommand::Arc {
+                    cx,
+                    start_angle,
+                    arc_span,
+                    ..
+                } => {
+                    *cx = mirror_x(*cx);
+                    *start_angle = (PI - (*start_angle + *arc_span)).rem_euclid(TAU);
+                }
+                DrawCommand::HighlightBand {
+                    cx,
+                    start_angle,
+                    end_angle,
+                    ..
+                } => {
+                    *cx = mirror_x(*cx);
+                    let (start, end) = (*start_angle, *end_angle);
+                    *start_angle = (PI - end).rem_euclid(TAU);
+                    *end_angle = (PI - start).rem_euclid(TAU);
+                }
+                DrawCommand::Tick { cx, angle, .. } => {
+                    *cx = mirror_x(*cx);
+                    *angle = PI - *angle;
+                }
+                DrawCommand::Text { x, .. } => {
+                    *x = mirror_x(*x);
+                }
+                DrawCommand::CurvedText {
+                    cx, start_angle, ..
+                } => {
+                    *cx = mirror_x(*cx);
+                    *start_angle = PI - *start_angle;
+                }
+                DrawCommand::NeedleLine { x0, x1, .. } => {
+                    *x0 = mirror_x(*x0);
+                    *x1 = mirror_x(*x1);
+                }
+                DrawCommand::Circle { cx, .. } => {
+                    *cx = mirror_x(*cx);
+                }
+                DrawCommand::Polygon { points, .. } => {
+                    for (x, _) in points {
+                        *x = width as f64 - 1.0 - *x;
+                    }
+                }
+                DrawCommand::Image { x, .. } => {
+                    *x = mirror_x(*x);
+                }
+                DrawCommand::Translucent { commands, .. } => {
+                    pending.extend(commands.iter_mut());
+                }
+            }
+        }
+    }
+
+    pub fn render(&self, rasterizer: &mut impl Rasterizer, config: &InstrumentConfig) {
+        trace_span!("rasterize");
+        for command in self.commands() {
+            trace_span!("draw", kind = command.kind());
+            rasterizer.draw(command, config);
+        }
+    }
+
+    // Like `render`, but replaces `costs` with the time spent on each kind of command
+    fn render_profiled(
+        &self,
+        rasterizer: &mut impl Rasterizer,
+        config: &InstrumentConfig,
+        costs: &mut CommandCosts,
+    ) {
+        trace_span!("rasterize");
+        costs.clear();
+        for command in self.commands() {
+            trace_span!("draw", kind = command.kind());
+            let started = Instant::now();
+            rasterizer.draw(command, config);
+            let kind = command.kind();
+            match costs.iter_mut().find(|(k, _)| *k == kind) {
+                Some((_, total)) => *total += started.elapsed(),
+                None => costs.push((kind, started.elapsed())),
+            }
+        }
+    }
+}
+
+// ============================================================================
+// RASTERIZER BACKENDS
+// ============================================================================
+
+/// A backend that turns draw commands into output. The software `Canvas` is the default;
+/// alternative renderers only need to implement `draw`.
+pub trait Rasterizer {
+    fn draw(&mut self, command: &DrawCommand, config: &InstrumentConfig);
+}
+
+impl Rasterizer for Canvas<'_> {
+    fn draw(&mut self, command: &DrawCommand, config: &InstrumentConfig) {
+        match command {
+            DrawCommand::Clear(color) => {
+                self.clear(*color);
+            }
+            DrawCommand::FillRect {
+                x,
+                y,
+                width,
+                height,
+                color,
+            } => {
+                self.fill_rect(*x, *y, *width, *height, *color);
+            }
+            DrawCommand::RoundedRect {
+                x,
+                y,
+                width,
+                height,
+                radius,
+                stroke,
+                color,
+            } => {
+                let bounds = (
+                    *x as f64 - 0.5,
+                    *y as f64 - 0.5,
+                    *width as f64,
+                    *height as f64,
+                );
+                draw_rounded_rect_aa(self, bounds, *radius, *stroke, *color);
+            }
+            DrawCommand::Arc {
+                cx,
+                cy,
+                r,
+                thickness,
+                start_angle,
+                arc_span,
+                color,
+                style,
+                cap,
+            } => {
+                render_arc_immediate(
+                    self,
+                    *cx,
+                    *cy,
+                    *r,
+                    *thickness,
+                    *start_angle,
+                    *arc_span,
+                    *color,
+                    *style,
+                    *cap,
+                );
+            }
+            DrawCommand::HighlightBand {
+                cx,
+                cy,
+                r,
+                start_angle,
+                end_angle,
+                inner_radius,
+                outer_radius,
+                alpha,
+            } => {
+                render_highlight_band_immediate(
+                    self,
+                    *cx,
+                    *cy,
+                    *r,
+                    (*start_angle, *end_angle),
+                    (*inner_radius, *outer_radius),
+                    *alpha,
+                    config,
+                );
+            }
+            DrawCommand::Tick {
+                cx,
+                cy,
+                r,
+                angle,
+                length,
+                thickness,
+                color,
+                style,
+            } => {
+                let outer_x = *cx as f64 + angle.cos() * (*r as f64 - 1.0);
+                let outer_y = *cy as f64 + angle.sin() * (*r as f64 - 1.0);
+                let inner_x = *cx as f64 + angle.cos() * (*r as f64 - *length as f64);
+                let inner_y = *cy as f64 + angle.sin() * (*r as f64 - *length as f64);
+                draw_thick_line_aa(
+                    self,
+                    inner_x.round() as i32,
+                    inner_y.round() as i32,
+                    outer_x.round() as i32,
+                    outer_y.round() as i32,
+                    *thickness,
+                    color.0,
+                    color.1,
+                    color.2,
+                    *style,
+                );
+            }
+            DrawCommand::Text {
+                x,
+                y,
+                text,
+                font_size,
+                color,
+            } => {
+                draw_text(self, *x, *y, text, text_shaper(config), *font_size, *color);
+            }
+            DrawCommand::CurvedText {
+                cx,
+                cy,
+                radius,
+                text,
+                font_size,
+                arc_span,
+                start_angle,
+                inverted,
+                letter_spacing,
+                fit,
+                color,
+            } => {
+                let shaper = text_shaper(config);
+                let (font_size, letter_spacing) = fit_curved_text(
+                    shaper,
+                    text,
+                    *font_size,
+                    *letter_spacing,
+                    *fit,
+                    *radius * *arc_span,
+                );
+                draw_curved_text(
+                    self,
+                    *cx,
+                    *cy,
+                    *radius,
+                    text,
+                    shaper,
+                    font_size,
+                    letter_spacing,
+                    *arc_span,
+                    *start_angle,
+                    *inverted,
+                    *color,
+                );
+            }
+            DrawCommand::NeedleLine {
+                x0,
+                y0,
+                x1,
+                y1,
+                thickness,
+                tapered,
+                color,
+                tip,
+            } => {
+                if let Some(tip) = tip {
+                    draw_segmented_line_aa(
+                        self,
+                        (*x0, *y0),
+                        (*x1, *y1),
+                        (*thickness, *color),
+                        *tapered,
+                        tip,
+                    );
+                } else if *tapered {
+                    draw_thick_line_tapered_aa(
+                        self, *x0, *y0, *x1, *y1, *thickness, color.0, color.1, color.2,
+                    );
+                } else {
+                    draw_thick_line_aa(
+                        self,
+                        *x0,
+                        *y0,
+                        *x1,
+                        *y1,
+                        *thickness,
+                        color.0,
+                        color.1,
+                        color.2,
+                        LineStyle::Solid,
+                    );
+                }
+            }
+            DrawCommand::Circle {
+                cx,
+                cy,
+                radius,
+                color,
+            } => {
+                draw_circle(self, *cx, *cy, *radius, color.0, color.1, color.2);
+            }
+            DrawCommand::Polygon { points, color } => {
+                fill_polygon_aa(self, points, *color);
+            }
+            DrawCommand::Image {
+                x,
+                y,
+                width,
+                height,
+                bitmap,
+            } => {
+                draw_bitmap(self, *x, *y, *width, *height, bitmap);
+            }
+            DrawCommand::Translucent { opacity, commands } => {
+                let opacity = opacity.clamp(0.0, 1.0);
+                if opacity >= 1.0 {
+                    for command in commands {
+                        self.draw(command, config);
+                    }
+                    return;
+                }
+                // Draw the group opaque, then fade it back towards what was there before,
+                // keeping only the rows of the group's bounds
+                let bounds = command_bounds(command, self.width, self.height, config);
+                let size = self.format.bytes_per_pixel();
+                let (left, right) = (bounds.left as usize * size, bounds.right as usize * size);
+                let rows = bounds.top as usize..bounds.bottom.max(bounds.top) as usize;
+                let stride = self.width * size;
+                let row_span = |row: usize| row * stride + left..row * stride + right;
+                let beneath: Vec<u8> = rows
+                    .clone()
+                    .flat_map(|row| self.frame[row_span(row)].iter().copied())
+                    .collect();
+                for command in commands {
+                    self.draw(command, config);
+                }
+                let format = self.format;
+                let fade = |drawn: u8, beneath: u8| {
+                    (beneath as f64 + (drawn as f64 - beneath as f64) * opacity).round() as u8
+                };
+                let beneath_rows = beneath.chunks_exact((right - left).max(1));
+                for (row, beneath) in rows.zip(beneath_rows) {
+                    for (pixel, beneath) in self.frame[row_span(row)]
+                        .chunks_exact_mut(size)
+                        .zip(beneath.chunks_exact(size))
+                    {
+                        if pixel != beneath {
+                            let (drawn, beneath) = (format.load(pixel), format.load(beneath));
+                            let faded = (
+                                fade(drawn.0, beneath.0),
+                                fade(drawn.1, beneath.1),
+                                fade(drawn.2, beneath.2),
+                            );
+                            format.store(pixel, faded);
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+// ============================================================================
+// SVG EXPORT
+// ============================================================================
+
+// Serializes draw commands to SVG. Geometry mirrors the software rasterizer:
+// arcs and bands grow inwards from `r`, text is centred on its anchor point.
+struct SvgRasterizer {
+    out: String,
+    width: usize,
+    height: usize,
+    next_id: usize,
+}
+
+impl SvgRasterizer {
+    fn new(width: usize, height: usize, config: &InstrumentConfig) -> Self {
+        use std::fmt::Write;
+
+        let (svg_width, svg_height) = if config.rotation.swaps_axes() {
+            (height, width)
+        } else {
+            (width, height)
+        };
+        let transform = match config.rotation {
+            Rotation::None => String::new(),
+            Rotation::Cw90 => format!(r#" transform="translate({} 0) rotate(90)""#, height),
+            Rotation::Cw180 => format!(
+                r#" transform="translate({} {}) rotate(180)""#,
+                width, height
+            ),
+            Rotation::Cw270 => format!(r#" transform="translate(0 {}) rotate(270)""#, width),
+        };
+
+        let mut out = String::new();
+        let _ = writeln!(
+            out,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}" height="{h}" viewBox="0 0 {w} {h}">"#,
+            w = svg_width,
+            h = svg_height,
+        );
+        let _ = writeln!(
+            out,
+            r#"<g{} font-family="Berkeley Mono, monospace">"#,
+            transform
+        );
+
+        Self {
+            out,
+            width,
+            height,
+            next_id: 0,
+        }
+    }
+
+    fn finish(mut self) -> String {
+        self.out.push_str("</g>\n</svg>\n");
+        self.out
+    }
+}
+
+impl Rasterizer for SvgRasterizer {
+    fn draw(&mut self, command: &DrawCommand, config: &InstrumentConfig) {
+        use std::fmt::Write;
+
+        match command {
+            DrawCommand::Clear(color) => {
+                let _ = writeln!(
+                    self.out,
+                    r#"<rect width="{}" height="{}" fill="{}"/>"#,
+                    self.width,
+                    self.height,
+                    svg_color(*color)
+                );
+            }
+            DrawCommand::FillRect {
+                x,
+                y,
+                width,
+                height,
+                color,
+            } => {
+                let _ = writeln!(
+                    self.out,
+                    r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"/>"#,
+                    x,
+                    y,
+                    width,
+                    height,
+                    svg_color(*color)
+                );
+            }
+            DrawCommand::RoundedRect {
+                x,
+                y,
+                width,
+                height,
+                radius,
+                stroke,
+                color,
+            } => {
+                let paint = match stroke {
+                    Some(stroke) => format!(
+                        r#"fill="none" stroke="{}" stroke-width="{}""#,
+                        svg_color(*color),
+                        stroke
+                    ),
+                    None => format!(r#"fill="{}""#, svg_color(*color)),
+                };
+                let _ = writeln!(
+                    self.out,
+                    r#"<rect x="{}" y="{}" width="{}" height="{}" rx="{:.2}" {}/>"#,
+                    x, y, width, height, radius, paint
+                );
+            }
+            DrawCommand::Arc {
+                cx,
+                cy,
+                r,
+                thickness,
+                start_angle,
+                arc_span,
+                color,
+                style,
+                cap,
+            } => {
+                let radius = *r as f64 - *thickness as f64 / 2.0;
+                let linecap = match cap {
+                    ArcCap::Butt => "",
+                    ArcCap::Round => r#" stroke-linecap="round""#,
+                };
+                let _ = writeln!(
+                    self.out,
+                    r#"<path d="{}" fill="none" stroke="{}" stroke-width="{}"{}{}/>"#,
+                    svg_arc_path(*cx, *cy, radius, *start_angle, *arc_span),
+                    svg_color(*color),
+                    thickness,
+                    svg_dash(*style, *thickness as f64),
+                    linecap
+                );
+            }
+            DrawCommand::HighlightBand {
+                cx,
+                cy,
+                r,
+                start_angle,
+                end_angle,
+                inner_radius,
+                outer_radius,
+                alpha,
+            } => {
+                let span = if end_angle >= start_angle {
+                    end_angle - start_angle
+                } else {
+                    end_angle + std::f64::consts::TAU - start_angle
+                };
+                let radius = *r as f64 - (inner_radius + outer_radius) / 2.0;
+                let _ = writeln!(
+                    self.out,
+                    r#"<path d="{}" fill="none" stroke="{}" stroke-opacity="{}" stroke-width="{}"/>"#,
+                    svg_arc_path(*cx, *cy, radius, *start_angle, span),
+                    svg_color(config.highlight_band_color.as_tuple()),
+                    config.highlight_band_alpha * alpha,
+                    (inner_radius - outer_radius).abs()
+                );
+            }
+            DrawCommand::Tick {
+                cx,
+                cy,
+                r,
+                angle,
+                length,
+                thickness,
+                color,
+                style,
+            } => {
+                let outer = *r as f64 - 1.0;
+                let inner = (*r - *length) as f64;
+                let _ = writeln!(
+                    self.out,
+                    r#"<line x1="{:.2}" y1="{:.2}" x2="{:.2}" y2="{:.2}" stroke="{}" stroke-width="{}" stroke-linecap="round"{}/>"#,
+                    *cx as f64 + angle.cos() * inner,
+                    *cy as f64 + angle.sin() * inner,
+                    *cx as f64 + angle.cos() * outer,
+                    *cy as f64 + angle.sin() * outer,
+                    svg_color(*color),
+                    thickness,
+                    svg_dash(*style, *thickness as f64)
+                );
+            }
+            DrawCommand::Text {
+                x,
+                y,
+                text,
+                font_size,
+                color,
+            } => {
+                let _ = writeln!(
+                    self.out,
+                    r#"<text x="{}" y="{}" font-size="{}" fill="{}" text-anchor="middle" dominant-baseline="central">{}</text>"#,
+                    x,
+                    y,
+                    font_size,
+                    svg_color(*color),
+                    svg_escape(text)
+                );
+            }
+            DrawCommand::CurvedText {
+                cx,
+                cy,
+                radius,
+                text,
+                font_size,
+                arc_span,
+                start_angle,
+                inverted,
+                letter_spacing,
+                fit,
+                color,
+            } => {
+                if text.is_empty() {
+                    return;
+                }
+                let (font_size, letter_spacing) = fit_curved_text(
+                    text_shaper(config),
+                    text,
+                    *font_size,
+                    *letter_spacing,
+                    *fit,
+                    *radius * *arc_span,
+                );
+                // Half-circle guide centred on the text's centre angle, running
+                // counter-clockwise for inverted text so it reads upright
+                self.next_id += 1;
+                let id = format!("curved-text-{}", self.next_id);
+                let guide = if *inverted {
+                    let (from, to) = (
+                        start_angle + std::f64::consts::FRAC_PI_2,
+                        start_angle - std::f64::consts::FRAC_PI_2,
+                    );
+                    format!(
+                        "M {:.2} {:.2} A {:.2} {:.2} 0 0 0 {:.2} {:.2}",
+                        *cx as f64 + from.cos() * radius,
+                        *cy as f64 + from.sin() * radius,
+                        radius,
+                        radius,
+                        *cx as f64 + to.cos() * radius,
+                        *cy as f64 + to.sin() * radius,
+                    )
+                } else {
+                    svg_arc_path(
+                        *cx,
+                        *cy,
+                        *radius,
+                        start_angle - std::f64::consts::FRAC_PI_2,
+                        std::f64::consts::PI,
+                    )
+                };
+                let _ = writeln!(self.out, r#"<path id="{}" d="{}" fill="none"/>"#, id, guide);
+                let _ = writeln!(
+                    self.out,
+                    r##"<text font-size="{}" letter-spacing="{:.2}" fill="{}" text-anchor="middle" dominant-baseline="central"><textPath href="#{}" startOffset="50%">{}</textPath></text>"##,
+                    font_size,
+                    letter_spacing,
+                    svg_color(*color),
+                    id,
+                    svg_escape(text)
+                );
+            }
+            DrawCommand::NeedleLine {
+                x0,
+                y0,
+                x1,
+                y1,
+                thickness,
+                tapered,
+                color,
+                tip,
+            } => {
+                let (start, end) = ((*x0 as f64, *y0 as f64), (*x1 as f64, *y1 as f64));
+                let split = tip.map_or(1.0, |tip| 1.0 - tip.fraction.clamp(0.0, 1.0));
+                let join = (
+                    start.0 + (end.0 - start.0) * split,
+                    start.1 + (end.1 - start.1) * split,
+                );
+                // Same 95% taper as draw_thick_line_tapered_aa
+                let taper = |thickness: f32, t: f64| {
+                    if *tapered {
+                        thickness as f64 * (1.0 - t * 0.95)
+                    } else {
+                        thickness as f64
+                    }
+                };
+                let mut segments = vec![(start, join, *thickness, 0.0, split, *color)];
+                if let Some(tip) = tip {
+                    segments.push((join, end, tip.thickness, split, 1.0, tip.color));
+                }
+                for (from, to, thickness, t0, t1, color) in segments {
+                    if t1 <= t0 {
+                        continue;
+                    }
+                    if *tapered {
+                        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+                        let len = (dx * dx + dy * dy).sqrt().max(f64::EPSILON);
+                        let (nx, ny) = (-dy / len, dx / len);
+                        let (base, tip) = (taper(thickness, t0) / 2.0, taper(thickness, t1) / 2.0);
+                        let _ = writeln!(
+                            self.out,
+                            r#"<polygon points="{:.2},{:.2} {:.2},{:.2} {:.2},{:.2} {:.2},{:.2}" fill="{}"/>"#,
+                            from.0 + nx * base,
+                            from.1 + ny * base,
+                            to.0 + nx * tip,
+                            to.1 + ny * tip,
+                            to.0 - nx * tip,
+                            to.1 - ny * tip,
+                            from.0 - nx * base,
+                            from.1 - ny * base,
+                            svg_color(color)
+                        );
+                    } else {
+                        let _ = writeln!(
+                            self.out,
+                            r#"<line x1="{:.2}" y1="{:.2}" x2="{:.2}" y2="{:.2}" stroke="{}" stroke-width="{}" stroke-linecap="round"/>"#,
+                            from.0,
+                            from.1,
+                            to.0,
+                            to.1,
+                            svg_color(color),
+                            thickness
+                        );
+                    }
+                }
+            }
+            DrawCommand::Circle {
+                cx,
+                cy,
+                radius,
+                color,
+            } => {
+                let _ = writeln!(
+                    self.out,
+                    r#"<circle cx="{}" cy="{}" r="{}" fill="{}"/>"#,
+                    cx,
+                    cy,
+                    radius,
+                    svg_color(*color)
+                );
+            }
+            DrawCommand::Polygon { points, color } => {
+                let points: Vec<String> = points
+                    .iter()
+                    .map(|(x, y)| format!("{x:.2},{y:.2}"))
+                    .collect();
+                let _ = writeln!(
+                    self.out,
+                    r#"<polygon points="{}" fill="{}"/>"#,
+                    points.join(" "),
+                    svg_color(*color)
+                );
+            }
+            DrawCommand::Image {
+                x,
+                y,
+                width,
+                height,
+                bitmap,
+            } => {
+                // One rect per source pixel keeps the output dependency-free; icons are small
+                let (sx, sy) = (
+                    *width as f64 / bitmap.width.max(1) as f64,
+                    *height as f64 / bitmap.height.max(1) as f64,
+                );
+                let (left, top) = (
+                    *x as f64 - *width as f64 / 2.0,
+                    *y as f64 - *height as f64 / 2.0,
+                );
+                let _ = writeln!(self.out, "<g shape-rendering=\"crispEdges\">");
+                for (i, px) in bitmap.pixels.chunks_exact(4).enumerate() {
+                    if px[3] == 0 {
+                        continue;
+                    }
+                    let (col, row) = (i % bitmap.width, i / bitmap.width);
+                    let _ = writeln!(
+                        self.out,
+                        r#"<rect x="{:.2}" y="{:.2}" width="{:.2}" height="{:.2}" fill="{}" fill-opacity="{:.3}"/>"#,
+                        left + col as f64 * sx,
+                        top + row as f64 * sy,
+                        sx,
+                        sy,
+                        svg_color((px[0], px[1], px[2])),
+                        px[3] as f64 / 255.0
+                    );
+                }
+                let _ = writeln!(self.out, "</g>");
+            }
+            DrawCommand::Translucent { opacity, commands } => {
+                let _ = writeln!(self.out, r#"<g opacity="{:.3}">"#, opacity.clamp(0.0, 1.0));
+                for command in commands {
+                    self.draw(command, config);
+                }
+                let _ = writeln!(self.out, "</g>");
+            }
+        }
+    }
+}
+
+fn svg_color(color: (u8, u8, u8)) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.0, color.1, color.2)
+}
+
+fn svg_dash(style: LineStyle, thickness: f64) -> String {
+    match style.pattern(thickness) {
+        Some((on, period)) => format!(r#" stroke-dasharray="{on:.2} {:.2}""#, period - on),
+        None => String::new(),
+    }
+}
+
+fn svg_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// Clockwise (screen-space) arc path from `start_angle` sweeping `arc_span` radians
+fn svg_arc_path(cx: i32, cy: i32, radius: f64, start_angle: f64, arc_span: f64) -> String {
+    let arc_span = arc_span.min(std::f64::consts::TAU - 1e-6);
+    let end_angle = start_angle + arc_span;
+    let large_arc = if arc_span > std::f64::consts::PI {
+        1
+    } else {
+        0
+    };
+    format!(
+        "M {:.2} {:.2} A {:.2} {:.2} 0 {} 1 {:.2} {:.2}",
+        cx as f64 + start_angle.cos() * radius,
+        cy as f64 + start_angle.sin() * radius,
+        radius,
+        radius,
+        large_arc,
+        cx as f64 + end_angle.cos() * radius,
+        cy as f64 + end_angle.sin() * radius,
+    )
+}
+
+// ============================================================================
+// CORE DATA TYPES
+// ============================================================================
+
+/// Memory layout of one pixel in a frame, for [`InstrumentConfig::pixel_format`].
+/// Frames are always opaque; where a format has alpha it is set to 0xff.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum PixelFormat {
+    /// Red, green, blue and alpha bytes
+    #[default]
+    Rgba8,
+    /// Blue, green, red and alpha bytes, as on most 32-bit Linux framebuffers
+    Bgra8,
+    /// Five bits of red, six of green and five of blue in a little-endian `u16`, as on
+    /// small SPI TFT panels and 16-bit framebuffers
+    Rgb565,
+    /// A single luma byte, for e-paper and monochrome panels
+    Gray8,
+}
+
+impl PixelFormat {
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            PixelFormat::Rgba8 | PixelFormat::Bgra8 => 4,
+            PixelFormat::Rgb565 => 2,
+            PixelFormat::Gray8 => 1,
+        }
+    }
+
+    // Writes an opaque colour into a pixel of `bytes_per_pixel` bytes
+    fn store(self, pixel: &mut [u8], (r, g, b): (u8, u8, u8)) {
+        match self {
+            PixelFormat::Rgba8 => pixel.copy_from_slice(&[r, g, b, 0xff]),
+            PixelFormat::Bgra8 => pixel.copy_from_slice(&[b, g, r, 0xff]),
+            PixelFormat::Rgb565 => {
+                let packed = ((r as u16 >> 3) << 11) | ((g as u16 >> 2) << 5) | (b as u16 >> 3);
+                pixel.copy_from_slice(&packed.to_le_bytes());
+            }
+            // Rec. 601 weights, in fixed point
+            PixelFormat::Gray8 => {
+                pixel[0] = ((r as u32 * 299 + g as u32 * 587 + b as u32 * 114 + 500) / 1000) as u8
+            }
+        }
+    }
+
+    // The colour of a pixel, with narrow channels widened to fill 0..=255
+    fn load(self, pixel: &[u8]) -> (u8, u8, u8) {
+        match self {
+            PixelFormat::Rgba8 => (pixel[0], pixel[1], pixel[2]),
+            PixelFormat::Bgra8 => (pixel[2], pixel[1], pixel[0]),
+            PixelFormat::Rgb565 => {
+                let packed = u16::from_le_bytes([pixel[0], pixel[1]]);
+                let (r, g, b) = (packed >> 11, (packed >> 5) & 0x3f, packed & 0x1f);
+                (
+                    (r << 3 | r >> 2) as u8,
+                    (g << 2 | g >> 4) as u8,
+                    (b << 3 | b >> 2) as u8,
+                )
+            }
+            PixelFormat::Gray8 => (pixel[0], pixel[0], pixel[0]),
+        }
+    }
+}
+
+/// The software rasterizer, drawing into a buffer of `width * height` pixels, RGBA8
+/// unless made with [`with_format`](Self::with_format)
+pub struct Canvas<'a> {
+    frame: &'a mut [u8],
+    width: usize,
+    height: usize,
+    format: PixelFormat,
+}
+
+impl<'a> Canvas<'a> {
+    pub fn new(frame: &'a mut [u8], width: usize, height: usize) -> Self {
+        assert_eq!(frame.len(), width * height * 4, "frame must be RGBA8");
+        Self::with_format(frame, width, height, PixelFormat::Rgba8)
+    }
+
+    /// Like [`new`](Self::new), but for a frame of `width * height` pixels in `format`
+    pub fn with_format(
+        frame: &'a mut [u8],
+        width: usize,
+        height: usize,
+        format: PixelFormat,
+    ) -> Self {
+        assert_eq!(
+            frame.len(),
+            width * height * format.bytes_per_pixel(),
+            "frame must be {format:?}"
+        );
+        Self {
+            frame,
+            width,
+            height,
+            format,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn format(&self) -> PixelFormat {
+        self.format
+    }
+
+    fn clear(&mut self, color: (u8, u8, u8)) {
+        let size = self.format.bytes_per_pixel();
+        let mut pixel = [0; 4];
+        self.format.store(&mut pixel[..size], color);
+        for chunk in self.frame.chunks_exact_mut(size) {
+            chunk.copy_from_slice(&pixel[..size]);
+        }
+    }
+
+    // Copies the pixels of `rect`, right and bottom exclusive, from a same-sized frame
+    fn restore(&mut self, source: &[u8], rect: &Rect) {
+        let (left, right) = (
+            rect.left.max(0) as usize,
+            (rect.right.max(0) as usize).min(self.width),
+        );
+        let (top, bottom) = (
+            rect.top.max(0) as usize,
+            (rect.bottom.max(0) as usize).min(self.height),
+        );
+        if left >= right {
+            return;
+        }
+        let size = self.format.bytes_per_pixel();
+        for row in top..bottom {
+            let span = (row * self.width + left) * size..(row * self.width + right) * size;
+            self.frame[span.clone()].copy_from_slice(&source[span]);
+        }
+    }
+
+    fn fill_rect(&mut self, x: i32, y: i32, width: usize, height: usize, color: (u8, u8, u8)) {
+        let (x0, y0) = (x.max(0) as usize, y.max(0) as usize);
+        let x1 = ((x + width as i32).max(0) as usize).min(self.width);
+        let y1 = ((y + height as i32).max(0) as usize).min(self.height);
+        let size = self.format.bytes_per_pixel();
+        let mut pixel = [0; 4];
+        self.format.store(&mut pixel[..size], color);
+        for row in y0..y1 {
+            for chunk in self.frame
+                [(row * self.width + x0) * size..(row * self.width + x1.max(x0)) * size]
+                .chunks_exact_mut(size)
+            {
+                chunk.copy_from_slice(&pixel[..size]);
+            }
+        }
+    }
+}
+
+/// Shortest gap between writes of the totalizer file while the total keeps changing
+const TOTALIZER_SAVE_INTERVAL: Duration = Duration::from_secs(1);
+
+// Running total behind `InstrumentConfig::totalizer`, persisted to its file if any
+struct Totalizer {
+    total: f64,
+    file: Option<std::path::PathBuf>,
+    dirty: bool,
+    last_saved: Option<Instant>,
+}
+
+impl Totalizer {
+    // A missing or unreadable file starts the count from zero
+    fn load(file: Option<std::path::PathBuf>) -> Self {
+        let total = file
+            .as_ref()
+            .and_then(|file| std::fs::read_to_string(file).ok())
+            .and_then(|text| text.trim().parse::<f64>().ok())
+            .filter(|total| total.is_finite())
+            .unwrap_or(0.0);
+        Self {
+            total,
+            file,
+            dirty: false,
+            last_saved: None,
+        }
+    }
+
+    fn set(&mut self, total: f64) {
+        self.total = total;
+        self.dirty = true;
+        self.flush();
+    }
+
+    // Saves a changed total once the save interval has passed, so the end of a burst of
+    // changes reaches the file even if no further change arrives
+    fn flush(&mut self) {
+        if self.dirty
+            && self
+                .last_saved
+                .is_none_or(|saved| saved.elapsed() >= TOTALIZER_SAVE_INTERVAL)
+        {
+            self.save();
+        }
+    }
+
+    // Write errors are dropped: the display carries on and the next save retries
+    fn save(&mut self) {
+        let Some(ref file) = self.file else {
+            return;
+        };
+        if self.dirty && write_atomically(file, &format!("{}\n", self.total)).is_ok() {
+            self.dirty = false;
+        }
+        self.last_saved = Some(Instant::now());
+    }
+}
+
+impl Drop for Totalizer {
+    fn drop(&mut self) {
+        self.save();
+    }
+}
+
+struct AppState {
+    needle1: Option<Needle>,
+    needle2: Option<Needle>,
+    chronograph: Option<Needle>,
+    secondary_chronograph: Option<Needle>,
+    readout_value: Option<f64>,
+    min_value: f64,
+    max_value: f64,
+    scale_break: Option<ScaleBreak>,
+    secondary_range: Option<(f64, f64)>,
+    chronograph_range: (f64, f64),
+    secondary_chronograph_range: (f64, f64),
+    highlight_bounds: Option<(f64, f64)>, // As drawn; glides towards `highlight_target`
+    highlight_target: Option<(f64, f64)>,
+    highlight_lerp_factor: f64,
+    // Suppresses the band without forgetting its bounds
+    highlight_visible: bool,
+    highlight_transition: Option<BandTransition>, // Replaces the lerp while fading or timed
+    highlight_alpha: f64,
+    zones: Vec<(Zone, BandTransition)>,
+    zone_transition_duration: f64,
+    range_transition: Option<RangeTransition>,
+    range_transition_duration: f64,
+    primary_dynamics: NeedleDynamics,
+    secondary_dynamics: NeedleDynamics,
+    chronograph_dynamics: NeedleDynamics,
+    secondary_chronograph_dynamics: NeedleDynamics,
+    stopwatch: Option<Stopwatch>,
+    chronograph_sweep: ChronographSweep,
+    chronograph_peak: Option<(f64, Instant)>, // Highest value and when it was reached
+    chronograph_peak_hold: Option<f64>,
+    wrap_around: bool,
+    started: Instant,
+    fixed_clock: Option<(Instant, Duration)>, // Animation time and its step per frame
+    brightness: f32,
+    layer_order: Vec<Layer>,
+    hidden_layers: Vec<Layer>,
+    animation: Option<(Animation, Instant)>,
+    held_readout: Option<Option<f64>>, // Readout frozen by `HoldReadout`
+    sampled_readout: Option<f64>,      // Readout as last refreshed
+    readout_sampled_at: Option<Instant>,
+    readout_update_interval: f64,
+    power_on_sweep: Option<(Instant, f64)>, // started, duration
+    alerts: Vec<(AlertRule, Option<Instant>)>, // Each rule with when its condition began
+    history: std::collections::VecDeque<HistorySample>,
+    history_duration: f64,
+    totalizer: Totalizer,
+    tell_tales: Option<(f64, f64)>, // Lowest and highest primary value commanded
+    failure: Option<String>,
+    needle_blinks: HashMap<NeedleId, (NeedleBlink, Instant)>, // Each with when it began
+    target_bug: Option<f64>,
+    calibrations: HashMap<String, Calibration>,
+    derived_channels: Vec<DerivedChannel>,
+    #[cfg(feature = "scripting")]
+    value_script: Option<ValueScript>,
+    inputs: HashMap<String, f64>, // Latest calibrated value of every key
+    dead_band: f64,
+    sequence: Option<u64>,              // Last `Sequence` id applied
+    acknowledged_sequence: Option<u64>, // Last one handed to the sequence hook
+    frames_presented: u64,
+    frame_started: Instant, // When the frame being drawn began updating
+    scene_cache: std::cell::RefCell<Scene>, // Last frame's scene, updated in place
+    background_cache: std::cell::RefCell<BackgroundCache>,
+}
+
+// Pixels of the static layers, kept across frames for `InstrumentConfig::cached_background`
+#[derive(Default)]
+struct BackgroundCache {
+    key: u64, // Fingerprint of the size and sections the pixels were drawn from
+    pixels: Vec<u8>,
+    // Address and length of the buffer last drawn into, while it still holds that frame
+    frame: Option<(usize, usize)>,
+    damage: Vec<Rect>, // Where the dynamic layers drew in that frame
+}
+
+struct Stopwatch {
+    started: Option<Instant>,
+    accumulated: f64,
+}
+
+impl Stopwatch {
+    fn elapsed(&self, now: Instant) -> f64 {
+        self.accumulated + self.started.map_or(0.0, |s| seconds_between(s, now))
+    }
+}
+
+struct RangeTransition {
+    from: (f64, f64),
+    to: (f64, f64),
+    started: Instant,
+}
+
+// Lower bound, upper bound and opacity of a zone or the highlight band, easing from
+// `from` to `to` over the zone transition
+#[derive(Debug, Clone, Copy)]
+struct BandTransition {
+    from: (f64, f64, f64),
+    to: (f64, f64, f64),
+    started: Instant,
+}
+
+impl BandTransition {
+    fn new(from: (f64, f64, f64), to: (f64, f64, f64), now: Instant) -> Self {
+        Self {
+            from,
+            to,
+            started: now,
+        }
+    }
+
+    fn settled(at: (f64, f64, f64), now: Instant) -> Self {
+        Self::new(at, at, now)
+    }
+
+    fn sample(&self, duration: f64, now: Instant) -> (f64, f64, f64) {
+        let t = if duration > 0.0 {
+            (seconds_between(self.started, now) / duration).clamp(0.0, 1.0)
+        } else {
+            1.0
+        };
+        let eased = t * t * (3.0 - 2.0 * t);
+        let mix = |a: f64, b: f64| a + (b - a) * eased;
+        (
+            mix(self.from.0, self.to.0),
+            mix(self.from.1, self.to.1),
+            mix(self.from.2, self.to.2),
+        )
+    }
+
+    fn finished(&self, duration: f64, now: Instant) -> bool {
+        seconds_between(self.started, now) >= duration
+    }
+
+    fn fading_out(&self) -> bool {
+        self.to.2 <= 0.0
+    }
+}
+
+impl AppState {
+    fn new(config: &InstrumentConfig) -> Self {
+        let dynamics =
+            |motion, max_rate, attack: Option<f64>, release: Option<f64>| NeedleDynamics {
+                motion,
+                quartz_step_duration: config.quartz_step_duration,
+                max_rate,
+                attack: attack.unwrap_or(config.needle_lerp_factor),
+                release: release.unwrap_or(config.needle_lerp_factor),
+                wraps: false,
+            };
+        let started = Instant::now();
+        Self {
+            needle1: None,
+            needle2: None,
+            chronograph: None,
+            secondary_chronograph: None,
+            readout_value: None,
+            min_value: config.range.0,
+            max_value: config.range.1,
+            scale_break: config.scale_break,
+            secondary_range: config.secondary_range,
+            chronograph_range: config.chronograph_range,
+            secondary_chronograph_range: config.secondary_chronograph_range,
+            highlight_bounds: None,
+            highlight_target: None,
+            highlight_lerp_factor: config
+                .highlight_lerp_factor
+                .unwrap_or(config.needle_lerp_factor),
+            highlight_visible: true,
+            highlight_transition: None,
+            highlight_alpha: 1.0,
+            zones: config
+                .zones
+                .iter()
+                .map(|zone| {
+                    let bounds = (zone.from, zone.to, 1.0);
+                    (*zone, BandTransition::settled(bounds, started))
+                })
+                .collect(),
+            zone_transition_duration: config.zone_transition_duration,
+            range_transition: None,
+            range_transition_duration: config.range_transition_duration,
+            primary_dynamics: NeedleDynamics {
+                wraps: config.wrap_around,
+                ..dynamics(
+                    config.needle_motion,
+                    config.needle_max_rate,
+                    config.needle_attack,
+                    config.needle_release,
+                )
+            },
+            secondary_dynamics: NeedleDynamics {
+                wraps: config.wrap_around,
+                ..dynamics(
+                    config.secondary_needle_motion,
+                    config.secondary_needle_max_rate,
+                    config.secondary_needle_attack,
+                    config.secondary_needle_release,
+                )
+            },
+            chronograph_dynamics: dynamics(
+                config.chronograph_needle_motion,
+                config.chronograph_needle_max_rate,
+                config.chronograph_needle_attack,
+                config.chronograph_needle_release,
+            ),
+            secondary_chronograph_dynamics: dynamics(
+                config.secondary_chronograph_needle_motion,
+                config.secondary_chronograph_needle_max_rate,
+                config.secondary_chronograph_needle_attack,
+                config.secondary_chronograph_needle_release,
+            ),
+            stopwatch: None,
+            chronograph_sweep: config.chronograph_sweep,
+            chronograph_peak: None,
+            chronograph_peak_hold: config.chronograph_peak_hold,
+            wrap_around: config.wrap_around,
+            started,
+            fixed_clock: config
+                .fixed_timestep
+                .map(|step| (started, Duration::from_secs_f64(step))),
+            brightness: 1.0,
+            layer_order: config.layer_order.clone(),
+            hidden_layers: config.hidden_layers.clone(),
+            animation: None,
+            held_readout: None,
+            sampled_readout: None,
+            readout_sampled_at: None,
+            readout_update_interval: config.readout_update_interval,
+            power_on_sweep: config
+                .power_on_sweep
+                .then_some((started, config.power_on_sweep_duration)),
+            alerts: config
+                .alert_rules
+                .iter()
+                .map(|rule| (rule.clone(), None))
+                .collect(),
+            history: std::collections::VecDeque::new(),
+            history_duration: config.history_duration,
+            totalizer: Totalizer::load(config.totalizer_file.clone()),
+            tell_tales: None,
+            failure: None,
+            needle_blinks: HashMap::new(),
+            target_bug: None,
+            calibrations: config.calibrations.clone(),
+            derived_channels: config.derived_channels.clone(),
+            #[cfg(feature = "scripting")]
+            value_script: config.value_script.clone(),
+            inputs: HashMap::new(),
+            dead_band: config.dead_band,
+            sequence: None,
+            acknowledged_sequence: None,
+            frames_presented: 0,
+            frame_started: started,
+            scene_cache: std::cell::RefCell::new(Scene::new(0, 0)),
+            background_cache: Default::default(),
+        }
+    }
+
+    fn normalize(&self, value: f64) -> f64 {
+        let value = if self.wrap_around {
+            self.min_value + (value - self.min_value).rem_euclid(self.max_value - self.min_value)
+        } else {
+            value
+        };
+        scale_fraction(
+            value,
+            (self.min_value, self.max_value),
+            self.scale_break.as_ref(),
+        )
+        .clamp(0.0, 1.0)
+    }
+
+    // Secondary values are carried onto the main scale before normalizing, so the needle
+    // follows the main range through transitions and scale breaks
+    fn normalize_secondary(&self, value: f64) -> f64 {
+        let range = (self.min_value, self.max_value);
+        match secondary_conversion(range, self.secondary_range, None) {
+            Some((factor, offset)) => self.normalize((value - offset) / factor),
+            None => self.normalize(value),
+        }
+    }
+
+    // Animation time: the clock, or with a fixed timestep, one step per frame since start
+    fn now(&self) -> Instant {
+        self.fixed_clock.map_or_else(Instant::now, |(now, _)| now)
+    }
+
+    // Animation time since the state was created
+    fn elapsed(&self) -> f64 {
+        seconds_between(self.started, self.now())
+    }
+
+    fn record_history(&mut self, key: &'static str, value: f64) {
+        if self.history_duration <= 0.0 {
+            return;
+        }
+        let time = self.elapsed();
+        while self
+            .history
+            .front()
+            .is_some_and(|sample| time - sample.time > self.history_duration)
+        {
+            self.history.pop_front();
+        }
+        self.history.push_back(HistorySample { time, key, value });
+    }
+
+    fn set_primary_value(&mut self, value: f64) {
+        self.record_history(NeedleId::Primary.name(), value);
+        self.tell_tales = Some(match self.tell_tales {
+            Some((low, high)) => (low.min(value), high.max(value)),
+            None => (value, value),
+        });
+        if self.needle1.is_none() {
+            self.needle1 = Some(Needle::new(self.primary_dynamics));
+        }
+        let (target_pos, now) = (self.normalize(value), self.now());
+        if let Some(ref mut needle) = self.needle1 {
+            needle.target_value = value;
+            needle.set_target_pos(target_pos, now);
+        }
+    }
+
+    fn set_secondary_value(&mut self, value: f64) {
+        self.record_history(NeedleId::Secondary.name(), value);
+        if self.needle2.is_none() {
+            self.needle2 = Some(Needle::new(self.secondary_dynamics));
+        }
+        let (target_pos, now) = (self.normalize_secondary(value), self.now());
+        if let Some(ref mut needle) = self.needle2 {
+            needle.target_value = value;
+            needle.set_target_pos(target_pos, now);
+        }
+    }
+
+    fn set_chronograph_value(&mut self, value: f64) {
+        self.record_history(NeedleId::Chronograph.name(), value);
+        if self.chronograph.is_none() {
+            self.chronograph = Some(Needle::new(self.chronograph_dynamics));
+        }
+        let now = self.now();
+        if let Some(ref mut needle) = self.chronograph {
+            needle.target_value = value;
+            let target_pos = ((value - self.chronograph_range.0)
+                / (self.chronograph_range.1 - self.chronograph_range.0))
+                .clamp(0.0, 1.0);
+            needle.set_target_pos(target_pos, now);
+        }
+        self.record_chronograph_peak(value);
+    }
+
+    // Keeps the highest value until it is beaten or has been held for the full time
+    fn record_chronograph_peak(&mut self, value: f64) {
+        let Some(hold) = self.chronograph_peak_hold else {
+            return;
+        };
+        let now = self.now();
+        self.chronograph_peak = match self.chronograph_peak {
+            Some((peak, at)) if peak >= value && seconds_between(at, now) <= hold => {
+                Some((peak, at))
+            }
+            _ => Some((value, now)),
+        };
+    }
+
+    fn set_secondary_chronograph_value(&mut self, value: f64) {
+        self.record_history(NeedleId::SecondaryChronograph.name(), value);
+        if self.secondary_chronograph.is_none() {
+            self.secondary_chronograph = Some(Needle::new(self.secondary_chronograph_dynamics));
+        }
+        let now = self.now();
+        if let Some(ref mut needle) = self.secondary_chronograph {
+            needle.target_value = value;
+            let target_pos = ((value - self.secondary_chronograph_range.0)
+                / (self.secondary_chronograph_range.1 - self.secondary_chronograph_range.0))
+                .clamp(0.0, 1.0);
+            needle.set_target_pos(target_pos, now);
+        }
+    }
+
+    fn set_readout_value(&mut self, value: f64) {
+        self.record_history("readout", value);
+        self.readout_value = Some(value);
+    }
+
+    // Setting the chronograph directly takes it over from the built-in stopwatch
+    fn set_needle_value(&mut self, id: NeedleId, value: f64) {
+        match id {
+            NeedleId::Primary => self.set_primary_value(value),
+            NeedleId::Secondary => self.set_secondary_value(value),
+            NeedleId::Chronograph => {
+                self.stopwatch = None;
+                self.set_chronograph_value(value);
+            }
+            NeedleId::SecondaryChronograph => self.set_secondary_chronograph_value(value),
+        }
+    }
+
+    fn hold_readout(&mut self, hold: bool) {
+        if !hold {
+            self.held_readout = None;
+        } else if self.held_readout.is_none() {
+            self.held_readout = Some(self.sampled_readout);
+        }
+    }
+
+    fn sample_readout(&mut self) {
+        let now = self.now();
+        if self
+            .readout_sampled_at
+            .is_some_and(|at| seconds_between(at, now) < self.readout_update_interval)
+        {
+            return;
+        }
+        self.sampled_readout = self.readout_value;
+        self.readout_sampled_at = Some(now);
+    }
+
+    fn displayed_readout(&self) -> Option<f64> {
+        self.held_readout.unwrap_or(self.sampled_readout)
+    }
+
+    // A band that isn't showing yet appears in place; after that it glides like a needle.
+    // With a zone transition it fades in and moves on a timer instead
+    fn set_highlight_bounds(&mut self, lower: f64, upper: f64) {
+        let bounds = (lower.min(upper), lower.max(upper));
+        if self.zone_transition_duration > 0.0 {
+            let from = match self.highlight_bounds {
+                Some((lower, upper)) => (lower, upper, self.highlight_alpha),
+                None => (bounds.0, bounds.1, 0.0),
+            };
+            let to = (bounds.0, bounds.1, 1.0);
+            self.highlight_transition = Some(BandTransition::new(from, to, self.now()));
+            self.highlight_bounds = Some((from.0, from.1));
+            self.highlight_alpha = from.2;
+        }
+        self.highlight_target = Some(bounds);
+        self.highlight_bounds.get_or_insert(bounds);
+    }
+
+    fn clear_highlight_bounds(&mut self) {
+        self.highlight_target = None;
+        match self.highlight_bounds {
+            Some((lower, upper)) if self.zone_transition_duration > 0.0 => {
+                self.highlight_transition = Some(BandTransition::new(
+                    (lower, upper, self.highlight_alpha),
+                    (lower, upper, 0.0),
+                    self.now(),
+                ));
+            }
+            _ => {
+                self.highlight_bounds = None;
+                self.highlight_transition = None;
+            }
+        }
+    }
+
+    fn update_highlight_bounds(&mut self) {
+        if let Some(transition) = self.highlight_transition {
+            let (duration, now) = (self.zone_transition_duration, self.now());
+            let (lower, upper, alpha) = transition.sample(duration, now);
+            self.highlight_alpha = alpha;
+            self.highlight_bounds = Some((lower, upper));
+            if transition.finished(duration, now) {
+                self.highlight_transition = None;
+                if transition.fading_out() {
+                    self.highlight_bounds = None;
+                    self.highlight_alpha = 1.0;
+                }
+            }
+            return;
+        }
+        if let (Some(bounds), Some(target)) = (&mut self.highlight_bounds, self.highlight_target) {
+            bounds.0 = lerp(bounds.0, target.0, self.highlight_lerp_factor);
+            bounds.1 = lerp(bounds.1, target.1, self.highlight_lerp_factor);
+        }
+    }
+
+    // Zones are matched up by position: kept ones glide to their new bounds, new ones fade
+    // in where they belong and dropped ones fade out where they are
+    fn set_zones(&mut self, zones: Vec<Zone>) {
+        let (duration, now) = (self.zone_transition_duration, self.now());
+        let (current, fading): (Vec<_>, Vec<_>) = self
+            .zones
+            .drain(..)
+            .partition(|(_, transition)| !transition.fading_out());
+        let mut next = Vec::with_capacity(current.len().max(zones.len()) + fading.len());
+        for i in 0..current.len().max(zones.len()) {
+            let (zone, transition) = match (current.get(i), zones.get(i)) {
+                (Some((_, transition)), Some(zone)) => (
+                    *zone,
+                    BandTransition::new(
+                        transition.sample(duration, now),
+                        (zone.from, zone.to, 1.0),
+                        now,
+                    ),
+                ),
+                (None, Some(zone)) => (
+                    *zone,
+                    BandTransition::new((zone.from, zone.to, 0.0), (zone.from, zone.to, 1.0), now),
+                ),
+                (Some((zone, transition)), None) => {
+                    let (lower, upper, alpha) = transition.sample(duration, now);
+                    (
+                        *zone,
+                        BandTransition::new((lower, upper, alpha), (lower, upper, 0.0), now),
+                    )
+                }
+                (None, None) => unreachable!(),
+            };
+            next.push((zone, transition));
+        }
+        next.extend(fading);
+        self.zones = next;
+    }
+
+    fn update_zones(&mut self) {
+        let (duration, now) = (self.zone_transition_duration, self.now());
+        self.zones.retain(|(_, transition)| {
+            !(transition.fading_out() && transition.finished(duration, now))
+        });
+    }
+
+    // Zones at their bounds and opacity for this frame
+    fn drawn_zones(&self) -> Vec<(Zone, f64)> {
+        let now = self.now();
+        self.zones
+            .iter()
+            .map(|(zone, transition)| {
+                let (from, to, alpha) = transition.sample(self.zone_transition_duration, now);
+                (
+                    Zone {
+                        from,
+                        to,
+                        color: zone.color,
+                    },
+                    alpha,
+                )
+            })
+            .filter(|(_, alpha)| *alpha > 0.0)
+            .collect()
+    }
+
+    fn set_range(&mut self, min_value: f64, max_value: f64) {
+        if self.range_transition_duration <= 0.0 {
+            self.snap_range(min_value, max_value);
+        } else {
+            self.range_transition = Some(RangeTransition {
+                from: (self.min_value, self.max_value),
+                to: (min_value, max_value),
+                started: self.now(),
+            });
+        }
+    }
+
+    fn snap_range(&mut self, min_value: f64, max_value: f64) {
+        self.min_value = min_value;
+        self.max_value = max_value;
+        self.range_transition = None;
+        self.renormalize_needles();
+    }
+
+    fn update_range_transition(&mut self) {
+        let Some(ref transition) = self.range_transition else {
+            return;
+        };
+        let t = (seconds_between(transition.started, self.now()) / self.range_transition_duration)
+            .clamp(0.0, 1.0);
+        let eased = t * t * (3.0 - 2.0 * t);
+        let (from, to) = (transition.from, transition.to);
+        self.min_value = from.0 + (to.0 - from.0) * eased;
+        self.max_value = from.1 + (to.1 - from.1) * eased;
+        if t >= 1.0 {
+            self.range_transition = None;
+        }
+        self.renormalize_needles();
+    }
+
+    // Re-derive needle targets from their raw values after the scale moved
+    fn renormalize_needles(&mut self) {
+        let now = self.now();
+        let targets = (
+            self.needle1
+                .as_ref()
+                .map(|n| self.normalize(n.target_value)),
+            self.needle2
+                .as_ref()
+                .map(|n| self.normalize_secondary(n.target_value)),
+        );
+        for (needle, target) in [
+            (&mut self.needle1, targets.0),
+            (&mut self.needle2, targets.1),
+        ] {
+            if let (Some(needle), Some(target)) = (needle, target) {
+                needle.rescale_target_pos(target, now);
+            }
+        }
+    }
+
+    fn start_stopwatch(&mut self) {
+        let now = self.now();
+        let stopwatch = self.stopwatch.get_or_insert(Stopwatch {
+            started: None,
+            accumulated: 0.0,
+        });
+        if stopwatch.started.is_none() {
+            stopwatch.started = Some(now);
+        }
+    }
+
+    fn stop_stopwatch(&mut self) {
+        let now = self.now();
+        if let Some(ref mut stopwatch) = self.stopwatch {
+            stopwatch.accumulated = stopwatch.elapsed(now);
+            stopwatch.started = None;
+        }
+    }
+
+    fn reset_stopwatch(&mut self) {
+        let running = self
+            .stopwatch
+            .as_ref()
+            .is_some_and(|stopwatch| stopwatch.started.is_some());
+        self.stopwatch = Some(Stopwatch {
+            started: running.then(|| self.now()),
+            accumulated: 0.0,
+        });
+    }
+
+    // Drive the chronograph hand from the stopwatch, wrapping around its range
+    fn update_stopwatch(&mut self) {
+        let Some(ref stopwatch) = self.stopwatch else {
+            return;
+        };
+        let (min, max) = self.chronograph_range;
+        let (span, now) = (max - min, self.now());
+        let elapsed = match self.chronograph_sweep {
+            ChronographSweep::Continuous => stopwatch.elapsed(now),
+            ChronographSweep::Stepped(steps) => {
+                let steps = steps.max(1) as f64;
+                (stopwatch.elapsed(now) * steps).floor() / steps
+            }
+        };
+        let wrapped = if span > 0.0 { elapsed % span } else { 0.0 };
+        let target_pos = if span > 0.0 { wrapped / span } else { 0.0 };
+        let needle = self
+            .chronograph
+            .get_or_insert_with(|| Needle::new(self.chronograph_dynamics));
+        needle.target_value = min + wrapped;
+        match self.chronograph_sweep {
+            ChronographSweep::Continuous => needle.snap_to(target_pos),
+            ChronographSweep::Stepped(_) => needle.set_target_pos(target_pos, now),
+        }
+    }
+
+    // Jump every needle straight to its target, skipping the animation
+    fn settle(&mut self) {
+        [
+            &mut self.needle1,
+            &mut self.needle2,
+            &mut self.chronograph,
+            &mut self.secondary_chronograph,
+        ]
+        .into_iter()
+        .filter_map(|n| n.as_mut())
+        .for_each(|n| n.snap_to(n.target_pos));
+        self.highlight_bounds = self.highlight_target;
+        self.highlight_transition = None;
+        self.highlight_alpha = 1.0;
+        self.zones
+            .retain(|(_, transition)| !transition.fading_out());
+        let now = self.now();
+        for (_, transition) in &mut self.zones {
+            *transition = BandTransition::settled(transition.to, now);
+        }
+        self.sampled_readout = self.readout_value;
+        self.update_alerts();
+    }
+
+    fn play_animation(&mut self, animation: Animation) {
+        self.animation = Some((animation, self.now()));
+    }
+
+    // Animated values bypass needle smoothing; the keyframes already describe the motion
+    fn update_animation(&mut self) {
+        let Some((ref animation, started)) = self.animation else {
+            return;
+        };
+        let duration = animation.duration();
+        let mut time = seconds_between(started, self.now());
+        let finished = time >= duration;
+        if finished && animation.looping && duration > 0.0 {
+            time %= duration;
+        }
+        let values: Vec<(AnimationTarget, f64)> = animation
+            .tracks
+            .iter()
+            .filter_map(|(target, keyframes)| Some((*target, Animation::sample(keyframes, time)?)))
+            .collect();
+        if finished && !animation.looping {
+            self.animation = None;
+        }
+
+        for (target, value) in values {
+            let needle = match target {
+                AnimationTarget::Primary => {
+                    self.set_primary_value(value);
+                    &mut self.needle1
+                }
+                AnimationTarget::Secondary => {
+                    self.set_secondary_value(value);
+                    &mut self.needle2
+                }
+                AnimationTarget::Chronograph => {
+                    self.stopwatch = None;
+                    self.set_chronograph_value(value);
+                    &mut self.chronograph
+                }
+                AnimationTarget::SecondaryChronograph => {
+                    self.set_secondary_chronograph_value(value);
+                    &mut self.secondary_chronograph
+                }
+                AnimationTarget::Readout => {
+                    self.set_readout_value(value);
+                    continue;
+                }
+            };
+            if let Some(needle) = needle {
+                needle.snap_to(needle.target_pos);
+            }
+        }
+    }
+
+    fn update(&mut self) {
+        self.frame_started = Instant::now();
+        if let Some((ref mut now, step)) = self.fixed_clock {
+            *now += step;
+        }
+        let now = self.now();
+        self.update_range_transition();
+        self.update_stopwatch();
+        self.update_animation();
+        if let Some(value) = self.chronograph.as_ref().map(|n| n.target_value) {
+            self.record_chronograph_peak(value);
+        }
+        let span = |(min, max): (f64, f64)| max - min;
+        let main_span = span((self.min_value, self.max_value));
+        let secondary_span = self.secondary_range.map_or(main_span, span);
+        [
+            (&mut self.needle1, main_span),
+            (&mut self.needle2, secondary_span),
+            (&mut self.chronograph, span(self.chronograph_range)),
+            (
+                &mut self.secondary_chronograph,
+                span(self.secondary_chronograph_range),
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(n, span)| Some((n.as_mut()?, span)))
+        .for_each(|(n, span)| n.update_position(span, now));
+        self.update_highlight_bounds();
+        self.update_zones();
+        self.sample_readout();
+        self.update_alerts();
+        self.update_power_on_sweep();
+        self.totalizer.flush();
+    }
+
+    fn needle(&self, id: NeedleId) -> Option<&Needle> {
+        match id {
+            NeedleId::Primary => self.needle1.as_ref(),
+            NeedleId::Secondary => self.needle2.as_ref(),
+            NeedleId::Chronograph => self.chronograph.as_ref(),
+            NeedleId::SecondaryChronograph => self.secondary_chronograph.as_ref(),
+        }
+    }
+
+    fn update_alerts(&mut self) {
+        let holding: Vec<bool> = self
+            .alerts
+            .iter()
+            .map(|(rule, _)| {
+                self.needle(rule.needle)
+                    .is_some_and(|needle| rule.condition.holds(needle.target_value))
+            })
+            .collect();
+        let now = self.now();
+        for ((_, since), holding) in self.alerts.iter_mut().zip(holding) {
+            *since = if holding {
+                Some(since.unwrap_or(now))
+            } else {
+                None
+            };
+        }
+    }
+
+    // Rules whose condition has held long enough, with how long they have been active
+    // Opacity a blinking or pulsing needle is drawn at this frame, 1.0 for the rest. Each
+    // cycle starts fully shown.
+    fn blink_opacity(&self, id: NeedleId, rate: f64) -> f64 {
+        let Some(&(blink, since)) = self.needle_blinks.get(&id) else {
+            return 1.0;
+        };
+        let phase = (seconds_between(since, self.now()) * rate).fract();
+        match blink {
+            NeedleBlink::Blink if phase < 0.5 => 1.0,
+            NeedleBlink::Blink => 0.0,
+            NeedleBlink::Pulse => 0.6 + 0.4 * (phase * std::f64::consts::TAU).cos(),
+        }
+    }
+
+    fn active_alerts(&self) -> impl Iterator<Item = (&AlertRule, f64)> {
+        let now = self.now();
+        self.alerts.iter().filter_map(move |(rule, since)| {
+            let active = seconds_between((*since)?, now) - rule.hold;
+            (active >= 0.0).then_some((rule, active))
+        })
+    }
+
+    // Overrides the displayed positions while leaving targets alone, so live values that
+    // arrive during the sweep are picked up as soon as it ends
+    fn update_power_on_sweep(&mut self) {
+        let Some((started, duration)) = self.power_on_sweep else {
+            return;
+        };
+        let t = seconds_between(started, self.now()) / duration;
+        if t.is_nan() || t >= 1.0 {
+            self.power_on_sweep = None;
+            return;
+        }
+        let pos = Easing::EaseInOut.apply(1.0 - (2.0 * t - 1.0).abs());
+        [
+            &mut self.needle1,
+            &mut self.needle2,
+            &mut self.chronograph,
+            &mut self.secondary_chronograph,
+        ]
+        .into_iter()
+        .filter_map(|n| n.as_mut())
+        .for_each(|n| n.pos = pos);
+    }
+
+    #[cfg(feature = "shared-memory")]
+    fn apply_shared_values(&mut self, values: [f64; 5]) {
+        let [primary, secondary, chronograph, secondary_chronograph, readout] = values;
+        let commands = [
+            (primary, InstrumentCommand::SetPrimaryNeedle(primary), None),
+            (
+                secondary,
+                InstrumentCommand::SetSecondaryNeedle(secondary),
+                Some(InstrumentCommand::ClearSecondaryNeedle),
+            ),
+            (
+                chronograph,
+                InstrumentCommand::SetChronograph(chronograph),
+                Some(InstrumentCommand::ClearChronograph),
+            ),
+            (
+                secondary_chronograph,
+                InstrumentCommand::SetSecondaryChronograph(secondary_chronograph),
+                Some(InstrumentCommand::ClearSecondaryChronograph),
+            ),
+            (
+                readout,
+                InstrumentCommand::SetReadout(readout),
+                Some(InstrumentCommand::ClearReadout),
+            ),
+        ];
+        for (value, set, clear) in commands {
+            if !value.is_nan() {
+                self.apply_command(set);
+            } else if let Some(clear) = clear {
+                self.apply_command(clear);
+            }
+        }
+    }
+
+    fn calibrated(&self, key: &str, value: f64) -> f64 {
+        self.calibrations
+            .get(key)
+            .map_or(value, |calibration| calibration.apply(value))
+    }
+
+    // Holds the current value if the change is inside the dead band
+    fn held(&self, key: &str, value: f64) -> f64 {
+        let current = match key {
+            "primary" => self.needle1.as_ref().map(|needle| needle.target_value),
+            "secondary" => self.needle2.as_ref().map(|needle| needle.target_value),
+            "readout" => self.readout_value,
+            _ => None,
+        };
+        match current {
+            Some(current) if (value - current).abs() < self.dead_band => current,
+            _ => value,
+        }
+    }
+
+    // Calibrates incoming values, computes the derived channels and value script, then
+    // applies the dead band
+    fn condition(&mut self, command: InstrumentCommand) -> InstrumentCommand {
+        let command = self.map_values(command, Self::calibrated);
+        let command = self.derived(command);
+        self.map_values(command, Self::held)
+    }
+
+    // Passes each value a command carries through `f` along with its `SetMany` key
+    fn map_values(
+        &self,
+        command: InstrumentCommand,
+        f: impl Fn(&Self, &str, f64) -> f64,
+    ) -> InstrumentCommand {
+        use InstrumentCommand as C;
+        let c = |id: NeedleId, value| f(self, id.name(), value);
+        let (primary, secondary) = (NeedleId::Primary, NeedleId::Secondary);
+        let (chrono1, chrono2) = (NeedleId::Chronograph, NeedleId::SecondaryChronograph);
+        match command {
+            C::SetPrimaryNeedle(value) => C::SetPrimaryNeedle(c(primary, value)),
+            C::SetSecondaryNeedle(value) => C::SetSecondaryNeedle(c(secondary, value)),
+            C::SetChronograph(value) => C::SetChronograph(c(chrono1, value)),
+            C::SetSecondaryChronograph(value) => C::SetSecondaryChronograph(c(chrono2, value)),
+            C::SetReadout(value) => C::SetReadout(f(self, "readout", value)),
+            C::SetBothNeedles(a, b) => C::SetBothNeedles(c(primary, a), c(secondary, b)),
+            C::SetAllNeedles(a, b, d, e) => {
+                C::SetAllNeedles(c(primary, a), c(secondary, b), c(chrono1, d), c(chrono2, e))
+            }
+            C::SetBothChronographs(a, b) => C::SetBothChronographs(c(chrono1, a), c(chrono2, b)),
+            C::SetNeedle { id, value } => C::SetNeedle {
+                id,
+                value: c(id, value),
+            },
+            C::SetMany(values) => C::SetMany(
+                values
+                    .into_iter()
+                    .map(|(key, value)| {
+                        let value = f(self, &key, value);
+                        (key, value)
+                    })
+                    .collect(),
+            ),
+            command => command,
+        }
+    }
+
+    // Evaluates the derived channels, then the value script, over the latest inputs.
+    // Displayed keys the command doesn't carry but they assign, e.g. a readout computed
+    // from two inputs, turn it into a `SetMany`.
+    fn derived(&mut self, command: InstrumentCommand) -> InstrumentCommand {
+        #[cfg(feature = "scripting")]
+        let script = self.value_script.clone();
+        #[cfg(not(feature = "scripting"))]
+        let script: Option<()> = None;
+        if self.derived_channels.is_empty() && script.is_none() {
+            return command;
+        }
+        let incoming = std::cell::RefCell::new(HashMap::new());
+        let command = self.map_values(command, |_, key, value| {
+            incoming.borrow_mut().insert(key.to_string(), value);
+            value
+        });
+        let incoming = incoming.into_inner();
+        if incoming.is_empty() {
+            return command;
+        }
+        self.inputs.extend(incoming.clone());
+        let mut outputs = self.inputs.clone();
+        for channel in &self.derived_channels {
+            let value = channel.expression.evaluate(&outputs);
+            if let Some(value) = value.filter(|value| value.is_finite()) {
+                outputs.insert(channel.key.clone(), value);
+            }
+        }
+        #[cfg(feature = "scripting")]
+        if let Some(scripted) = script.and_then(|script| script.run(&outputs)) {
+            outputs = scripted;
+        }
+        let derived: HashMap<String, f64> = outputs
+            .iter()
+            .filter(|(key, value)| {
+                let displayed = NeedleId::from_name(key).is_some() || key.as_str() == "readout";
+                displayed && !incoming.contains_key(*key) && self.inputs.get(*key) != Some(value)
+            })
+            .map(|(key, value)| (key.clone(), *value))
+            .collect();
+        let command = self.map_values(command, |_, key, value| {
+            outputs.get(key).copied().unwrap_or(value)
+        });
+        if derived.is_empty() {
+            return command;
+        }
+        let mut values = derived;
+        for key in incoming.keys() {
+            values.insert(
+                key.clone(),
+                outputs.get(key).copied().unwrap_or(incoming[key]),
+            );
+        }
+        InstrumentCommand::SetMany(values)
+    }
+
+    fn apply_command(&mut self, command: InstrumentCommand) {
+        match self.condition(command) {
+            InstrumentCommand::SetPrimaryNeedle(value) => {
+                self.set_primary_value(value);
+            }
+            InstrumentCommand::SetSecondaryNeedle(value) => {
+                self.set_secondary_value(value);
+            }
+            InstrumentCommand::SetReadout(value) => {
+                self.set_readout_value(value);
+            }
+            InstrumentCommand::SetHighlightBounds(lower, upper) => {
+                self.set_highlight_bounds(lower, upper);
+            }
+            InstrumentCommand::ClearHighlightBounds => {
+                self.clear_highlight_bounds();
+            }
+            InstrumentCommand::SetZones(zones) => {
+                self.set_zones(zones);
+            }
+            InstrumentCommand::SetHighlightVisible(visible) => {
+                self.highlight_visible = visible;
+            }
+            InstrumentCommand::SetNeedleBlink(id, Some(blink)) => {
+                let now = self.now();
+                self.needle_blinks.insert(id, (blink, now));
+            }
+            InstrumentCommand::SetNeedleBlink(id, None) => {
+                self.needle_blinks.remove(&id);
+            }
+            InstrumentCommand::SetTargetBug(value) => {
+                self.target_bug = value.filter(|value| value.is_finite());
+            }
+            InstrumentCommand::SetFailure(message) => {
+                self.failure = message;
+            }
+            InstrumentCommand::SetRange(min_value, max_value) => {
+                self.set_range(min_value, max_value);
+            }
+            InstrumentCommand::SetBothNeedles(primary, secondary) => {
+                self.set_primary_value(primary);
+                self.set_secondary_value(secondary);
+            }
+            InstrumentCommand::SetChronograph(value) => {
+                self.stopwatch = None;
+                self.set_chronograph_value(value);
+            }
+            InstrumentCommand::StartChronograph => {
+                self.start_stopwatch();
+            }
+            InstrumentCommand::StopChronograph => {
+                self.stop_stopwatch();
+            }
+            InstrumentCommand::ResetChronograph => {
+                self.reset_stopwatch();
+            }
+            InstrumentCommand::SetBrightness(brightness) => {
+                self.brightness = brightness.clamp(0.0, 1.0);
+            }
+            InstrumentCommand::SetLayerVisible(layer, visible) => {
+                self.hidden_layers.retain(|&hidden| hidden != layer);
+                if !visible {
+                    self.hidden_layers.push(layer);
+                }
+            }
+            InstrumentCommand::SetLayerOrder(order) => {
+                self.layer_order = order;
+            }
+            InstrumentCommand::HoldReadout(hold) => {
+                self.hold_readout(hold);
+            }
+            InstrumentCommand::PlayAnimation(animation) => {
+                self.play_animation(animation);
+            }
+            InstrumentCommand::StopAnimation => {
+                self.animation = None;
+            }
+            InstrumentCommand::SetSecondaryChronograph(value) => {
+                self.set_secondary_chronograph_value(value);
+            }
+            InstrumentCommand::SetAllNeedles(
+                primary,
+                secondary,
+                chronograph,
+                secondary_chronograph,
+            ) => {
+                self.set_primary_value(primary);
+                self.set_secondary_value(secondary);
+                self.set_chronograph_value(chronograph);
+                self.set_secondary_chronograph_value(secondary_chronograph);
+            }
+            InstrumentCommand::SetBothChronographs(chronograph, secondary_chronograph) => {
+                self.set_chronograph_value(chronograph);
+                self.set_secondary_chronograph_value(secondary_chronograph);
+            }
+            InstrumentCommand::SetMany(values) => {
+                for (key, value) in values {
+                    match NeedleId::from_name(&key) {
+                        Some(id) => self.set_needle_value(id, value),
+                        None if key == "readout" => self.set_readout_value(value),
+                        None => {}
+                    }
+                }
+            }
+            InstrumentCommand::SetNeedle { id, value } => {
+                self.set_needle_value(id, value);
+            }
+            InstrumentCommand::ClearSecondaryNeedle => {
+                self.needle2 = None;
+            }
+            InstrumentCommand::ClearChronograph => {
+                self.stopwatch = None;
+                self.chronograph = None;
+            }
+            InstrumentCommand::ClearSecondaryChronograph => {
+                self.secondary_chronograph = None;
+            }
+            InstrumentCommand::ClearReadout => {
+                self.held_readout = None;
+                self.readout_value = None;
+                self.sampled_readout = None;
+            }
+            InstrumentCommand::Sequence(id) => {
+                self.sequence = Some(id);
+            }
+            // A non-finite amount would stick in the total for good
+            InstrumentCommand::AddToTotal(amount) if amount.is_finite() => {
+                self.totalizer.set(self.totalizer.total + amount);
+            }
+            InstrumentCommand::AddToTotal(_) => {}
+            InstrumentCommand::ResetTotal => {
+                self.totalizer.set(0.0);
+            }
+            InstrumentCommand::ResetTellTales => {
+                self.tell_tales = None;
+                self.chronograph_peak = None;
+            }
+            InstrumentCommand::SetCalibration(key, calibration) => {
+                if calibration == Calibration::default() {
+                    self.calibrations.remove(&key);
+                } else {
+                    self.calibrations.insert(key, calibration);
+                }
+            }
+            InstrumentCommand::ExportHistory(format, sender) => {
+                // Nobody is listening any more; nothing to do
+                let _ = sender.send(format_history(self.history.iter(), format));
+            }
+        }
+    }
+
+    // Called once a frame has been presented; reports its timing and any newly applied
+    // sequence id
+    fn acknowledge_presented(&mut self, config: &InstrumentConfig) {
+        if let Some(ref hook) = config.frame_timing_hook {
+            (hook.0)(&FrameTiming {
+                frame: self.frames_presented,
+                sequence: self.sequence,
+                started: self.frame_started,
+                presented: Instant::now(),
+                presented_at: std::time::SystemTime::now(),
+            });
+        }
+        self.frames_presented += 1;
+        if self.sequence == self.acknowledged_sequence {
+            return;
+        }
+        self.acknowledged_sequence = self.sequence;
+        if let (Some(id), Some(hook)) = (self.sequence, &config.sequence_hook) {
+            (hook.0)(id);
+        }
+    }
+
+    // Judged on the raw commanded values; needle positions are clamped to the scale
+    fn is_out_of_range(&self) -> bool {
+        if self.wrap_around {
+            return false;
+        }
+        let main = (self.min_value, self.max_value);
+        [
+            (&self.needle1, main),
+            (&self.needle2, self.secondary_range.unwrap_or(main)),
+        ]
+        .into_iter()
+        .filter_map(|(needle, range)| Some((needle.as_ref()?, range)))
+        .any(|(needle, (min, max))| needle.target_value < min || needle.target_value > max)
+    }
+
+    fn set_highlight_override(&mut self, lower: f64, upper: f64) {
+        let bounds = (lower.min(upper), lower.max(upper));
+        self.highlight_bounds = Some(bounds);
+        self.highlight_target = Some(bounds);
+        self.highlight_transition = None;
+        self.highlight_alpha = 1.0;
+    }
+}
+
+struct Dial {
+    cx: i32,
+    cy: i32,
+    r: i32,
+    thickness: i32,
+    arc_span: f64,
+    start_angle: f64,
+    scale: f64, // Applied to tick, label and needle dimensions when the layout shrinks a dial
+    tick_placement: ScalePlacement,
+    scale_break: Option<ScaleBreak>,               // Main dial only
+    opacity: (f64, f64),                           // Of the ring and of the ticks; main dial only
+    line_style: (LineStyle, LineStyle, LineStyle), // Ring, major and minor ticks; main dial only
+}
+
+impl Dial {
+    fn new(width: usize, height: usize, config: &InstrumentConfig) -> Self {
+        let cx = width as i32 / 2 + config.pivot_offset.0;
+        let cy = height as i32 / 2 + config.pivot_offset.1;
+        let r = (width.min(height) as i32) / 2
+            - config.dial_margin
+            - outside_reach(
+                config.tick_placement,
+                config.major_tick_length,
+                config.dial_ticks_to_numbers_distance,
+            );
+        Self {
+            cx,
+            cy,
+            r,
+            thickness: config.dial_thickness,
+            arc_span: config.dial_arc_span,
+            start_angle: config.dial_start_angle,
+            scale: 1.0,
+            tick_placement: config.tick_placement,
+            scale_break: config.scale_break,
+            opacity: (config.dial_opacity, config.tick_opacity),
+            line_style: (
+                config.dial_line_style,
+                config.major_tick_style,
+                config.minor_tick_style,
+            ),
+        }
+    }
+
+    fn new_chronograph(width: usize, height: usize, config: &InstrumentConfig) -> Self {
+        // Create a smaller dial for the chronograph
+        let r = ((width.min(height) as f64) / config.chronograph_dial_size) as i32
+            - config.chronograph_dial_margin
+            - outside_reach(
+                config.chronograph_tick_placement,
+                config.chronograph_tick_length,
+                config.chronograph_dial_ticks_to_numbers_distance,
+            );
+        let cx = width as i32 / 2; // Center horizontally
+        let cy = r + config.chronograph_dial_margin + config.chronograph_dial_shift; // Position in top middle
+        Self {
+            cx,
+            cy,
+            r,
+            thickness: config.chronograph_dial_thickness,
+            arc_span: config.chronograph_arc_span,
+            start_angle: config.chronograph_start_angle,
+            scale: 1.0,
+            tick_placement: config.chronograph_tick_placement,
+            scale_break: None,
+            opacity: (1.0, 1.0),
+            line_style: Default::default(),
+        }
+    }
+
+    fn new_secondary_chronograph(width: usize, height: usize, config: &InstrumentConfig) -> Self {
+        // Create a smaller dial for the secondary chronograph
+        let r = ((width.min(height) as f64) / config.secondary_chronograph_dial_size) as i32
+            - config.secondary_chronograph_dial_margin
+            - outside_reach(
+                config.secondary_chronograph_tick_placement,
+                config.secondary_chronograph_tick_length,
+                config.secondary_chronograph_dial_ticks_to_numbers_distance,
+            );
+        let cx = width as i32 / 2; // Center horizontally
+        let cy = (height as i32 / 2) + config.secondary_chronograph_dial_shift; // Position below center
+        Self {
+            cx,
+            cy,
+            r,
+            thickness: config.secondary_chronograph_dial_thickness,
+            arc_span: config.secondary_chronograph_arc_span,
+            start_angle: config.secondary_chronograph_start_angle,
+            scale: 1.0,
+            tick_placement: config.secondary_chronograph_tick_placement,
+            scale_break: None,
+            opacity: (1.0, 1.0),
+            line_style: Default::default(),
+        }
+    }
+
+    // Ticks are drawn inward from their `r`, so an outside tick hangs from beyond the arc
+    fn tick_radius(&self, length: i32) -> i32 {
+        match self.tick_placement {
+            ScalePlacement::Inside => self.r,
+            ScalePlacement::Outside => self.r + length,
+        }
+    }
+
+    fn angle_of(&self, value: f64, range: (f64, f64)) -> f64 {
+        self.start_angle + self.arc_span * scale_fraction(value, range, self.scale_break.as_ref())
+    }
+
+    // Whether the scale breaks somewhere between two values
+    fn breaks_between(&self, a: f64, b: f64) -> bool {
+        self.scale_break
+            .is_some_and(|scale_break| a.min(b) < scale_break.to && a.max(b) > scale_break.from)
+    }
+
+    // Centre radius of labels `inset` from the arc on the ticks' side
+    fn label_radius(&self, inset: f64) -> f64 {
+        match self.tick_placement {
+            ScalePlacement::Inside => self.r as f64 - inset,
+            ScalePlacement::Outside => self.r as f64 + inset,
+        }
+    }
+}
+
+// How far outside ticks and their labels extend past the arc, which the radius gives up
+// so the dial keeps its footprint
+fn outside_reach(placement: ScalePlacement, tick_length: i32, label_distance: f64) -> i32 {
+    match placement {
+        ScalePlacement::Inside => 0,
+        ScalePlacement::Outside => tick_length + label_distance.round() as i32,
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct NeedleDynamics {
+    motion: NeedleMotion,
+    quartz_step_duration: f64,
+    max_rate: Option<f64>, // Scale units per second
+    attack: f64,           // Smooth-motion lerp factors for rising and falling values
+    release: f64,
+    wraps: bool, // Positions are taken modulo 1, moving the short way round
+}
+
+struct Needle {
+    pos: f64, // Normalized [0,1]
+    target_pos: f64,
+    target_value: f64, // Raw commanded value, kept so range changes can re-normalize
+    dynamics: NeedleDynamics,
+    step: Option<(f64, Instant)>, // Quartz step origin and start time
+    last_update: Option<Instant>,
+}
+
+impl Needle {
+    fn new(dynamics: NeedleDynamics) -> Self {
+        Self {
+            pos: 0.5,
+            target_pos: 0.5,
+            target_value: 0.0,
+            dynamics,
+            step: None,
+            last_update: None,
+        }
+    }
+
+    fn set_target_pos(&mut self, target: f64, now: Instant) {
+        let target = self.nearest_target(target);
+        if self.dynamics.motion == NeedleMotion::Quartz && target != self.target_pos {
+            self.step = Some((self.pos, now));
+        }
+        self.target_pos = target;
+    }
+
+    // Follows a moving scale without restarting a Quartz step already under way, so the
+    // needle still settles while the range is transitioning
+    fn rescale_target_pos(&mut self, target: f64, now: Instant) {
+        if self.step.is_some() {
+            self.target_pos = self.nearest_target(target);
+        } else {
+            self.set_target_pos(target, now);
+        }
+    }
+
+    fn nearest_target(&self, target: f64) -> f64 {
+        if self.dynamics.wraps {
+            // The nearest turn of the target to where the needle is now
+            self.pos + (target - self.pos + 0.5).rem_euclid(1.0) - 0.5
+        } else {
+            target.clamp(0.0, 1.0)
+        }
+    }
+
+    fn snap_to(&mut self, pos: f64) {
+        self.pos = if self.dynamics.wraps {
+            pos.rem_euclid(1.0)
+        } else {
+            pos.clamp(0.0, 1.0)
+        };
+        self.target_pos = self.pos;
+        self.step = None;
+    }
+
+    // `span` is the width of the needle's scale, used to convert the slew-rate limit
+    fn update_position(&mut self, span: f64, now: Instant) {
+        let dt = self
+            .last_update
+            .map_or(0.0, |last| seconds_between(last, now));
+        self.last_update = Some(now);
+
+        let previous = self.pos;
+        self.advance(now);
+        if let Some(max_rate) = self.dynamics.max_rate {
+            if span.abs() > 0.0 {
+                let max_step = max_rate * dt / span.abs();
+                self.pos = previous + (self.pos - previous).clamp(-max_step, max_step);
+            }
+        }
+        // Bring a wrapping needle back to the first turn, keeping where it is heading
+        let turns = self.pos.floor();
+        if self.dynamics.wraps && turns != 0.0 {
+            self.pos -= turns;
+            self.target_pos -= turns;
+            if let Some((ref mut from, _)) = self.step {
+                *from -= turns;
+            }
+        }
+    }
+
+    fn bounded(&self, pos: f64) -> f64 {
+        if self.dynamics.wraps {
+            pos
+        } else {
+            pos.clamp(0.0, 1.0)
+        }
+    }
+
+    fn advance(&mut self, now: Instant) {
+        match self.dynamics.motion {
+            NeedleMotion::Smooth => {
+                let factor = if self.target_pos > self.pos {
+                    self.dynamics.attack
+                } else {
+                    self.dynamics.release
+                };
+                self.pos = self.bounded(lerp(self.pos, self.target_pos, factor));
+            }
+            NeedleMotion::Quartz => {
+                let Some((from, started)) = self.step else {
+                    self.pos = self.target_pos;
+                    return;
+                };
+                let t = seconds_between(started, now) / self.dynamics.quartz_step_duration;
+                if t >= 1.0 {
+                    self.pos = self.target_pos;
+                    self.step = None;
+                } else {
+                    // Damped cosine: crosses the target at 20% of the step, overshoots ~9%, settles
+                    let offset = (from - self.target_pos)
+                        * (-6.0 * t).exp()
+                        * (2.5 * std::f64::consts::PI * t).cos();
+                    self.pos = self.bounded(self.target_pos + offset);
                 }
-                DrawCommand::HighlightBand {
-                    cx,
-                    cy,
-                    r,
-                    start_angle,
-                    end_angle,
-                    inner_radius,
-                    outer_radius,
-                } => {
-                    render_highlight_band_immediate(
-                        canvas,
-                        *cx,
-                        *cy,
-                        *r,
-                        *start_angle,
-                        *end_angle,
-                        *inner_radius,
-                        *outer_radius,
-                        config,
+            }
+        }
+    }
+}
+
+// ============================================================================
+// HELPER FUNCTIONS
+// ============================================================================
+
+fn seconds_between(earlier: Instant, later: Instant) -> f64 {
+    later.saturating_duration_since(earlier).as_secs_f64()
+}
+
+// Writes a sibling temp file and renames it over `path`, so losing power mid-write leaves
+// the previous contents instead of a truncated file
+fn write_atomically(path: &std::path::Path, contents: &str) -> std::io::Result<()> {
+    use std::io::Write;
+    let mut temp = path.as_os_str().to_owned();
+    temp.push(".tmp");
+    let mut file = std::fs::File::create(&temp)?;
+    file.write_all(contents.as_bytes())?;
+    file.sync_all()?;
+    std::fs::rename(&temp, path)
+}
+
+// ============================================================================
+// RENDERING AND DRAWING FUNCTIONS
+// ============================================================================
+
+// Renders into an RGBA frame, going through a scratch buffer when the gauge is rotated
+fn render_frame(
+    frame: &mut [u8],
+    width: usize,
+    height: usize,
+    state: &AppState,
+    config: &InstrumentConfig,
+    elapsed: f64,
+    costs: Option<&mut CommandCosts>,
+) {
+    trace_span!("render_frame", width, height);
+    let config = &*config.scaled();
+    if config.rotation == Rotation::None {
+        let mut canvas = Canvas::with_format(frame, width, height, config.pixel_format);
+        render_instrument(&mut canvas, state, config, costs);
+    } else {
+        render_rotated(frame, width, height, state, config, costs);
+    }
+    apply_burn_in_protection(frame, width, height, elapsed, config);
+    apply_brightness(frame, state.brightness, config.pixel_format);
+    if config.timestamp_marker {
+        draw_timestamp_marker(frame, width, height, state.frames_presented, config);
+    }
+    // Anything that rewrites the frame after the scene leaves it out of step with the
+    // background cache, so the next frame starts again from a full copy. Rotation draws
+    // into a fresh scratch buffer, which may land at the address of the last one.
+    let rewritten = config.rotation != Rotation::None
+        || (config.burn_in_shift > 0 && config.burn_in_shift_period > 0.0)
+        || config.burn_in_invert_period.is_some()
+        || state.brightness < 1.0
+        || config.timestamp_marker
+        || config.frame_hook.is_some();
+    if rewritten {
+        state.background_cache.borrow_mut().frame = None;
+    }
+}
+
+// What a dashboard cell shows once its gauge has panicked: the dial can't be trusted any
+// more, so a grey cell with a message in `SetFailure`'s font and colour
+fn render_crashed_cell(frame: &mut [u8], width: usize, height: usize, config: &InstrumentConfig) {
+    let config = &*config.scaled();
+    let mut canvas = Canvas::new(frame, width, height);
+    canvas.clear((0x80, 0x80, 0x80));
+    let (cx, cy) = ((width / 2) as i32, (height / 2) as i32);
+    let band = (config.alert_indicator_font_size * 2.0) as usize;
+    canvas.fill_rect(0, cy - band as i32 / 2, width, band, (0xff, 0xff, 0xff));
+    if let Ok(shaper) = try_text_shaper(config) {
+        draw_text(
+            &mut canvas,
+            cx,
+            cy,
+            "GAUGE FAILED",
+            shaper,
+            config.alert_indicator_font_size,
+            (0xff, 0x00, 0x00),
+        );
+    }
+}
+
+// The frame number as described on `FrameTiming::frame`, on top of everything else so
+// burn-in shifts and dimming never blur it
+fn draw_timestamp_marker(
+    frame: &mut [u8],
+    width: usize,
+    height: usize,
+    number: u64,
+    config: &InstrumentConfig,
+) {
+    let size = config.timestamp_marker_size.max(1) as usize;
+    let mut canvas = Canvas::with_format(frame, width, height, config.pixel_format);
+    let bits = (0..16).rev().map(|bit| number >> bit & 1 == 1);
+    for (cell, white) in [true, false].into_iter().chain(bits).enumerate() {
+        let shade = if white { 0xff } else { 0x00 };
+        canvas.fill_rect((cell * size) as i32, 0, size, size, (shade, shade, shade));
+    }
+}
+
+fn render_rotated(
+    frame: &mut [u8],
+    width: usize,
+    height: usize,
+    state: &AppState,
+    config: &InstrumentConfig,
+    costs: Option<&mut CommandCosts>,
+) {
+    trace_span!("rotate", rotation = ?config.rotation);
+    let (src_width, src_height) = if config.rotation.swaps_axes() {
+        (height, width)
+    } else {
+        (width, height)
+    };
+    let size = config.pixel_format.bytes_per_pixel();
+    let mut scratch = vec![0u8; src_width * src_height * size];
+    let mut canvas = Canvas::with_format(&mut scratch, src_width, src_height, config.pixel_format);
+    render_instrument(&mut canvas, state, config, costs);
+
+    for y in 0..src_height {
+        for x in 0..src_width {
+            let (dx, dy) = match config.rotation {
+                Rotation::None => (x, y),
+                Rotation::Cw90 => (src_height - 1 - y, x),
+                Rotation::Cw180 => (src_width - 1 - x, src_height - 1 - y),
+                Rotation::Cw270 => (y, src_width - 1 - x),
+            };
+            let src = (y * src_width + x) * size;
+            let dst = (dy * width + dx) * size;
+            frame[dst..dst + size].copy_from_slice(&scratch[src..src + size]);
+        }
+    }
+}
+
+// Orbits the finished frame a few pixels around its origin (replicating edge pixels into
+// the exposed border) and periodically inverts it, so static elements never sit still
+fn apply_burn_in_protection(
+    frame: &mut [u8],
+    width: usize,
+    height: usize,
+    elapsed: f64,
+    config: &InstrumentConfig,
+) {
+    trace_span!("burn_in_protection");
+    let (format, size) = (config.pixel_format, config.pixel_format.bytes_per_pixel());
+    if config.burn_in_shift > 0 && config.burn_in_shift_period > 0.0 {
+        let phase = elapsed / config.burn_in_shift_period * std::f64::consts::TAU;
+        let shift = config.burn_in_shift as f64;
+        let (dx, dy) = (
+            (phase.cos() * shift).round() as i32,
+            (phase.sin() * shift).round() as i32,
+        );
+        if dx != 0 || dy != 0 {
+            let source = frame.to_vec();
+            for y in 0..height as i32 {
+                let sy = (y - dy).clamp(0, height as i32 - 1) as usize;
+                for x in 0..width as i32 {
+                    let sx = (x - dx).clamp(0, width as i32 - 1) as usize;
+                    let (src, dst) = (
+                        (sy * width + sx) * size,
+                        (y as usize * width + x as usize) * size,
                     );
+                    frame[dst..dst + size].copy_from_slice(&source[src..src + size]);
                 }
-                DrawCommand::Tick {
-                    cx,
-                    cy,
-                    r,
-                    angle,
-                    length,
-                    thickness,
-                    color,
-                } => {
-                    let outer_x = *cx as f64 + angle.cos() * (*r as f64 - 1.0);
-                    let outer_y = *cy as f64 + angle.sin() * (*r as f64 - 1.0);
-                    let inner_x = *cx as f64 + angle.cos() * (*r as f64 - *length as f64);
-                    let inner_y = *cy as f64 + angle.sin() * (*r as f64 - *length as f64);
-                    draw_thick_line_aa(
-                        canvas.frame,
-                        canvas.width,
-                        inner_x.round() as i32,
-                        inner_y.round() as i32,
-                        outer_x.round() as i32,
-                        outer_y.round() as i32,
-                        *thickness,
-                        color.0,
-                        color.1,
-                        color.2,
-                    );
+            }
+        }
+    }
+
+    if let Some(period) = config.burn_in_invert_period {
+        if period > 0.0 && (elapsed / period) as u64 % 2 == 1 {
+            for pixel in frame.chunks_exact_mut(size) {
+                let (r, g, b) = format.load(pixel);
+                format.store(pixel, (0xff - r, 0xff - g, 0xff - b));
+            }
+        }
+    }
+}
+
+fn apply_brightness(frame: &mut [u8], brightness: f32, format: PixelFormat) {
+    if brightness >= 1.0 {
+        return;
+    }
+    trace_span!("brightness");
+    let dim = |channel: u8| (channel as f32 * brightness).round() as u8;
+    for pixel in frame.chunks_exact_mut(format.bytes_per_pixel()) {
+        let (r, g, b) = format.load(pixel);
+        format.store(pixel, (dim(r), dim(g), dim(b)));
+    }
+}
+
+fn check_frame_size(frame: &[u8], width: usize, height: usize, config: &InstrumentConfig) {
+    let format = config.pixel_format;
+    assert_eq!(
+        frame.len(),
+        width * height * format.bytes_per_pixel(),
+        "frame must be {format:?}"
+    );
+}
+
+fn render_instrument(
+    canvas: &mut Canvas,
+    state: &AppState,
+    config: &InstrumentConfig,
+    costs: Option<&mut CommandCosts>,
+) {
+    let mut scene = state.scene_cache.borrow_mut();
+    update_scene(&mut scene, canvas.width, canvas.height, state, config);
+    match costs {
+        Some(costs) => {
+            state.background_cache.borrow_mut().frame = None;
+            scene.render_profiled(canvas, config, costs);
+        }
+        None if config.cached_background => render_over_background(canvas, &scene, state, config),
+        None => scene.render(canvas, config),
+    }
+}
+
+// Draws the dynamic layers of `scene` over the cached static ones. While `canvas` still
+// holds the previous frame, only the regions either frame's dynamic commands cover are
+// restored from the cache; otherwise the whole cache is copied in.
+fn render_over_background(
+    canvas: &mut Canvas,
+    scene: &Scene,
+    state: &AppState,
+    config: &InstrumentConfig,
+) {
+    let mut cache = state.background_cache.borrow_mut();
+    let split = scene.static_layer_count(config);
+    let key = {
+        use std::hash::{Hash, Hasher};
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        (canvas.width, canvas.height, split).hash(&mut hasher);
+        scene.layers_fingerprint(split).hash(&mut hasher);
+        hasher.finish()
+    };
+    if cache.key != key || cache.pixels.len() != canvas.frame.len() {
+        trace_span!("cache_background");
+        let mut pixels = vec![0u8; canvas.frame.len()];
+        let mut background =
+            Canvas::with_format(&mut pixels, canvas.width, canvas.height, canvas.format);
+        for command in scene.layer_commands(0..split) {
+            background.draw(command, config);
+        }
+        *cache = BackgroundCache {
+            key,
+            pixels,
+            frame: None,
+            damage: Vec::new(),
+        };
+    }
+
+    trace_span!("rasterize");
+    let dynamic: Vec<&DrawCommand> = scene.layer_commands(split..scene.layers.len()).collect();
+    let damage: Vec<Rect> = dynamic
+        .iter()
+        .map(|command| command_bounds(command, canvas.width, canvas.height, config))
+        .collect();
+    let frame = (canvas.frame.as_ptr() as usize, canvas.frame.len());
+    if cache.frame == Some(frame) {
+        for rect in cache.damage.iter().chain(&damage) {
+            canvas.restore(&cache.pixels, rect);
+        }
+    } else {
+        canvas.frame.copy_from_slice(&cache.pixels);
+    }
+    for command in dynamic {
+        trace_span!("draw", kind = command.kind());
+        canvas.draw(command, config);
+    }
+    cache.frame = Some(frame);
+    cache.damage = damage;
+}
+
+// A box, clipped to the canvas, holding every pixel `command` can touch
+fn command_bounds(
+    command: &DrawCommand,
+    width: usize,
+    height: usize,
+    config: &InstrumentConfig,
+) -> Rect {
+    // Room for anti-aliased fringes and rounding
+    const MARGIN: i32 = 2;
+    let around_points = |points: &mut dyn Iterator<Item = (f64, f64)>, reach: f64| {
+        let (left, top, right, bottom) = points.fold(
+            (f64::MAX, f64::MAX, f64::MIN, f64::MIN),
+            |(l, t, r, b), (x, y)| (l.min(x), t.min(y), r.max(x), b.max(y)),
+        );
+        Rect {
+            left: (left - reach).floor() as i32,
+            top: (top - reach).floor() as i32,
+            right: (right + reach).ceil() as i32,
+            bottom: (bottom + reach).ceil() as i32,
+        }
+    };
+    let rect = match command {
+        DrawCommand::Clear(_) => Rect {
+            left: 0,
+            top: 0,
+            right: width as i32,
+            bottom: height as i32,
+        },
+        DrawCommand::FillRect {
+            x,
+            y,
+            width,
+            height,
+            ..
+        }
+        | DrawCommand::RoundedRect {
+            x,
+            y,
+            width,
+            height,
+            ..
+        } => {
+            let stroke = match command {
+                DrawCommand::RoundedRect {
+                    stroke: Some(stroke),
+                    ..
+                } => (*stroke / 2.0).ceil() as i32,
+                _ => 0,
+            };
+            Rect {
+                left: x - stroke,
+                top: y - stroke,
+                right: x + *width as i32 + stroke,
+                bottom: y + *height as i32 + stroke,
+            }
+        }
+        DrawCommand::Arc { cx, cy, r, .. }
+        | DrawCommand::HighlightBand { cx, cy, r, .. }
+        | DrawCommand::Circle {
+            cx, cy, radius: r, ..
+        } => Rect::around(*cx, *cy, *r, *r),
+        DrawCommand::Tick {
+            cx,
+            cy,
+            r,
+            angle,
+            length,
+            thickness,
+            ..
+        } => {
+            let (cos, sin) = (angle.cos(), angle.sin());
+            let ends = [*r, r - length].map(|radius| {
+                (
+                    *cx as f64 + cos * radius as f64,
+                    *cy as f64 + sin * radius as f64,
+                )
+            });
+            around_points(&mut ends.into_iter(), *thickness as f64)
+        }
+        DrawCommand::Text {
+            x,
+            y,
+            text,
+            font_size,
+            ..
+        } => {
+            let half_width = calculate_text_width(text, text_shaper(config), *font_size) / 2;
+            let font_size = font_size.ceil() as i32;
+            Rect::around(*x, *y, half_width + font_size / 2, font_size)
+        }
+        DrawCommand::Image {
+            x,
+            y,
+            width,
+            height,
+            ..
+        } => Rect::around(*x, *y, *width as i32 / 2 + 1, *height as i32 / 2 + 1),
+        DrawCommand::CurvedText {
+            cx,
+            cy,
+            radius,
+            font_size,
+            ..
+        } => {
+            let reach = (radius.abs() + *font_size as f64 * 1.5).ceil() as i32;
+            Rect::around(*cx, *cy, reach, reach)
+        }
+        DrawCommand::NeedleLine {
+            x0,
+            y0,
+            x1,
+            y1,
+            thickness,
+            tip,
+            ..
+        } => {
+            let thickness = tip.map_or(*thickness, |tip| thickness.max(tip.thickness));
+            let ends = [(*x0 as f64, *y0 as f64), (*x1 as f64, *y1 as f64)];
+            around_points(&mut ends.into_iter(), thickness as f64)
+        }
+        DrawCommand::Polygon { points, .. } => around_points(&mut points.iter().copied(), 0.5),
+        DrawCommand::Translucent { commands, .. } => commands
+            .iter()
+            .map(|command| command_bounds(command, width, height, config))
+            .reduce(|a, b| Rect {
+                left: a.left.min(b.left),
+                top: a.top.min(b.top),
+                right: a.right.max(b.right),
+                bottom: a.bottom.max(b.bottom),
+            })
+            .unwrap_or(Rect {
+                left: 0,
+                top: 0,
+                right: 0,
+                bottom: 0,
+            }),
+    };
+    Rect {
+        left: (rect.left - MARGIN).clamp(0, width as i32),
+        top: (rect.top - MARGIN).clamp(0, height as i32),
+        right: (rect.right + MARGIN + 1).clamp(0, width as i32),
+        bottom: (rect.bottom + MARGIN + 1).clamp(0, height as i32),
+    }
+}
+
+fn build_scene(width: usize, height: usize, state: &AppState, config: &InstrumentConfig) -> Scene {
+    let mut scene = Scene::new(width, height);
+    update_scene(&mut scene, width, height, state, config);
+    scene
+}
+
+// Brings a scene up to date with `state`, rebuilding only the sections whose inputs changed
+fn update_scene(
+    scene: &mut Scene,
+    width: usize,
+    height: usize,
+    state: &AppState,
+    config: &InstrumentConfig,
+) {
+    trace_span!("build_scene");
+    scene.layers = visible_layers(&state.layer_order, &state.hidden_layers);
+    // Non-square canvases get the gauge laid out in the largest centred square
+    let (width, height, offset) = if config.letterbox && width != height {
+        let side = width.min(height);
+        let offset = (((width - side) / 2) as i32, ((height - side) / 2) as i32);
+        (side, side, offset)
+    } else {
+        (width, height, (0, 0))
+    };
+    let placement = Placement {
+        mirror_width: config.mirror_horizontal.then_some(width),
+        offset,
+    };
+    // Every section depends on where the gauge sits on the canvas
+    let canvas = (width, height, offset, config.mirror_horizontal);
+
+    let dial = Dial::new(width, height, config);
+    let is_out_of_range = state.is_out_of_range();
+    let base_color = if is_out_of_range {
+        (0xff, 0x00, 0x00)
+    } else {
+        (0x00, 0x00, 0x00)
+    };
+    let range = (state.min_value, state.max_value);
+    let range_bits = (range.0.to_bits(), range.1.to_bits());
+
+    scene.update_section(0, Layer::Background, canvas, None, |scene| {
+        if offset == (0, 0) {
+            scene.add_command(DrawCommand::Clear((0xff, 0xff, 0xff)));
+        } else {
+            scene.add_command(DrawCommand::Clear(config.letterbox_color.as_tuple()));
+            scene.add_command(DrawCommand::FillRect {
+                x: offset.0,
+                y: offset.1,
+                width,
+                height,
+                color: (0xff, 0xff, 0xff),
+            });
+        }
+    });
+
+    // Add highlight band if needed
+    let highlight_bounds = state.highlight_bounds.filter(|_| state.highlight_visible);
+    let highlight_bits = highlight_bounds.map(|(start, end)| {
+        (
+            start.to_bits(),
+            end.to_bits(),
+            state.highlight_alpha.to_bits(),
+        )
+    });
+    let zones = state.drawn_zones();
+    let zone_bits: Vec<_> = zones
+        .iter()
+        .map(|(zone, alpha)| (zone.from.to_bits(), zone.to.to_bits(), alpha.to_bits()))
+        .collect();
+    scene.update_section(
+        1,
+        Layer::Zones,
+        (canvas, range_bits, highlight_bits, &zone_bits),
+        Some(&placement),
+        |scene| {
+            if config.zone_bands {
+                let thickness =
+                    ((config.zone_band_width as f64 * dial.scale).round() as i32).max(1);
+                scene.add_translucent(config.zone_opacity, |scene| {
+                    for (zone, alpha) in &zones {
+                        let (from, to) = (
+                            zone.from.min(zone.to).clamp(range.0, range.1),
+                            zone.from.max(zone.to).clamp(range.0, range.1),
+                        );
+                        if to <= from {
+                            continue;
+                        }
+                        let start_angle = dial.angle_of(from, range);
+                        scene.add_command(DrawCommand::Arc {
+                            cx: dial.cx,
+                            cy: dial.cy,
+                            r: dial.r - dial.thickness,
+                            thickness,
+                            start_angle,
+                            arc_span: dial.angle_of(to, range) - start_angle,
+                            // The face under the bands is white
+                            color: blend((0xff, 0xff, 0xff), zone.color.as_tuple(), *alpha),
+                            style: LineStyle::Solid,
+                            cap: config.zone_band_cap,
+                        });
+                    }
+                });
+            }
+            if let Some((hl_start, hl_end)) = highlight_bounds {
+                let (norm_hl_start, norm_hl_end) = (
+                    scale_fraction(hl_start, range, dial.scale_break.as_ref()).clamp(0.0, 1.0),
+                    scale_fraction(hl_end, range, dial.scale_break.as_ref()).clamp(0.0, 1.0),
+                );
+                scene.add_command(DrawCommand::HighlightBand {
+                    cx: dial.cx,
+                    cy: dial.cy,
+                    r: dial.r,
+                    start_angle: dial.start_angle + dial.arc_span * norm_hl_start,
+                    end_angle: dial.start_angle + dial.arc_span * norm_hl_end,
+                    inner_radius: config.highlight_band_width as f64,
+                    outer_radius: 0.0,
+                    alpha: state.highlight_alpha,
+                });
+            }
+        },
+    );
+
+    // Lay out the movable complications before drawing anything, dropping any that
+    // would be too small to read at this size
+    let shown = |min_size: Option<usize>| min_size.is_none_or(|min| width.min(height) >= min);
+    let mut chrono_dial = state
+        .chronograph
+        .as_ref()
+        .filter(|_| shown(config.chronograph_min_size))
+        .map(|_| Dial::new_chronograph(width, height, config));
+    let mut sec_chrono_dial = state
+        .secondary_chronograph
+        .as_ref()
+        .filter(|_| shown(config.secondary_chronograph_min_size))
+        .map(|_| Dial::new_secondary_chronograph(width, height, config));
+    let mut readout = state
+        .displayed_readout()
+        .filter(|_| shown(config.readout_min_size))
+        .map(|value| ReadoutLayout {
+            held: state.held_readout.is_some(),
+            ..layout_readout(value, width, height, config)
+        });
+    if config.layout_collision_avoidance {
+        let obstacles = fixed_layout_obstacles(&dial, range, &main_ticks(range, config), config);
+        let mut movables: Vec<(u8, &mut dyn LayoutElement)> = Vec::new();
+        if let Some(ref mut readout) = readout {
+            movables.push((config.layout_priorities.readout, readout));
+        }
+        if let Some(ref mut chrono) = chrono_dial {
+            movables.push((config.layout_priorities.chronograph, chrono));
+        }
+        if let Some(ref mut sec_chrono) = sec_chrono_dial {
+            movables.push((config.layout_priorities.secondary_chronograph, sec_chrono));
+        }
+        resolve_layout_collisions(width, height, &obstacles, &mut movables);
+    }
+
+    // Main dial with ticks, labels and curved text
+    let tick_zones: &[(Zone, f64)] = if config.zone_ticks { &zones } else { &[] };
+    scene.update_section(
+        2,
+        Layer::Ticks,
+        (
+            canvas,
+            range_bits,
+            is_out_of_range,
+            config.zone_ticks.then_some(&zone_bits),
+        ),
+        Some(&placement),
+        |scene| {
+            let mut ticks = main_ticks(range, config);
+            for icon in config.scale_icons.iter().filter(|icon| icon.replaces_label) {
+                for (value, label) in &mut ticks {
+                    if (*value - icon.value).abs() <= f64::EPSILON * value.abs().max(1.0) * 4.0 {
+                        label.clear();
+                    }
                 }
-                DrawCommand::Text {
+            }
+            thin_labels(&mut ticks, &dial, config);
+            add_dial_with_ticks(
+                scene,
+                &dial,
+                range,
+                &ticks,
+                config.major_tick_length,
+                config.major_tick_thickness,
+                config.minor_tick_thickness,
+                config.minor_ticks_per_interval,
+                config.minor_tick_length,
+                config.dial_numbers_font_size,
+                config.dial_ticks_to_numbers_distance,
+                base_color,
+                tick_zones,
+            );
+            add_secondary_scale(scene, &dial, range, config);
+            if config.minor_tick_labels {
+                scene.add_translucent(config.tick_opacity, |scene| {
+                    add_minor_labels(scene, &dial, range, &ticks, config, base_color, tick_zones)
+                });
+            }
+            add_scale_icons(scene, &dial, range, config, base_color);
+
+            scene.add_command(DrawCommand::CurvedText {
+                cx: dial.cx,
+                cy: dial.cy,
+                radius: dial.r as f64 + config.curved_text_radius_offset,
+                text: config.curved_text.to_string(),
+                font_size: config.curved_text_font_size,
+                arc_span: config.curved_text_arc_span,
+                start_angle: config.curved_text_angle,
+                inverted: false,
+                letter_spacing: config.curved_text_letter_spacing,
+                fit: config.curved_text_fit,
+                color: base_color,
+            });
+            if !config.bottom_curved_text.is_empty() {
+                scene.add_command(DrawCommand::CurvedText {
+                    cx: dial.cx,
+                    cy: dial.cy,
+                    radius: dial.r as f64 + config.bottom_curved_text_radius_offset,
+                    text: config.bottom_curved_text.to_string(),
+                    font_size: config.bottom_curved_text_font_size,
+                    arc_span: config.bottom_curved_text_arc_span,
+                    start_angle: config.bottom_curved_text_angle,
+                    inverted: true,
+                    letter_spacing: config.bottom_curved_text_letter_spacing,
+                    fit: config.bottom_curved_text_fit,
+                    color: base_color,
+                });
+            }
+            for (x, y, text, font_size) in dial_text_lines(&dial, config) {
+                scene.add_command(DrawCommand::Text {
                     x,
                     y,
-                    text,
+                    text: text.to_string(),
                     font_size,
-                    color,
-                } => {
-                    let font =
-                        Font::try_from_vec(config.font_data.to_vec()).expect("Error loading font");
-                    let scale = Scale::uniform(*font_size);
-                    draw_text(
-                        canvas.frame,
-                        canvas.width,
-                        canvas.height,
-                        *x,
-                        *y,
-                        text,
-                        &font,
-                        scale,
-                        *color,
-                    );
+                    color: base_color,
+                });
+            }
+            if let Some((rect, bitmap)) = logo_placement(&dial, config) {
+                scene.add_command(DrawCommand::Image {
+                    x: (rect.left + rect.right) / 2,
+                    y: (rect.top + rect.bottom) / 2,
+                    width: (rect.right - rect.left) as usize,
+                    height: (rect.bottom - rect.top) as usize,
+                    bitmap: bitmap.clone(),
+                });
+            }
+        },
+    );
+
+    // Needles
+    let needle_pos = |needle: &Option<Needle>| needle.as_ref().map(|n| n.pos.to_bits());
+    let blink = |id| state.blink_opacity(id, config.needle_blink_rate);
+    let [primary_blink, secondary_blink, chrono_blink, sec_chrono_blink] = NeedleId::ALL.map(blink);
+    scene.update_section(
+        3,
+        Layer::Needles,
+        (
+            canvas,
+            needle_pos(&state.needle1),
+            needle_pos(&state.needle2),
+            is_out_of_range,
+            (primary_blink.to_bits(), secondary_blink.to_bits()),
+            (state.target_bug.map(f64::to_bits), range_bits),
+        ),
+        Some(&placement),
+        |scene| {
+            if let Some(value) = state.target_bug {
+                add_target_bug(
+                    scene,
+                    &dial,
+                    dial.angle_of(value.clamp(range.0, range.1), range),
+                    config,
+                );
+            }
+            let tip = config.needle_tip_color.map(|color| NeedleTip {
+                fraction: config.needle_tip_fraction,
+                thickness: config.needle_tip_width.unwrap_or(config.needle_width),
+                color: color.as_tuple(),
+            });
+            // A hub cap covers the pivot once, so the needles skip their own dots
+            let dot_radius = if config.hub_radius.is_some() {
+                0
+            } else {
+                config.dot_radius
+            };
+            let needles = [
+                (
+                    &state.needle1,
+                    (0x00, 0x00, 0x00),
+                    config.needle_opacity * primary_blink,
+                ),
+                (
+                    &state.needle2,
+                    (0x00, 0x7f, 0xff),
+                    config.secondary_needle_opacity * secondary_blink,
+                ),
+            ];
+            for (needle, color, opacity) in needles {
+                let Some(ref needle) = needle else {
+                    continue;
+                };
+                if opacity <= 0.0 {
+                    continue;
                 }
-                DrawCommand::CurvedText {
-                    cx,
-                    cy,
-                    radius,
-                    text,
-                    font_size,
-                    arc_span,
-                    start_angle,
-                    color,
-                } => {
-                    let font =
-                        Font::try_from_vec(config.font_data.to_vec()).expect("Error loading font");
-                    let scale = Scale::uniform(*font_size);
-                    draw_curved_text(
-                        canvas,
-                        *cx,
-                        *cy,
-                        *radius,
-                        text,
-                        &font,
-                        scale,
-                        *arc_span,
-                        *start_angle,
-                        *color,
+                let color = if is_out_of_range {
+                    (0xff, 0x00, 0x00)
+                } else {
+                    color
+                };
+                scene.add_translucent(opacity, |scene| {
+                    add_needle(
+                        scene,
+                        &dial,
+                        needle,
+                        color,
+                        config.needle_length_factor,
+                        config.needle_width,
+                        config.needle_back_length,
+                        dot_radius,
+                        tip,
+                        config.needle_shape,
+                    )
+                });
+            }
+            if let Some(radius) = config.hub_radius {
+                add_hub(scene, &dial, config, radius);
+            }
+        },
+    );
+
+    // Chronograph
+    let chrono_range_bits = (
+        state.chronograph_range.0.to_bits(),
+        state.chronograph_range.1.to_bits(),
+    );
+    scene.update_section(
+        4,
+        Layer::Subdials,
+        (
+            canvas,
+            chrono_dial.as_ref().map(Dial::fingerprint),
+            needle_pos(&state.chronograph),
+            chrono_range_bits,
+            is_out_of_range,
+            state.chronograph_peak.map(|(peak, _)| peak.to_bits()),
+            chrono_blink.to_bits(),
+        ),
+        Some(&placement),
+        |scene| {
+            if let (Some(needle), Some(chrono_dial)) = (&state.chronograph, &chrono_dial) {
+                let color = if is_out_of_range {
+                    (0xff, 0x00, 0x00)
+                } else {
+                    (0xff, 0x80, 0x00)
+                };
+                add_dial_with_ticks(
+                    scene,
+                    chrono_dial,
+                    state.chronograph_range,
+                    &subdial_ticks(
+                        state.chronograph_range,
+                        config.chronograph_ticks_count,
+                        config.chronograph_label_formatter.as_ref(),
+                        config
+                            .chronograph_labels
+                            .then_some(config.chronograph_label_every),
+                    ),
+                    config.chronograph_tick_length,
+                    config.chronograph_major_tick_thickness,
+                    config.chronograph_minor_tick_thickness,
+                    config.chronograph_minor_ticks_per_interval,
+                    config.chronograph_minor_tick_length,
+                    config.chronograph_dial_numbers_font_size,
+                    config.chronograph_dial_ticks_to_numbers_distance,
+                    (0x00, 0x00, 0x00),
+                    &[],
+                );
+                if let Some((peak, _)) = state.chronograph_peak {
+                    let tick_length =
+                        (config.chronograph_tick_length as f64 * chrono_dial.scale).round() as i32;
+                    scene.add_command(DrawCommand::Tick {
+                        cx: chrono_dial.cx,
+                        cy: chrono_dial.cy,
+                        r: chrono_dial.tick_radius(tick_length),
+                        angle: chrono_dial.angle_of(
+                            peak.clamp(state.chronograph_range.0, state.chronograph_range.1),
+                            state.chronograph_range,
+                        ),
+                        length: tick_length,
+                        thickness: config.chronograph_major_tick_thickness
+                            * 2.0
+                            * chrono_dial.scale as f32,
+                        color: (0xff, 0x00, 0x00),
+                        style: LineStyle::Solid,
+                    });
+                }
+                scene.add_translucent(chrono_blink, |scene| {
+                    add_needle(
+                        scene,
+                        chrono_dial,
+                        needle,
+                        color,
+                        config.chronograph_needle_length_factor,
+                        config.chronograph_needle_width,
+                        config.chronograph_needle_back_length,
+                        config.chronograph_dial_dot_radius,
+                        None,
+                        NeedleShape::Line,
+                    )
+                });
+            }
+        },
+    );
+
+    // Secondary chronograph
+    let sec_chrono_range_bits = (
+        state.secondary_chronograph_range.0.to_bits(),
+        state.secondary_chronograph_range.1.to_bits(),
+    );
+    scene.update_section(
+        5,
+        Layer::Subdials,
+        (
+            canvas,
+            sec_chrono_dial.as_ref().map(Dial::fingerprint),
+            needle_pos(&state.secondary_chronograph),
+            sec_chrono_range_bits,
+            is_out_of_range,
+            sec_chrono_blink.to_bits(),
+        ),
+        Some(&placement),
+        |scene| {
+            if let (Some(needle), Some(sec_chrono_dial)) =
+                (&state.secondary_chronograph, &sec_chrono_dial)
+            {
+                let color = if is_out_of_range {
+                    (0xff, 0x00, 0x00)
+                } else {
+                    (0x00, 0x80, 0xff)
+                };
+                add_dial_with_ticks(
+                    scene,
+                    sec_chrono_dial,
+                    state.secondary_chronograph_range,
+                    &subdial_ticks(
+                        state.secondary_chronograph_range,
+                        config.secondary_chronograph_ticks_count,
+                        config.secondary_chronograph_label_formatter.as_ref(),
+                        config
+                            .secondary_chronograph_labels
+                            .then_some(config.secondary_chronograph_label_every),
+                    ),
+                    config.secondary_chronograph_tick_length,
+                    config.secondary_chronograph_major_tick_thickness,
+                    config.secondary_chronograph_minor_tick_thickness,
+                    config.secondary_chronograph_minor_ticks_per_interval,
+                    config.secondary_chronograph_minor_tick_length,
+                    config.secondary_chronograph_dial_numbers_font_size,
+                    config.secondary_chronograph_dial_ticks_to_numbers_distance,
+                    (0x00, 0x00, 0x00),
+                    &[],
+                );
+                scene.add_translucent(sec_chrono_blink, |scene| {
+                    add_needle(
+                        scene,
+                        sec_chrono_dial,
+                        needle,
+                        color,
+                        config.secondary_chronograph_needle_length_factor,
+                        config.secondary_chronograph_needle_width,
+                        config.secondary_chronograph_needle_back_length,
+                        config.secondary_chronograph_dial_dot_radius,
+                        None,
+                        NeedleShape::Line,
+                    )
+                });
+            }
+        },
+    );
+
+    // Readout and totalizer
+    let totalizer = config.totalizer.then(|| {
+        format_totalizer(
+            state.totalizer.total,
+            config.totalizer_digits,
+            config.totalizer_decimals,
+        )
+    });
+    scene.update_section(
+        6,
+        Layer::Readout,
+        (
+            canvas,
+            readout.as_ref().map(ReadoutLayout::fingerprint),
+            &totalizer,
+            is_out_of_range,
+        ),
+        Some(&placement),
+        |scene| {
+            scene.add_translucent(config.readout_opacity, |scene| {
+                if let Some(ref readout) = readout {
+                    add_readout(
+                        scene,
+                        readout,
+                        (
+                            config.readout_box_thickness,
+                            config.readout_box_corner_radius,
+                        ),
+                        base_color,
                     );
                 }
-                DrawCommand::NeedleLine {
-                    x0,
-                    y0,
-                    x1,
-                    y1,
-                    thickness,
-                    tapered,
-                    color,
-                } => {
-                    if *tapered {
-                        draw_thick_line_tapered_aa(
-                            canvas.frame,
-                            canvas.width,
-                            *x0,
-                            *y0,
-                            *x1,
-                            *y1,
-                            *thickness,
-                            color.0,
-                            color.1,
-                            color.2,
-                        );
-                    } else {
-                        draw_thick_line_aa(
-                            canvas.frame,
-                            canvas.width,
-                            *x0,
-                            *y0,
-                            *x1,
-                            *y1,
-                            *thickness,
-                            color.0,
-                            color.1,
-                            color.2,
-                        );
-                    }
+                if let Some(ref text) = totalizer {
+                    add_totalizer(scene, width, height, text, config);
                 }
-                DrawCommand::Circle {
-                    cx,
-                    cy,
-                    radius,
+            });
+        },
+    );
+
+    // Warning indicator and alerts. Each flash is on for the first half of its period.
+    let flash = state.active_alerts().find_map(|(rule, active)| {
+        let color = rule.flash?;
+        ((active * ALERT_FLASH_RATE).fract() < 0.5).then(|| color.as_tuple())
+    });
+    let indicators: Vec<&str> = state
+        .active_alerts()
+        .filter_map(|(rule, _)| rule.indicator.as_deref())
+        .collect();
+    let tell_tales = state
+        .tell_tales
+        .filter(|_| config.tell_tales)
+        .map(|(low, high)| (low.to_bits(), high.to_bits()));
+    scene.update_section(
+        7,
+        Layer::Overlay,
+        (
+            canvas,
+            is_out_of_range,
+            flash,
+            &indicators,
+            tell_tales,
+            range_bits,
+            state.failure.as_deref(),
+        ),
+        Some(&placement),
+        |scene| {
+            if is_out_of_range {
+                scene.add_command(DrawCommand::Text {
+                    x: dial.cx,
+                    y: dial.cy - (dial.r / 4),
+                    text: "!".to_string(),
+                    font_size: config.exclamation_mark_size,
+                    color: (0xff, 0x00, 0x00),
+                });
+            }
+            if let Some(color) = flash {
+                scene.add_command(DrawCommand::Arc {
+                    cx: dial.cx,
+                    cy: dial.cy,
+                    r: dial.r,
+                    thickness: dial.thickness * 3,
+                    start_angle: dial.start_angle,
+                    arc_span: dial.arc_span,
                     color,
-                } => {
-                    draw_circle(
-                        canvas.frame,
-                        canvas.width,
-                        *cx,
-                        *cy,
-                        *radius,
-                        color.0,
-                        color.1,
-                        color.2,
-                    );
-                }
+                    style: LineStyle::Solid,
+                    cap: ArcCap::Butt,
+                });
+            }
+            for value in tell_tales.into_iter().flat_map(|(low, high)| [low, high]) {
+                scene.add_command(DrawCommand::Tick {
+                    cx: dial.cx,
+                    cy: dial.cy,
+                    r: dial.r,
+                    angle: dial.start_angle
+                        + dial.arc_span * state.normalize(f64::from_bits(value)),
+                    length: config.major_tick_length,
+                    thickness: config.major_tick_thickness * 2.0,
+                    color: (0xff, 0x00, 0x00),
+                    style: LineStyle::Solid,
+                });
+            }
+            if !indicators.is_empty() {
+                scene.add_command(DrawCommand::Text {
+                    x: dial.cx,
+                    y: dial.cy + (dial.r / 4),
+                    text: indicators.join(" "),
+                    font_size: config.alert_indicator_font_size,
+                    color: (0xff, 0x00, 0x00),
+                });
+            }
+            if let Some(ref message) = state.failure {
+                scene.add_command(DrawCommand::Circle {
+                    cx: dial.cx,
+                    cy: dial.cy,
+                    radius: dial.r / 3,
+                    color: (0xff, 0xff, 0xff),
+                });
+                scene.add_command(DrawCommand::Text {
+                    x: dial.cx,
+                    y: dial.cy,
+                    text: message.clone(),
+                    font_size: config.alert_indicator_font_size,
+                    color: (0xff, 0x00, 0x00),
+                });
+            }
+        },
+    );
+
+    // Custom layer; its inputs are opaque, so it is rebuilt every frame
+    if let Some(ref layer) = config.custom_layer {
+        let context = LayerContext {
+            width,
+            height,
+            cx: dial.cx,
+            cy: dial.cy,
+            radius: dial.r,
+        };
+        scene.replace_section(8, config.custom_layer_z, 0, Some(&placement), |scene| {
+            (layer.0)(scene, &context)
+        });
+    }
+    scene.truncate_sections(if config.custom_layer.is_some() { 9 } else { 8 });
+}
+
+fn main_ticks(range: (f64, f64), config: &InstrumentConfig) -> Vec<(f64, String)> {
+    if let Some(ref custom) = config.custom_ticks {
+        let mut ticks = explicit_ticks(custom);
+        if let Some(ref scale_break) = config.scale_break {
+            ticks.retain(|(value, _)| !scale_break.hides(*value));
+        }
+        return ticks;
+    }
+    let generate = |range: (f64, f64), share: f64| {
+        if config.auto_ticks {
+            nice_ticks(
+                range,
+                ((config.auto_ticks_target as f64 * share).round() as usize).max(1),
+            )
+        } else {
+            even_ticks(
+                range,
+                ((config.ticks_count as f64 * share).round() as usize).max(2),
+            )
+        }
+    };
+    let ticks = match config
+        .scale_break
+        .filter(|scale_break| scale_break.from > range.0 && scale_break.to < range.1)
+    {
+        // Each side of the break gets ticks in proportion to the arc it covers
+        Some(scale_break) => {
+            let below = scale_break.fraction(scale_break.from, range);
+            let above = 1.0 - below - scale_break.gap;
+            let mut ticks = generate((range.0, scale_break.from), below);
+            ticks.extend(generate((scale_break.to, range.1), above));
+            ticks
+        }
+        None if config.auto_ticks => nice_ticks(range, config.auto_ticks_target),
+        None => even_ticks(range, config.ticks_count),
+    };
+    let ticks = relabel(ticks, config.number_format);
+    if !config.signed_labels {
+        return ticks;
+    }
+    ticks
+        .into_iter()
+        .map(|(value, label)| match label.as_str() {
+            "" | "0" => (value, label),
+            _ if value > 0.0 => (value, format!("+{label}")),
+            _ => (value, label),
+        })
+        .collect()
+}
+
+// Large labels reformatted; anything below a thousand keeps its generated text
+fn relabel(ticks: Vec<(f64, String)>, format: NumberFormat) -> Vec<(f64, String)> {
+    ticks
+        .into_iter()
+        .map(|(value, label)| {
+            if format.split(value).1.is_empty() {
+                (value, label)
+            } else {
+                (value, format.format(value, 0))
             }
+        })
+        .collect()
+}
+
+// ============================================================================
+// DEBUG OVERLAY
+// ============================================================================
+
+type CommandCosts = Vec<(&'static str, Duration)>;
+
+#[derive(Default)]
+struct FrameStats {
+    last_frame: Option<Instant>,
+    fps: f64,
+    render_time: Duration,
+    command_costs: CommandCosts,
+}
+
+impl FrameStats {
+    fn record_frame(&mut self, render_time: Duration) {
+        let now = Instant::now();
+        if let Some(last) = self.last_frame {
+            let instant_fps = 1.0 / now.duration_since(last).as_secs_f64().max(1e-6);
+            // Exponential smoothing keeps the number readable
+            self.fps = if self.fps == 0.0 {
+                instant_fps
+            } else {
+                self.fps * 0.9 + instant_fps * 0.1
+            };
+        }
+        self.last_frame = Some(now);
+        self.render_time = render_time;
+    }
+}
+
+fn draw_debug_overlay(
+    frame: &mut [u8],
+    width: usize,
+    height: usize,
+    stats: &FrameStats,
+    config: &InstrumentConfig,
+) {
+    const FONT_SIZE: f32 = 14.0;
+    const LINE_HEIGHT: i32 = 18;
+    const MARGIN: i32 = 6;
+
+    let mut costs = stats.command_costs.clone();
+    costs.sort_by_key(|&(_, cost)| std::cmp::Reverse(cost));
+    let lines: Vec<String> = [
+        format!("{:.1} fps", stats.fps),
+        format!("frame {:.2} ms", stats.render_time.as_secs_f64() * 1000.0),
+    ]
+    .into_iter()
+    .chain(
+        costs
+            .iter()
+            .map(|(kind, cost)| format!("{kind} {:.2} ms", cost.as_secs_f64() * 1000.0)),
+    )
+    .collect();
+
+    let shaper = text_shaper(config);
+    let box_width = lines
+        .iter()
+        .map(|line| calculate_text_width(line, shaper, FONT_SIZE))
+        .max()
+        .unwrap_or(0)
+        + 2 * MARGIN;
+    let box_height = lines.len() as i32 * LINE_HEIGHT + MARGIN;
+    let mut canvas = Canvas::new(frame, width, height);
+    canvas.fill_rect(
+        0,
+        0,
+        box_width as usize,
+        box_height as usize,
+        (0x00, 0x00, 0x00),
+    );
+    for (i, line) in lines.iter().enumerate() {
+        let line_width = calculate_text_width(line, shaper, FONT_SIZE);
+        draw_text(
+            &mut canvas,
+            MARGIN + line_width / 2,
+            MARGIN + i as i32 * LINE_HEIGHT + LINE_HEIGHT / 2,
+            line,
+            shaper,
+            FONT_SIZE,
+            (0xff, 0xff, 0xff),
+        );
+    }
+}
+
+// ============================================================================
+// LAYOUT
+// ============================================================================
+
+#[derive(Debug, Clone, Copy)]
+struct Rect {
+    left: i32,
+    top: i32,
+    right: i32,
+    bottom: i32,
+}
+
+impl Rect {
+    fn around(cx: i32, cy: i32, half_width: i32, half_height: i32) -> Self {
+        Self {
+            left: cx - half_width,
+            top: cy - half_height,
+            right: cx + half_width,
+            bottom: cy + half_height,
+        }
+    }
+
+    fn center(&self) -> (i32, i32) {
+        ((self.left + self.right) / 2, (self.top + self.bottom) / 2)
+    }
+
+    fn intersects(&self, other: &Rect) -> bool {
+        self.left < other.right
+            && other.left < self.right
+            && self.top < other.bottom
+            && other.top < self.bottom
+    }
+
+    // Smallest translation that moves `self` clear of `other`
+    fn separation_from(&self, other: &Rect) -> (i32, i32) {
+        let (cx, cy) = self.center();
+        let (ox, oy) = other.center();
+        let push_x = if cx < ox {
+            other.left - self.right
+        } else {
+            other.right - self.left
+        };
+        let push_y = if cy < oy {
+            other.top - self.bottom
+        } else {
+            other.bottom - self.top
+        };
+        if push_x.abs() <= push_y.abs() {
+            (push_x, 0)
+        } else {
+            (0, push_y)
+        }
+    }
+}
+
+/// Relative importance of the movable complications; on overlap the lower one yields
+#[derive(Debug, Clone, Copy)]
+pub struct LayoutPriorities {
+    pub readout: u8,
+    pub chronograph: u8,
+    pub secondary_chronograph: u8,
+}
+
+impl Default for LayoutPriorities {
+    fn default() -> Self {
+        Self {
+            readout: 3,
+            chronograph: 2,
+            secondary_chronograph: 1,
         }
     }
 }
 
-// ============================================================================
-// CORE DATA TYPES
-// ============================================================================
+trait LayoutElement {
+    fn bounds(&self) -> Rect;
+    fn translate(&mut self, dx: i32, dy: i32);
+    fn shrink(&mut self, factor: f64);
+}
 
-struct Canvas<'a> {
-    frame: &'a mut [u8],
+impl Dial {
+    fn fingerprint(&self) -> (i32, i32, i32, i32, u64) {
+        (
+            self.cx,
+            self.cy,
+            self.r,
+            self.thickness,
+            self.scale.to_bits(),
+        )
+    }
+}
+
+impl LayoutElement for Dial {
+    fn bounds(&self) -> Rect {
+        Rect::around(self.cx, self.cy, self.r, self.r)
+    }
+
+    fn translate(&mut self, dx: i32, dy: i32) {
+        self.cx += dx;
+        self.cy += dy;
+    }
+
+    fn shrink(&mut self, factor: f64) {
+        self.r = (self.r as f64 * factor).round() as i32;
+        self.thickness = ((self.thickness as f64 * factor).round() as i32).max(1);
+        self.scale *= factor;
+    }
+}
+
+struct ReadoutLayout {
+    bounds: Rect,
+    integer: (i32, i32, String),
+    fraction: (i32, i32, String),
+    big_font_size: f32,
+    small_font_size: f32,
+    held: bool,
+}
+
+impl ReadoutLayout {
+    fn fingerprint(&self) -> impl std::hash::Hash + '_ {
+        let Rect {
+            left,
+            top,
+            right,
+            bottom,
+        } = self.bounds;
+        (
+            (left, top, right, bottom),
+            &self.integer,
+            &self.fraction,
+            self.big_font_size.to_bits(),
+            self.small_font_size.to_bits(),
+            self.held,
+        )
+    }
+}
+
+impl LayoutElement for ReadoutLayout {
+    fn bounds(&self) -> Rect {
+        self.bounds
+    }
+
+    fn translate(&mut self, dx: i32, dy: i32) {
+        self.bounds = Rect {
+            left: self.bounds.left + dx,
+            top: self.bounds.top + dy,
+            right: self.bounds.right + dx,
+            bottom: self.bounds.bottom + dy,
+        };
+        self.integer.0 += dx;
+        self.integer.1 += dy;
+        self.fraction.0 += dx;
+        self.fraction.1 += dy;
+    }
+
+    fn shrink(&mut self, factor: f64) {
+        let (cx, cy) = self.bounds.center();
+        let scale = |v: i32, c: i32| c + ((v - c) as f64 * factor).round() as i32;
+        self.bounds = Rect {
+            left: scale(self.bounds.left, cx),
+            top: scale(self.bounds.top, cy),
+            right: scale(self.bounds.right, cx),
+            bottom: scale(self.bounds.bottom, cy),
+        };
+        self.integer.0 = scale(self.integer.0, cx);
+        self.integer.1 = scale(self.integer.1, cy);
+        self.fraction.0 = scale(self.fraction.0, cx);
+        self.fraction.1 = scale(self.fraction.1, cy);
+        self.big_font_size *= factor as f32;
+        self.small_font_size *= factor as f32;
+    }
+}
+
+fn layout_readout(
+    value: f64,
     width: usize,
     height: usize,
-}
+    config: &InstrumentConfig,
+) -> ReadoutLayout {
+    let (value, suffix) = config.number_format.split(value);
+    let (value_int, value_frac) = (
+        value.trunc() as i32,
+        ((value.fract().abs() * 1000.0).round() as u32).min(999),
+    );
+    let value_str = format!("{}", value_int);
+    let frac_str = format!("{:03}{}", value_frac, suffix);
+
+    // Size the integer field for `readout_integer_digits` so the box stays put as the
+    // digit count changes, growing only when the value needs more room
+    let shaper = text_shaper(config);
+    let big_size = config.readout_big_font_size;
+    let small_size = config.readout_small_font_size;
+    let int_width = calculate_text_width(&value_str, shaper, big_size);
+    let reserved_width = int_width.max(calculate_text_width(
+        &"0".repeat(config.readout_integer_digits),
+        shaper,
+        big_size,
+    ));
+    let frac_width = calculate_text_width(&frac_str, shaper, small_size);
+    // The box fits a digit; the fields are then centred on their own ink so that signs
+    // and unit suffixes keep the digits on a shared baseline
+    let (digit_top, digit_bottom) = text_ink_rows("0", shaper, big_size);
+    let big_height = digit_bottom - digit_top;
+    let ink_middle = |text: &str, font_size: f32| {
+        let (top, bottom) = text_ink_rows(text, shaper, font_size);
+        (top + bottom) / 2
+    };
+    let gap = (small_size / 3.0) as i32;
+
+    let box_padding = config.readout_box_padding;
+    let box_width = reserved_width + gap + frac_width + 2 * box_padding;
+    let box_height = big_height + 2 * box_padding;
+    let (anchor_x, anchor_y) = (
+        (width as f64 * config.readout_x_factor) as i32,
+        (height as f64 * config.readout_y_factor) as i32,
+    );
+    let box_left = match config.readout_horizontal_anchor {
+        HorizontalAnchor::Left => anchor_x,
+        HorizontalAnchor::Center => anchor_x - box_width / 2,
+        HorizontalAnchor::Right => anchor_x - box_width,
+    };
+    let box_top = match config.readout_vertical_anchor {
+        VerticalAnchor::Top => anchor_y,
+        VerticalAnchor::Middle => anchor_y - box_height / 2,
+        VerticalAnchor::Bottom => anchor_y - box_height,
+    };
 
-impl<'a> Canvas<'a> {
-    fn new(frame: &'a mut [u8], width: usize, height: usize) -> Self {
-        Self {
-            frame,
-            width,
-            height,
-        }
+    let field_left = box_left + box_padding;
+    let int_x = field_left
+        + match config.readout_alignment {
+            ReadoutAlignment::Left => int_width / 2,
+            ReadoutAlignment::Center => reserved_width / 2,
+            ReadoutAlignment::Right => reserved_width - int_width / 2,
+        };
+    // Fraction digits share the integer's baseline
+    let baseline = box_top + box_padding - digit_top;
+    ReadoutLayout {
+        bounds: Rect {
+            left: box_left,
+            top: box_top,
+            right: box_left + box_width,
+            bottom: box_top + box_height,
+        },
+        integer: (
+            int_x,
+            baseline + ink_middle(&value_str, big_size),
+            value_str,
+        ),
+        fraction: (
+            field_left + reserved_width + gap + frac_width / 2,
+            baseline + ink_middle(&frac_str, small_size),
+            frac_str,
+        ),
+        big_font_size: big_size,
+        small_font_size: small_size,
+        held: false,
     }
+}
 
-    fn clear(&mut self, color: (u8, u8, u8)) {
-        for chunk in self.frame.chunks_exact_mut(4) {
-            chunk.copy_from_slice(&[color.0, color.1, color.2, 0xff]);
+// Tick labels and curved text are anchored to the main dial and never move
+fn fixed_layout_obstacles(
+    dial: &Dial,
+    range: (f64, f64),
+    ticks: &[(f64, String)],
+    config: &InstrumentConfig,
+) -> Vec<Rect> {
+    let shaper = text_shaper(config);
+    let font_size = config.dial_numbers_font_size;
+    let (_, _, top, bottom) = glyph_bounds(&shaper.shape("0", font_size));
+    let half_height = (bottom - top) / 2;
+    let label_radius =
+        dial.label_radius(config.major_tick_length as f64 + config.dial_ticks_to_numbers_distance);
+    let mut obstacles: Vec<Rect> = ticks
+        .iter()
+        .map(|(value, label)| {
+            let angle = dial.angle_of(*value, range);
+            Rect::around(
+                (dial.cx as f64 + angle.cos() * label_radius) as i32,
+                (dial.cy as f64 + angle.sin() * label_radius) as i32,
+                calculate_text_width(label, shaper, font_size) / 2,
+                half_height,
+            )
+        })
+        .collect();
+
+    for (text, radius_offset, font_size, arc_span, angle) in [
+        (
+            &config.curved_text,
+            config.curved_text_radius_offset,
+            config.curved_text_font_size,
+            config.curved_text_arc_span,
+            config.curved_text_angle,
+        ),
+        (
+            &config.bottom_curved_text,
+            config.bottom_curved_text_radius_offset,
+            config.bottom_curved_text_font_size,
+            config.bottom_curved_text_arc_span,
+            config.bottom_curved_text_angle,
+        ),
+    ] {
+        if text.is_empty() {
+            continue;
         }
+        let radius = dial.r as f64 + radius_offset;
+        let text_span =
+            (calculate_text_width(text, shaper, font_size) as f64 / radius).min(arc_span);
+        let (inner, outer) = (
+            radius - font_size as f64 / 2.0,
+            radius + font_size as f64 / 2.0,
+        );
+        let points: Vec<(f64, f64)> = (0..=8)
+            .map(|i| angle - text_span / 2.0 + text_span * i as f64 / 8.0)
+            .flat_map(|a| [(a, inner), (a, outer)])
+            .map(|(a, r)| (dial.cx as f64 + a.cos() * r, dial.cy as f64 + a.sin() * r))
+            .collect();
+        obstacles.push(Rect {
+            left: points.iter().map(|p| p.0).fold(f64::MAX, f64::min) as i32,
+            top: points.iter().map(|p| p.1).fold(f64::MAX, f64::min) as i32,
+            right: points.iter().map(|p| p.0).fold(f64::MIN, f64::max) as i32,
+            bottom: points.iter().map(|p| p.1).fold(f64::MIN, f64::max) as i32,
+        });
+    }
+    for (x, y, text, font_size) in dial_text_lines(dial, config) {
+        let half_width = calculate_text_width(text, shaper, font_size) / 2;
+        obstacles.push(Rect::around(x, y, half_width, font_size as i32 / 2));
     }
+    obstacles.extend(logo_placement(dial, config).map(|(rect, _)| rect));
+    obstacles
 }
 
-struct AppState {
-    needle1: Option<Needle>,
-    needle2: Option<Needle>,
-    chronograph: Option<Needle>,
-    secondary_chronograph: Option<Needle>,
-    readout_value: Option<f64>,
-    min_value: f64,
-    max_value: f64,
-    chronograph_range: (f64, f64),
-    secondary_chronograph_range: (f64, f64),
-    highlight_bounds: Option<(f64, f64)>,
+// Where the logo goes on the dial, if there is one big enough to see
+fn logo_placement<'a>(
+    dial: &Dial,
+    config: &'a InstrumentConfig,
+) -> Option<(Rect, &'a std::sync::Arc<Bitmap>)> {
+    let bitmap = config.logo.as_ref()?;
+    let height = (dial.r as f64 * config.logo_size).round() as i32;
+    let width = (height as f64 * bitmap.width as f64 / bitmap.height.max(1) as f64).round() as i32;
+    if width <= 0 || height <= 0 {
+        return None;
+    }
+    let (x, y) = config.logo_position;
+    let (cx, cy) = (
+        dial.cx + (dial.r as f64 * x).round() as i32,
+        dial.cy + (dial.r as f64 * y).round() as i32,
+    );
+    let rect = Rect {
+        left: cx - width / 2,
+        top: cy - height / 2,
+        right: cx - width / 2 + width,
+        bottom: cy - height / 2 + height,
+    };
+    Some((rect, bitmap))
 }
 
-impl AppState {
-    fn new(min_value: f64, max_value: f64) -> Self {
-        Self {
-            needle1: None,
-            needle2: None,
-            chronograph: None,
-            secondary_chronograph: None,
-            readout_value: None,
-            min_value,
-            max_value,
-            chronograph_range: (0.0, 60.0),
-            secondary_chronograph_range: (0.0, 60.0),
-            highlight_bounds: None,
+// Each line of the dial text block with the point its ink is centred on, stacked
+// downwards from the configured position
+fn dial_text_lines<'a>(dial: &Dial, config: &'a InstrumentConfig) -> Vec<(i32, i32, &'a str, f32)> {
+    let (x, y) = config.dial_text_position;
+    let x = dial.cx + (dial.r as f64 * x).round() as i32;
+    let mut y = dial.cy as f64 + dial.r as f64 * y;
+    let mut lines = Vec::new();
+    let mut previous_size = None;
+    for line in config.dial_text.lines() {
+        let font_size = match previous_size {
+            None => config.dial_text_font_size,
+            Some(_) => config.dial_text_subtitle_font_size,
+        };
+        if let Some(previous) = previous_size {
+            y += f64::from(previous + font_size) / 2.0 * 1.2;
+        }
+        previous_size = Some(font_size);
+        if !line.trim().is_empty() {
+            lines.push((x, y.round() as i32, line, font_size));
         }
     }
+    lines
+}
 
-    fn set_primary_value(&mut self, value: f64) {
-        if self.needle1.is_none() {
-            self.needle1 = Some(Needle::new());
+// Pushes lower-priority elements clear of fixed obstacles and of higher-priority elements,
+// keeping them inside the window. Elements that can't be pushed clear shrink instead.
+fn resolve_layout_collisions(
+    width: usize,
+    height: usize,
+    obstacles: &[Rect],
+    movables: &mut [(u8, &mut dyn LayoutElement)],
+) {
+    const MAX_PASSES: usize = 8;
+    const SHRINK_FACTOR: f64 = 0.9;
+
+    for _ in 0..MAX_PASSES {
+        let mut moved = false;
+        for i in 0..movables.len() {
+            let priority = movables[i].0;
+            let blockers: Vec<Rect> = obstacles
+                .iter()
+                .copied()
+                .chain(
+                    movables
+                        .iter()
+                        .enumerate()
+                        .filter(|(j, (other, _))| *j != i && (*other, *j) > (priority, i))
+                        .map(|(_, (_, element))| element.bounds()),
+                )
+                .collect();
+
+            for blocker in &blockers {
+                let element = &mut movables[i].1;
+                if !element.bounds().intersects(blocker) {
+                    continue;
+                }
+                moved = true;
+                let (dx, dy) = element.bounds().separation_from(blocker);
+                element.translate(dx, dy);
+
+                // Pull back inside the window
+                let bounds = element.bounds();
+                let back_x = (-bounds.left).max(0) + (width as i32 - bounds.right).min(0);
+                let back_y = (-bounds.top).max(0) + (height as i32 - bounds.bottom).min(0);
+                element.translate(back_x, back_y);
+
+                if element.bounds().intersects(blocker) {
+                    element.translate(-dx - back_x, -dy - back_y);
+                    element.shrink(SHRINK_FACTOR);
+                }
+            }
         }
-        if let Some(ref mut needle) = self.needle1 {
-            needle.set_target_pos(
-                ((value - self.min_value) / (self.max_value - self.min_value)).clamp(0.0, 1.0),
-            );
+        if !moved {
+            break;
         }
     }
+}
 
-    fn set_secondary_value(&mut self, value: f64) {
-        if self.needle2.is_none() {
-            self.needle2 = Some(Needle::new());
-        }
-        if let Some(ref mut needle) = self.needle2 {
-            needle.set_target_pos(
-                ((value - self.min_value) / (self.max_value - self.min_value)).clamp(0.0, 1.0),
+// Zero-padded to a fixed width, rolling over past `digits` whole digits
+fn format_totalizer(total: f64, digits: usize, decimals: usize) -> String {
+    let wrap = 10f64.powi(digits.min(15) as i32);
+    let shown = total.rem_euclid(wrap);
+    let width = digits + if decimals > 0 { decimals + 1 } else { 0 };
+    format!("{shown:0width$.decimals$}")
+}
+
+fn add_totalizer(
+    scene: &mut Scene,
+    width: usize,
+    height: usize,
+    text: &str,
+    config: &InstrumentConfig,
+) {
+    let font_size = config.totalizer_font_size;
+    let (x, y) = (
+        (width as f64 * config.totalizer_x_factor) as i32,
+        (height as f64 * config.totalizer_y_factor) as i32,
+    );
+    let shaper = text_shaper(config);
+    let half_width = calculate_text_width(text, shaper, font_size) / 2;
+    let padding = (font_size / 4.0) as i32;
+    let (top, bottom) = text_ink_rows(text, shaper, font_size);
+    let half_height = (bottom - top) / 2;
+    scene.add_command(DrawCommand::FillRect {
+        x: x - half_width - padding,
+        y: y - half_height - padding,
+        width: (2 * (half_width + padding)) as usize,
+        height: (2 * (half_height + padding)) as usize,
+        color: (0x00, 0x00, 0x00),
+    });
+    scene.add_command(DrawCommand::Text {
+        x,
+        y,
+        text: text.to_string(),
+        font_size,
+        color: (0xff, 0xff, 0xff),
+    });
+}
+
+fn add_readout(
+    scene: &mut Scene,
+    readout: &ReadoutLayout,
+    (box_thickness, corner_radius): (f32, f64),
+    color: (u8, u8, u8),
+) {
+    let (int_x, int_y, ref int_text) = readout.integer;
+    scene.add_command(DrawCommand::Text {
+        x: int_x,
+        y: int_y,
+        text: int_text.clone(),
+        font_size: readout.big_font_size,
+        color,
+    });
+    let (frac_x, frac_y, ref frac_text) = readout.fraction;
+    scene.add_command(DrawCommand::Text {
+        x: frac_x,
+        y: frac_y,
+        text: frac_text.clone(),
+        font_size: readout.small_font_size,
+        color,
+    });
+
+    // Readout box
+    let Rect {
+        left,
+        top,
+        right,
+        bottom,
+    } = readout.bounds;
+    scene.add_command(DrawCommand::RoundedRect {
+        x: left,
+        y: top,
+        width: (right - left + 1).max(0) as usize,
+        height: (bottom - top + 1).max(0) as usize,
+        radius: corner_radius,
+        stroke: Some(box_thickness),
+        color,
+    });
+
+    // Hold flag centred above the box
+    if readout.held {
+        let font_size = readout.small_font_size * 0.6;
+        scene.add_command(DrawCommand::Text {
+            x: (left + right) / 2,
+            y: top - (font_size * 0.8) as i32,
+            text: "HOLD".to_string(),
+            font_size,
+            color,
+        });
+    }
+}
+
+// Helper functions to reduce repetitive rendering code
+fn add_dial_with_ticks(
+    scene: &mut Scene,
+    dial: &Dial,
+    range: (f64, f64),
+    ticks: &[(f64, String)],
+    tick_length: i32,
+    major_tick_thickness: f32,
+    minor_tick_thickness: f32,
+    minor_ticks_per_interval: usize,
+    minor_tick_length: i32,
+    font_size: f32,
+    ticks_to_numbers_distance: f64,
+    dial_color: (u8, u8, u8),
+    zones: &[(Zone, f64)],
+) {
+    let tick_length = (tick_length as f64 * dial.scale).round() as i32;
+    let minor_tick_length = (minor_tick_length as f64 * dial.scale).round() as i32;
+    let (major_tick_thickness, minor_tick_thickness) = (
+        major_tick_thickness * dial.scale as f32,
+        minor_tick_thickness * dial.scale as f32,
+    );
+    let font_size = font_size * dial.scale as f32;
+    let ticks_to_numbers_distance = ticks_to_numbers_distance * dial.scale;
+    scene.add_translucent(dial.opacity.0, |scene| {
+        add_dial_arc(scene, dial, range, tick_length, dial_color)
+    });
+    scene.add_translucent(dial.opacity.1, |scene| {
+        let normalize = |value: f64| scale_fraction(value, range, dial.scale_break.as_ref());
+        for (i, (value, label)) in ticks.iter().enumerate() {
+            let t = normalize(*value);
+            let angle = dial.start_angle + dial.arc_span * t;
+            scene.add_command(DrawCommand::Tick {
+                cx: dial.cx,
+                cy: dial.cy,
+                r: dial.tick_radius(tick_length),
+                angle,
+                length: tick_length,
+                thickness: major_tick_thickness,
+                color: zone_color(zones, *value, dial_color),
+                style: dial.line_style.1.scaled(dial.scale),
+            });
+            if let Some((next_value, _)) = ticks
+                .get(i + 1)
+                .filter(|(next_value, _)| !dial.breaks_between(*value, *next_value))
+            {
+                let next_t = normalize(*next_value);
+                for j in 1..=minor_ticks_per_interval {
+                    let k = j as f64 / minor_ticks_per_interval as f64;
+                    let minor_angle = dial.start_angle + dial.arc_span * (t + (next_t - t) * k);
+                    let minor_value = value + (next_value - value) * k;
+                    scene.add_command(DrawCommand::Tick {
+                        cx: dial.cx,
+                        cy: dial.cy,
+                        r: dial.tick_radius(minor_tick_length),
+                        angle: minor_angle,
+                        length: minor_tick_length,
+                        thickness: minor_tick_thickness,
+                        color: zone_color(zones, minor_value, dial_color),
+                        style: dial.line_style.2.scaled(dial.scale),
+                    });
+                }
+            }
+            if label.is_empty() {
+                continue;
+            }
+            let label_radius = dial.label_radius(tick_length as f64 + ticks_to_numbers_distance);
+            let (label_x, label_y) = (
+                dial.cx as f64 + angle.cos() * label_radius,
+                dial.cy as f64 + angle.sin() * label_radius,
             );
+            scene.add_command(DrawCommand::Text {
+                x: label_x as i32,
+                y: label_y as i32,
+                text: label.clone(),
+                font_size,
+                color: zone_color(zones, *value, dial_color),
+            });
         }
+    });
+}
+
+// The dial ring, interrupted by a pair of slashes where the scale breaks
+fn add_dial_arc(
+    scene: &mut Scene,
+    dial: &Dial,
+    range: (f64, f64),
+    tick_length: i32,
+    color: (u8, u8, u8),
+) {
+    let arc = |start_angle: f64, arc_span: f64| DrawCommand::Arc {
+        cx: dial.cx,
+        cy: dial.cy,
+        r: dial.r,
+        thickness: dial.thickness,
+        start_angle,
+        arc_span,
+        color,
+        style: dial.line_style.0.scaled(dial.scale),
+        cap: ArcCap::Butt,
+    };
+    let Some(scale_break) = dial
+        .scale_break
+        .filter(|scale_break| scale_break.from > range.0 && scale_break.to < range.1)
+    else {
+        scene.add_command(arc(dial.start_angle, dial.arc_span));
+        return;
+    };
+    let (from, to) = (
+        dial.angle_of(scale_break.from, range),
+        dial.angle_of(scale_break.to, range),
+    );
+    let centre = (from + to) / 2.0;
+    let min_half_gap = (dial.thickness as f64 * 2.0).max(4.0 * dial.scale) / dial.r as f64;
+    let half_gap = ((to - from) / 2.0).max(min_half_gap);
+    let end = dial.start_angle + dial.arc_span;
+    scene.add_command(arc(dial.start_angle, centre - half_gap - dial.start_angle));
+    scene.add_command(arc(centre + half_gap, end - centre - half_gap));
+    // Slashes lean the same way across the ring, like a break in a drawn axis
+    let reach = tick_length as f64 / 3.0;
+    let lean = reach / dial.r as f64;
+    for angle in [centre - half_gap, centre + half_gap] {
+        let point = |radius: f64, angle: f64| {
+            (
+                (dial.cx as f64 + angle.cos() * radius).round() as i32,
+                (dial.cy as f64 + angle.sin() * radius).round() as i32,
+            )
+        };
+        let (x0, y0) = point(dial.r as f64 - reach, angle - lean);
+        let (x1, y1) = point(dial.r as f64 + reach, angle + lean);
+        scene.add_command(DrawCommand::NeedleLine {
+            x0,
+            y0,
+            x1,
+            y1,
+            thickness: dial.thickness as f32,
+            tapered: false,
+            color,
+            tip: None,
+        });
     }
+}
 
-    fn set_chronograph_value(&mut self, value: f64) {
-        if self.chronograph.is_none() {
-            self.chronograph = Some(Needle::new());
+// Screen-space box of a tick label centred at `value`'s label position
+fn label_rect(
+    dial: &Dial,
+    range: (f64, f64),
+    value: f64,
+    radius: f64,
+    label: &str,
+    font_size: f32,
+    shaper: &dyn TextShaper,
+) -> Rect {
+    let angle = dial.angle_of(value, range);
+    Rect::around(
+        (dial.cx as f64 + angle.cos() * radius) as i32,
+        (dial.cy as f64 + angle.sin() * radius) as i32,
+        calculate_text_width(label, shaper, font_size) / 2,
+        (font_size / 2.0) as i32,
+    )
+}
+
+fn labels_overlap(rects: &[Rect]) -> bool {
+    rects.windows(2).any(|pair| pair[0].intersects(&pair[1]))
+}
+
+// Blanks all but every Nth major label, widening N under `label_decimation` until the
+// remaining labels clear each other
+fn thin_labels(ticks: &mut [(f64, String)], dial: &Dial, config: &InstrumentConfig) {
+    let (Some(first), Some(last)) = (ticks.first(), ticks.last()) else {
+        return;
+    };
+    let range = (first.0.min(last.0), first.0.max(last.0));
+    let mut stride = config.label_every.max(1);
+    if config.label_decimation && range.1 > range.0 {
+        let shaper = text_shaper(config);
+        let font_size = config.dial_numbers_font_size * dial.scale as f32;
+        let radius = dial.label_radius(
+            dial.scale * (config.major_tick_length as f64 + config.dial_ticks_to_numbers_distance),
+        );
+        let rects: Vec<Option<Rect>> = ticks
+            .iter()
+            .map(|(value, label)| {
+                (!label.is_empty())
+                    .then(|| label_rect(dial, range, *value, radius, label, font_size, shaper))
+            })
+            .collect();
+        while stride < ticks.len() {
+            let kept: Vec<Rect> = rects.iter().step_by(stride).flatten().copied().collect();
+            if !labels_overlap(&kept) {
+                break;
+            }
+            stride += 1;
         }
-        if let Some(ref mut needle) = self.chronograph {
-            let target_pos = ((value - self.chronograph_range.0)
-                / (self.chronograph_range.1 - self.chronograph_range.0))
-                .clamp(0.0, 1.0);
-            needle.set_target_pos(target_pos);
+    }
+    for (i, (_, label)) in ticks.iter_mut().enumerate() {
+        if i % stride != 0 {
+            label.clear();
         }
     }
+}
 
-    fn set_secondary_chronograph_value(&mut self, value: f64) {
-        if self.secondary_chronograph.is_none() {
-            self.secondary_chronograph = Some(Needle::new());
+// Labels between the major ticks at the minor tick positions, with just enough decimals
+// for the minor step
+fn add_minor_labels(
+    scene: &mut Scene,
+    dial: &Dial,
+    range: (f64, f64),
+    ticks: &[(f64, String)],
+    config: &InstrumentConfig,
+    color: (u8, u8, u8),
+    zones: &[(Zone, f64)],
+) {
+    let per_interval = config.minor_ticks_per_interval;
+    if per_interval < 2 {
+        return;
+    }
+    let shaper = text_shaper(config);
+    let font_size = config.minor_tick_labels_font_size * dial.scale as f32;
+    let radius = dial.label_radius(
+        dial.scale * (config.minor_tick_length as f64 + config.minor_tick_labels_font_size as f64),
+    );
+    let mut labels = Vec::new();
+    for pair in ticks.windows(2) {
+        if dial.breaks_between(pair[0].0, pair[1].0) {
+            continue;
         }
-        if let Some(ref mut needle) = self.secondary_chronograph {
-            let target_pos = ((value - self.secondary_chronograph_range.0)
-                / (self.secondary_chronograph_range.1 - self.secondary_chronograph_range.0))
-                .clamp(0.0, 1.0);
-            needle.set_target_pos(target_pos);
+        let step = (pair[1].0 - pair[0].0) / per_interval as f64;
+        let decimals = (-step.abs().log10().floor()).max(0.0) as usize;
+        for j in 1..per_interval {
+            let value = pair[0].0 + step * j as f64;
+            labels.push((value, format!("{:.*}", decimals, value)));
         }
     }
-
-    fn set_readout_value(&mut self, value: f64) {
-        self.readout_value = Some(value);
-    }
-
-    fn set_highlight_bounds(&mut self, lower: f64, upper: f64) {
-        let (min_bound, max_bound) = (lower.min(upper), lower.max(upper));
-        self.highlight_bounds = Some((min_bound, max_bound));
+    let rects: Vec<Rect> = labels
+        .iter()
+        .map(|(value, label)| label_rect(dial, range, *value, radius, label, font_size, shaper))
+        .collect();
+    if config.label_decimation && labels_overlap(&rects) {
+        return;
     }
-
-    fn update(&mut self) {
-        [
-            &mut self.needle1,
-            &mut self.needle2,
-            &mut self.chronograph,
-            &mut self.secondary_chronograph,
-        ]
-        .iter_mut()
-        .filter_map(|n| n.as_mut())
-        .for_each(|n| n.update_position());
+    for ((value, text), rect) in labels.into_iter().zip(rects) {
+        let (x, y) = rect.center();
+        scene.add_command(DrawCommand::Text {
+            x,
+            y,
+            text,
+            font_size,
+            color: zone_color(zones, value, color),
+        });
     }
+}
 
-    fn update_with_commands(&mut self, receiver: &Receiver<InstrumentCommand>) {
-        // Try to get the latest command without blocking
-        while let Ok(command) = receiver.try_recv() {
-            match command {
-                InstrumentCommand::SetPrimaryNeedle(value) => {
-                    self.set_primary_value(value);
-                }
-                InstrumentCommand::SetSecondaryNeedle(value) => {
-                    self.set_secondary_value(value);
-                }
-                InstrumentCommand::SetReadout(value) => {
-                    self.set_readout_value(value);
-                }
-                InstrumentCommand::SetHighlightBounds(lower, upper) => {
-                    self.set_highlight_bounds(lower, upper);
-                }
-                InstrumentCommand::SetBothNeedles(primary, secondary) => {
-                    self.set_primary_value(primary);
-                    self.set_secondary_value(secondary);
-                }
-                InstrumentCommand::SetChronograph(value) => {
-                    self.set_chronograph_value(value);
-                }
-                InstrumentCommand::SetSecondaryChronograph(value) => {
-                    self.set_secondary_chronograph_value(value);
-                }
-                InstrumentCommand::SetAllNeedles(
-                    primary,
-                    secondary,
-                    chronograph,
-                    secondary_chronograph,
-                ) => {
-                    self.set_primary_value(primary);
-                    self.set_secondary_value(secondary);
-                    self.set_chronograph_value(chronograph);
-                    self.set_secondary_chronograph_value(secondary_chronograph);
-                }
-                InstrumentCommand::SetBothChronographs(chronograph, secondary_chronograph) => {
-                    self.set_chronograph_value(chronograph);
-                    self.set_secondary_chronograph_value(secondary_chronograph);
+fn add_scale_icons(
+    scene: &mut Scene,
+    dial: &Dial,
+    range: (f64, f64),
+    config: &InstrumentConfig,
+    color: (u8, u8, u8),
+) {
+    let radius = dial.label_radius(
+        dial.scale * (config.major_tick_length as f64 + config.dial_ticks_to_numbers_distance),
+    );
+    for icon in &config.scale_icons {
+        let angle = dial.angle_of(icon.value, range);
+        let (x, y) = (
+            (dial.cx as f64 + angle.cos() * radius) as i32,
+            (dial.cy as f64 + angle.sin() * radius) as i32,
+        );
+        let size = icon.size.unwrap_or(config.dial_numbers_font_size) * dial.scale as f32;
+        scene.add_command(match icon.icon {
+            Icon::Glyph(glyph) => DrawCommand::Text {
+                x,
+                y,
+                text: glyph.to_string(),
+                font_size: size,
+                color,
+            },
+            Icon::Bitmap(ref bitmap) => {
+                let height = size.round().max(1.0) as usize;
+                DrawCommand::Image {
+                    x,
+                    y,
+                    width: (height * bitmap.width / bitmap.height.max(1)).max(1),
+                    height,
+                    bitmap: bitmap.clone(),
                 }
             }
-        }
-
-        self.update();
+        });
     }
+}
 
-    fn is_out_of_range(&self) -> bool {
-        // Check if primary needle value is out of range
-        if let Some(ref needle) = self.needle1 {
-            let value = self.min_value + needle.pos * (self.max_value - self.min_value);
-            if value < self.min_value || value > self.max_value {
-                return true;
-            }
+// Factor and offset taking a main scale value to the secondary scale, either as
+// configured or stretching `secondary_range` over the main range
+fn secondary_conversion(
+    (min, max): (f64, f64),
+    secondary_range: Option<(f64, f64)>,
+    configured: Option<(f64, f64)>,
+) -> Option<(f64, f64)> {
+    match secondary_range {
+        Some((low, high)) => {
+            let factor = (high - low) / (max - min);
+            Some((factor, low - min * factor))
         }
-        // Check if secondary needle value is out of range
-        if let Some(ref needle) = self.needle2 {
-            let value = self.min_value + needle.pos * (self.max_value - self.min_value);
-            if value < self.min_value || value > self.max_value {
-                return true;
-            }
-        }
-        false
-    }
-
-    fn set_highlight_override(&mut self, lower: f64, upper: f64) {
-        let (min_bound, max_bound) = (lower.min(upper), lower.max(upper));
-        self.highlight_bounds = Some((min_bound, max_bound));
+        None => configured,
     }
 }
 
-struct Dial {
-    cx: i32,
-    cy: i32,
-    r: i32,
-    thickness: i32,
-    arc_span: f64,
-    start_angle: f64,
-}
-
-impl Dial {
-    fn new(width: usize, height: usize, config: &InstrumentConfig) -> Self {
-        let cx = width as i32 / 2;
-        let cy = height as i32 / 2;
-        let r = (width.min(height) as i32) / 2 - config.dial_margin;
-        let arc_span = std::f64::consts::PI * 1.5;
-        let start_angle = std::f64::consts::FRAC_PI_2;
-        Self {
-            cx,
-            cy,
-            r,
-            thickness: config.dial_thickness,
-            arc_span,
-            start_angle,
+// Converted labels at nice values of the secondary unit, placed where those values fall
+// on the primary scale
+fn add_secondary_scale(
+    scene: &mut Scene,
+    dial: &Dial,
+    range: (f64, f64),
+    config: &InstrumentConfig,
+) {
+    let configured = config
+        .secondary_scale_factor
+        .map(|factor| (factor, config.secondary_scale_offset));
+    let Some((factor, offset)) = secondary_conversion(range, config.secondary_range, configured)
+    else {
+        return;
+    };
+    let converted = (range.0 * factor + offset, range.1 * factor + offset);
+    // The converted labels clear the primary labels on whichever side those sit
+    let primary_inset = config.major_tick_length as f64 + config.dial_ticks_to_numbers_distance;
+    let offset_from_arc = match (config.secondary_scale_placement, dial.tick_placement) {
+        (ScalePlacement::Inside, ScalePlacement::Inside) => {
+            -(primary_inset + config.secondary_scale_distance)
         }
-    }
-
-    fn new_chronograph(width: usize, height: usize, config: &InstrumentConfig) -> Self {
-        // Create a smaller dial for the chronograph
-        let r = ((width.min(height) as f64) / config.chronograph_dial_size) as i32
-            - config.chronograph_dial_margin;
-        let cx = width as i32 / 2; // Center horizontally
-        let cy = r + config.chronograph_dial_margin + config.chronograph_dial_shift; // Position in top middle
-        let arc_span = std::f64::consts::PI * 1.5;
-        let start_angle = std::f64::consts::FRAC_PI_2;
-        Self {
-            cx,
-            cy,
-            r,
-            thickness: config.chronograph_dial_thickness,
-            arc_span,
-            start_angle,
+        (ScalePlacement::Inside, ScalePlacement::Outside) => -config.secondary_scale_distance,
+        (ScalePlacement::Outside, ScalePlacement::Inside) => config.secondary_scale_distance,
+        (ScalePlacement::Outside, ScalePlacement::Outside) => {
+            primary_inset + config.secondary_scale_distance
         }
+    };
+    let radius = dial.r as f64 + dial.scale * offset_from_arc;
+    let ticks = relabel(
+        nice_ticks(converted, config.secondary_scale_ticks_target),
+        config.number_format,
+    );
+    for (value, label) in ticks {
+        let primary = (value - offset) / factor;
+        let angle = dial.angle_of(primary, range);
+        let text = match config.secondary_scale_decimals {
+            Some(decimals) => format!("{:.*}", decimals, value),
+            None => label,
+        };
+        scene.add_command(DrawCommand::Text {
+            x: (dial.cx as f64 + angle.cos() * radius) as i32,
+            y: (dial.cy as f64 + angle.sin() * radius) as i32,
+            text,
+            font_size: config.secondary_scale_font_size * dial.scale as f32,
+            color: config.secondary_scale_color.as_tuple(),
+        });
     }
+}
 
-    fn new_secondary_chronograph(width: usize, height: usize, config: &InstrumentConfig) -> Self {
-        // Create a smaller dial for the secondary chronograph
-        let r = ((width.min(height) as f64) / config.secondary_chronograph_dial_size) as i32
-            - config.secondary_chronograph_dial_margin;
-        let cx = width as i32 / 2; // Center horizontally
-        let cy = (height as i32 / 2) + config.secondary_chronograph_dial_shift; // Position below center
-        let arc_span = std::f64::consts::PI * 1.5;
-        let start_angle = std::f64::consts::FRAC_PI_2;
-        Self {
-            cx,
-            cy,
-            r,
-            thickness: config.secondary_chronograph_dial_thickness,
-            arc_span,
-            start_angle,
+fn add_needle(
+    scene: &mut Scene,
+    dial: &Dial,
+    needle: &Needle,
+    color: (u8, u8, u8),
+    length_factor: f64,
+    width: f32,
+    back_length: f64,
+    dot_radius: i32,
+    tip: Option<NeedleTip>,
+    shape: NeedleShape,
+) {
+    let (width, back_length) = (width * dial.scale as f32, back_length * dial.scale);
+    let tip = tip.map(|tip| NeedleTip {
+        thickness: tip.thickness * dial.scale as f32,
+        ..tip
+    });
+    let dot_radius = (dot_radius as f64 * dial.scale).round() as i32;
+    let angle = dial.start_angle + dial.arc_span * needle.pos;
+    let (nx, ny) = (
+        (dial.cx as f64 + angle.cos() * dial.r as f64 * length_factor) as i32,
+        (dial.cy as f64 + angle.sin() * dial.r as f64 * length_factor) as i32,
+    );
+    let (back_x, back_y) = (
+        (dial.cx as f64 - angle.cos() * back_length) as i32,
+        (dial.cy as f64 - angle.sin() * back_length) as i32,
+    );
+    match shape {
+        NeedleShape::Line => {
+            scene.add_command(DrawCommand::NeedleLine {
+                x0: dial.cx,
+                y0: dial.cy,
+                x1: nx,
+                y1: ny,
+                thickness: width,
+                tapered: true,
+                color,
+                tip,
+            });
+            scene.add_command(DrawCommand::NeedleLine {
+                x0: dial.cx,
+                y0: dial.cy,
+                x1: back_x,
+                y1: back_y,
+                thickness: width,
+                tapered: false,
+                color,
+                tip: None,
+            });
+        }
+        NeedleShape::Sword => {
+            // The blade narrows linearly from the pivot to each point, so a tip colour
+            // only has to repaint the last stretch of the same outline
+            let point = |distance: f64, across: f64| needle_point(dial, angle, distance, across);
+            let reach = dial.r as f64 * length_factor;
+            scene.add_command(DrawCommand::Polygon {
+                points: vec![
+                    point(-back_length, 0.0),
+                    point(0.0, width as f64),
+                    point(reach, 0.0),
+                    point(0.0, -width as f64),
+                ],
+                color,
+            });
+            if let Some(tip) = tip {
+                let fraction = tip.fraction.clamp(0.0, 1.0);
+                let half_width = width as f64 * fraction;
+                let join = reach * (1.0 - fraction);
+                scene.add_command(DrawCommand::Polygon {
+                    points: vec![
+                        point(join, half_width),
+                        point(reach, 0.0),
+                        point(join, -half_width),
+                    ],
+                    color: tip.color,
+                });
+            }
         }
+        NeedleShape::Arrow => {
+            let point = |distance: f64, across: f64| needle_point(dial, angle, distance, across);
+            let reach = dial.r as f64 * length_factor;
+            let head_length = (width as f64 * 4.0).min(reach);
+            let (base_x, base_y) = point(reach - head_length, 0.0);
+            scene.add_command(DrawCommand::NeedleLine {
+                x0: back_x,
+                y0: back_y,
+                x1: base_x.round() as i32,
+                y1: base_y.round() as i32,
+                thickness: width,
+                tapered: false,
+                color,
+                tip: None,
+            });
+            scene.add_command(DrawCommand::Polygon {
+                points: vec![
+                    point(reach - head_length, width as f64 * 1.5),
+                    point(reach, 0.0),
+                    point(reach - head_length, -width as f64 * 1.5),
+                ],
+                color: tip.map_or(color, |tip| tip.color),
+            });
+        }
+    }
+    if dot_radius > 0 {
+        scene.add_command(DrawCommand::Circle {
+            cx: dial.cx,
+            cy: dial.cy,
+            radius: dot_radius,
+            color,
+        });
     }
 }
 
-struct Needle {
-    pos: f64, // Normalized [0,1]
-    target_pos: f64,
+// A point `distance` out from the pivot along a needle at `angle`, and `across` to the
+// needle's right
+fn needle_point(dial: &Dial, angle: f64, distance: f64, across: f64) -> (f64, f64) {
+    let (sin, cos) = angle.sin_cos();
+    (
+        dial.cx as f64 + cos * distance - sin * across,
+        dial.cy as f64 + sin * distance + cos * across,
+    )
 }
 
-impl Needle {
-    fn new() -> Self {
-        Self {
-            pos: 0.5,
-            target_pos: 0.5,
-        }
-    }
+// A notched block sitting inside the rim at `angle`, for the needle to line up with
+fn add_target_bug(scene: &mut Scene, dial: &Dial, angle: f64, config: &InstrumentConfig) {
+    let size = config.target_bug_size as f64 * dial.scale;
+    let outer = dial.r as f64 + dial.thickness as f64 / 2.0;
+    let inner = outer - size * 0.75;
+    let point = |distance: f64, across: f64| needle_point(dial, angle, distance, across);
+    scene.add_command(DrawCommand::Polygon {
+        points: vec![
+            point(outer, -size / 2.0),
+            point(outer, size / 2.0),
+            point(inner, size / 2.0),
+            point(inner + size * 0.35, 0.0),
+            point(inner, -size / 2.0),
+        ],
+        color: config.target_bug_color.as_tuple(),
+    });
+}
 
-    fn set_target_pos(&mut self, target: f64) {
-        self.target_pos = target.clamp(0.0, 1.0);
+fn add_hub(scene: &mut Scene, dial: &Dial, config: &InstrumentConfig, radius: i32) {
+    let radius = (radius as f64 * dial.scale).round() as i32;
+    if radius <= 0 {
+        return;
     }
-
-    fn update_position(&mut self) {
-        self.pos = lerp(self.pos, self.target_pos).clamp(0.0, 1.0);
+    let ring = (config.hub_ring_width as f64 * dial.scale).round() as i32;
+    let fill_radius = match config.hub_ring_color {
+        Some(ring_color) if ring > 0 => {
+            scene.add_command(DrawCommand::Circle {
+                cx: dial.cx,
+                cy: dial.cy,
+                radius,
+                color: ring_color.as_tuple(),
+            });
+            radius - ring
+        }
+        _ => radius,
+    };
+    if fill_radius > 0 {
+        scene.add_command(DrawCommand::Circle {
+            cx: dial.cx,
+            cy: dial.cy,
+            radius: fill_radius,
+            color: config.hub_color.as_tuple(),
+        });
+    }
+    if let Some(ref bitmap) = config.hub_image {
+        let size = (fill_radius.max(1) * 2) as usize;
+        scene.add_command(DrawCommand::Image {
+            x: dial.cx,
+            y: dial.cy,
+            width: size,
+            height: size,
+            bitmap: bitmap.clone(),
+        });
     }
 }
 
@@ -969,421 +10586,392 @@ impl Needle {
 // HELPER FUNCTIONS
 // ============================================================================
 
-// ============================================================================
-// RENDERING AND DRAWING FUNCTIONS
-// ============================================================================
-
-fn render_instrument(canvas: &mut Canvas, state: &AppState, config: &InstrumentConfig) {
-    let mut scene = Scene::new(canvas.width, canvas.height);
-    scene.add_command(DrawCommand::Clear((0xff, 0xff, 0xff)));
-
-    let dial = Dial::new(canvas.width, canvas.height, config);
-    let is_out_of_range = state.is_out_of_range();
-    let base_color = if is_out_of_range {
-        (0xff, 0x00, 0x00)
+fn calculate_text_width(text: &str, shaper: &dyn TextShaper, font_size: f32) -> i32 {
+    let glyphs = shaper.shape(text, font_size);
+    let (min_x, max_x, _, _) = glyph_bounds(&glyphs);
+    if min_x < max_x {
+        max_x - min_x
     } else {
-        (0x00, 0x00, 0x00)
-    };
-    let range = (state.min_value, state.max_value);
-
-    // Add highlight band if needed
-    if let Some(highlight) = state.highlight_bounds {
-        let (hl_start, hl_end) = highlight;
-        let (norm_hl_start, norm_hl_end) = (
-            ((hl_start - range.0) / (range.1 - range.0)).clamp(0.0, 1.0),
-            ((hl_end - range.0) / (range.1 - range.0)).clamp(0.0, 1.0),
-        );
-        scene.add_command(DrawCommand::HighlightBand {
-            cx: dial.cx,
-            cy: dial.cy,
-            r: dial.r,
-            start_angle: dial.start_angle + dial.arc_span * norm_hl_start,
-            end_angle: dial.start_angle + dial.arc_span * norm_hl_end,
-            inner_radius: config.highlight_band_width as f64,
-            outer_radius: 0.0,
-        });
+        0
     }
+}
 
-    // Main dial with ticks and labels
-    add_dial_with_ticks(
-        &mut scene,
-        &dial,
-        range,
-        config.ticks_count,
-        config.major_tick_length,
-        config.major_tick_thickness,
-        config.minor_tick_thickness,
-        config.minor_ticks_per_interval,
-        config.minor_tick_length,
-        config.dial_numbers_font_size,
-        config.dial_ticks_to_numbers_distance,
-        base_color,
-    );
+// Top and bottom of the inked rows, measured down from the baseline
+fn text_ink_rows(text: &str, shaper: &dyn TextShaper, font_size: f32) -> (i32, i32) {
+    let glyphs = shaper.shape(text, font_size);
+    let (_, _, min_y, max_y) = glyph_bounds(&glyphs);
+    if min_y < max_y {
+        (min_y, max_y)
+    } else {
+        (0, 0)
+    }
+}
 
-    // Curved text
-    scene.add_command(DrawCommand::CurvedText {
-        cx: dial.cx,
-        cy: dial.cy,
-        radius: dial.r as f64 + config.curved_text_radius_offset,
-        text: config.curved_text.to_string(),
-        font_size: config.curved_text_font_size,
-        arc_span: config.curved_text_arc_span,
-        start_angle: config.curved_text_angle,
-        color: base_color,
-    });
+/// Evenly divides the range into `ticks_count - 1` intervals, labelled with rounded integers
+fn even_ticks(range: (f64, f64), ticks_count: usize) -> Vec<(f64, String)> {
+    (0..ticks_count)
+        .map(|i| {
+            let t = i as f64 / (ticks_count as f64 - 1.0);
+            let value = range.0 + t * (range.1 - range.0);
+            (value, format!("{}", value.round() as i64))
+        })
+        .collect()
+}
 
-    // Needles
-    if let Some(ref needle) = state.needle1 {
-        let color = if is_out_of_range {
-            (0xff, 0x00, 0x00)
-        } else {
-            (0x00, 0x00, 0x00)
-        };
-        add_needle(
-            &mut scene,
-            &dial,
-            needle,
-            color,
-            config.needle_length_factor,
-            config.needle_width,
-            config.needle_back_length,
-            config.dot_radius,
-        );
-    }
-    if let Some(ref needle) = state.needle2 {
-        let color = if is_out_of_range {
-            (0xff, 0x00, 0x00)
-        } else {
-            (0x00, 0x7f, 0xff)
-        };
-        add_needle(
-            &mut scene,
-            &dial,
-            needle,
-            color,
-            config.needle_length_factor,
-            config.needle_width,
-            config.needle_back_length,
-            config.dot_radius,
-        );
+/// Evenly spaced subdial ticks, labelled by `formatter` on every `label_every`th tick, or
+/// left blank when that is `None`
+fn subdial_ticks(
+    range: (f64, f64),
+    ticks_count: usize,
+    formatter: Option<&LabelFormatter>,
+    label_every: Option<usize>,
+) -> Vec<(f64, String)> {
+    let mut ticks = even_ticks(range, ticks_count);
+    for (i, (value, label)) in ticks.iter_mut().enumerate() {
+        match label_every {
+            Some(every) if i % every.max(1) == 0 => {
+                if let Some(formatter) = formatter {
+                    *label = (formatter.0)(*value);
+                }
+            }
+            _ => label.clear(),
+        }
     }
+    ticks
+}
 
-    // Chronograph
-    if let Some(ref needle) = state.chronograph {
-        let color = if is_out_of_range {
-            (0xff, 0x00, 0x00)
-        } else {
-            (0xff, 0x80, 0x00)
-        };
-        let chrono_dial = Dial::new_chronograph(canvas.width, canvas.height, config);
-        add_dial_with_ticks(
-            &mut scene,
-            &chrono_dial,
-            state.chronograph_range,
-            config.chronograph_ticks_count,
-            config.chronograph_tick_length,
-            config.chronograph_major_tick_thickness,
-            config.chronograph_minor_tick_thickness,
-            config.chronograph_minor_ticks_per_interval,
-            config.chronograph_minor_tick_length,
-            config.chronograph_dial_numbers_font_size,
-            config.chronograph_dial_ticks_to_numbers_distance,
-            (0x00, 0x00, 0x00),
-        );
-        add_needle(
-            &mut scene,
-            &chrono_dial,
-            needle,
-            color,
-            config.chronograph_needle_length_factor,
-            config.chronograph_needle_width,
-            config.chronograph_needle_back_length,
-            config.chronograph_dial_dot_radius,
-        );
-    }
+/// Rounds a raw step up to the nearest 1, 2 or 5 × 10ⁿ
+fn nice_step(span: f64, target_intervals: usize) -> f64 {
+    let raw = span.abs() / target_intervals.max(1) as f64;
+    let magnitude = 10f64.powf(raw.log10().floor());
+    let residual = raw / magnitude;
+    let nice = if residual <= 1.0 {
+        1.0
+    } else if residual <= 2.0 {
+        2.0
+    } else if residual <= 5.0 {
+        5.0
+    } else {
+        10.0
+    };
+    nice * magnitude
+}
 
-    // Secondary chronograph
-    if let Some(ref needle) = state.secondary_chronograph {
-        let color = if is_out_of_range {
-            (0xff, 0x00, 0x00)
-        } else {
-            (0x00, 0x80, 0xff)
-        };
-        let sec_chrono_dial = Dial::new_secondary_chronograph(canvas.width, canvas.height, config);
-        add_dial_with_ticks(
-            &mut scene,
-            &sec_chrono_dial,
-            state.secondary_chronograph_range,
-            config.secondary_chronograph_ticks_count,
-            config.secondary_chronograph_tick_length,
-            config.secondary_chronograph_major_tick_thickness,
-            config.secondary_chronograph_minor_tick_thickness,
-            config.secondary_chronograph_minor_ticks_per_interval,
-            config.secondary_chronograph_minor_tick_length,
-            config.secondary_chronograph_dial_numbers_font_size,
-            config.secondary_chronograph_dial_ticks_to_numbers_distance,
-            (0x00, 0x00, 0x00),
-        );
-        add_needle(
-            &mut scene,
-            &sec_chrono_dial,
-            needle,
-            color,
-            config.secondary_chronograph_needle_length_factor,
-            config.secondary_chronograph_needle_width,
-            config.secondary_chronograph_needle_back_length,
-            config.secondary_chronograph_dial_dot_radius,
-        );
+/// Ticks at multiples of a "nice" step that fall inside the range, labelled with
+/// just enough decimals to tell neighbouring ticks apart
+fn nice_ticks(range: (f64, f64), target_intervals: usize) -> Vec<(f64, String)> {
+    let (min, max) = (range.0.min(range.1), range.0.max(range.1));
+    let step = nice_step(max - min, target_intervals);
+    if !step.is_finite() || step <= 0.0 {
+        return even_ticks(range, 2);
     }
+    let decimals = (-step.log10().floor()).max(0.0) as usize;
+    let first = (min / step).ceil() as i64;
+    let last = (max / step + 1e-9).floor() as i64;
+    (first..=last)
+        .map(|i| {
+            let value = i as f64 * step;
+            (value, format!("{:.*}", decimals, value))
+        })
+        .collect()
+}
 
-    // Readout
-    if let Some(value) = state.readout_value {
-        let (value_int, value_frac) = (
-            value.trunc() as i32,
-            ((value.fract() * 1000.0).round() as u32).min(999),
-        );
-        let (label_x, label_y) = (
-            (canvas.width as f64 * config.readout_x_factor) as i32,
-            (canvas.height as f64 * config.readout_y_factor) as i32,
-        );
-        let value_str = format!("{}", value_int);
-        scene.add_command(DrawCommand::Text {
-            x: label_x,
-            y: label_y,
-            text: value_str.clone(),
-            font_size: config.readout_big_font_size,
-            color: base_color,
-        });
+/// Uses the caller's tick list as-is, falling back to the plain value for unlabelled ticks
+fn explicit_ticks(ticks: &[Tick]) -> Vec<(f64, String)> {
+    let mut ticks: Vec<(f64, String)> = ticks
+        .iter()
+        .map(|tick| {
+            let label = tick
+                .label
+                .clone()
+                .unwrap_or_else(|| format!("{}", tick.value));
+            (tick.value, label)
+        })
+        .collect();
+    ticks.sort_by(|a, b| a.0.total_cmp(&b.0));
+    ticks
+}
 
-        let font = Font::try_from_vec(config.font_data.to_vec()).expect("Error loading font");
-        let int_width = calculate_text_width(
-            &value_str,
-            &font,
-            Scale::uniform(config.readout_big_font_size),
-        );
-        let (frac_x, frac_y) = (label_x + int_width / 2 + 28, label_y + 2);
-        scene.add_command(DrawCommand::Text {
-            x: frac_x,
-            y: frac_y,
-            text: format!("{:03}", value_frac),
-            font_size: config.readout_small_font_size,
-            color: base_color,
-        });
+fn lerp(current: f64, target: f64, factor: f64) -> f64 {
+    current + (target - current) * factor
+}
 
-        // Readout box
-        let (box_padding, box_thickness) = (
-            config.readout_box_padding,
-            config.readout_box_thickness as f32,
-        );
-        let font_size = (config.readout_big_font_size / 11.0) as i32;
-        let (box_left, box_top, box_right, box_bottom) = (
-            label_x - box_padding - font_size * value_str.len() as i32,
-            label_y - box_padding,
-            frac_x + box_padding + 5,
-            frac_y + box_padding,
-        );
-        for (x0, y0, x1, y1) in [
-            (box_left, box_top, box_right, box_top),
-            (box_left, box_bottom, box_right, box_bottom),
-            (box_left, box_top, box_left, box_bottom),
-            (box_right, box_top, box_right, box_bottom),
-        ] {
-            scene.add_command(DrawCommand::NeedleLine {
-                x0,
-                y0,
-                x1,
-                y1,
-                thickness: box_thickness,
-                tapered: false,
-                color: base_color,
-            });
-        }
-    }
+// ============================================================================
+// TEXT SHAPING
+// ============================================================================
 
-    // Warning indicator
-    if is_out_of_range {
-        scene.add_command(DrawCommand::Text {
-            x: dial.cx,
-            y: dial.cy - (dial.r / 4),
-            text: "!".to_string(),
-            font_size: config.exclamation_mark_size,
-            color: (0xff, 0x00, 0x00),
-        });
-    }
+/// A positioned glyph bitmap. `left`/`top` are pixel offsets of the bitmap from the
+/// start of the line's baseline (y grows downwards); `pen_x` is the kerned pen position.
+struct ShapedGlyph {
+    pen_x: f32,
+    advance: f32,
+    left: i32,
+    top: i32,
+    width: usize,
+    height: usize,
+    coverage: Vec<u8>,
+}
 
-    scene.render(canvas, config);
+/// Turns a string into positioned, rasterized glyphs for a single line of text
+trait TextShaper {
+    fn shape(&self, text: &str, font_size: f32) -> Vec<ShapedGlyph>;
 }
 
-// Helper functions to reduce repetitive rendering code
-fn add_dial_with_ticks(
-    scene: &mut Scene,
-    dial: &Dial,
-    range: (f64, f64),
-    ticks_count: usize,
-    tick_length: i32,
-    major_tick_thickness: f32,
-    minor_tick_thickness: f32,
-    minor_ticks_per_interval: usize,
-    minor_tick_length: i32,
-    font_size: f32,
-    ticks_to_numbers_distance: f64,
-    dial_color: (u8, u8, u8),
-) {
-    scene.add_command(DrawCommand::Arc {
-        cx: dial.cx,
-        cy: dial.cy,
-        r: dial.r,
-        thickness: dial.thickness,
-        start_angle: dial.start_angle,
-        arc_span: dial.arc_span,
-        color: dial_color,
-    });
-    for i in 0..ticks_count {
-        let t = i as f64 / (ticks_count as f64 - 1.0);
-        let angle = dial.start_angle + dial.arc_span * t;
-        scene.add_command(DrawCommand::Tick {
-            cx: dial.cx,
-            cy: dial.cy,
-            r: dial.r,
-            angle,
-            length: tick_length,
-            thickness: major_tick_thickness,
-            color: dial_color,
-        });
-        if i < ticks_count - 1 {
-            for j in 1..=minor_ticks_per_interval {
-                let minor_angle = dial.start_angle
-                    + dial.arc_span
-                        * (t + (j as f64
-                            / (minor_ticks_per_interval as f64 * (ticks_count as f64 - 1.0))));
-                scene.add_command(DrawCommand::Tick {
-                    cx: dial.cx,
-                    cy: dial.cy,
-                    r: dial.r,
-                    angle: minor_angle,
-                    length: minor_tick_length,
-                    thickness: minor_tick_thickness,
-                    color: dial_color,
-                });
+type GlyphCache = HashMap<(usize, u16, u32), (fontdue::Metrics, Vec<u8>)>;
+
+/// Rasterized glyphs kept before the cache starts over. Fitted text, `ui_scale` and
+/// resizes all produce new font sizes, which would otherwise pile up forever.
+const GLYPH_CACHE_CAPACITY: usize = 4096;
+
+struct ShaperFont {
+    raster: fontdue::Font,
+    face: rustybuzz::Face<'static>,
+}
+
+/// Shapes with rustybuzz (ligatures, Arabic joining, GPOS kerning) after splitting the
+/// line into bidi runs in visual order, then rasterizes glyph ids with fontdue. Runs the
+/// primary font can't cover fall back to the first fallback font that can.
+struct FontdueShaper {
+    fonts: Vec<ShaperFont>,
+    glyphs: Mutex<GlyphCache>,
+}
+
+impl FontdueShaper {
+    fn new(font_data: &[&'static [u8]]) -> Result<Self, &'static str> {
+        let fonts = font_data
+            .iter()
+            .map(|data| {
+                Ok(ShaperFont {
+                    raster: fontdue::Font::from_bytes(*data, fontdue::FontSettings::default())?,
+                    face: rustybuzz::Face::from_slice(data, 0).ok_or("Unsupported font face")?,
+                })
+            })
+            .collect::<Result<Vec<_>, &'static str>>()?;
+        Ok(Self {
+            fonts,
+            glyphs: Mutex::new(HashMap::new()),
+        })
+    }
+
+    // Splits a run where font coverage changes, as (byte range, font index) in logical
+    // order. Characters stay with the current font while it covers them, so whitespace
+    // and combining marks don't break a run.
+    fn font_runs(&self, run: &str) -> Vec<(std::ops::Range<usize>, usize)> {
+        let mut runs: Vec<(std::ops::Range<usize>, usize)> = Vec::new();
+        for (i, c) in run.char_indices() {
+            let end = i + c.len_utf8();
+            let covers = |font: usize| self.fonts[font].raster.lookup_glyph_index(c) != 0;
+            match runs.last_mut() {
+                Some((range, font)) if c.is_whitespace() || c.is_control() || covers(*font) => {
+                    range.end = end;
+                }
+                _ => {
+                    let font = (0..self.fonts.len()).find(|&f| covers(f)).unwrap_or(0);
+                    match runs.last_mut() {
+                        Some((range, last)) if *last == font => range.end = end,
+                        _ => runs.push((i..end, font)),
+                    }
+                }
             }
         }
-        let label_radius = dial.r as f64 - tick_length as f64 - ticks_to_numbers_distance;
-        let (label_x, label_y) = (
-            dial.cx as f64 + angle.cos() * label_radius,
-            dial.cy as f64 + angle.sin() * label_radius,
-        );
-        scene.add_command(DrawCommand::Text {
-            x: label_x as i32,
-            y: label_y as i32,
-            text: format!("{}", (range.0 + t * (range.1 - range.0)).round() as i64),
-            font_size,
-            color: dial_color,
-        });
+        runs
     }
 }
 
-fn add_needle(
-    scene: &mut Scene,
-    dial: &Dial,
-    needle: &Needle,
-    color: (u8, u8, u8),
-    length_factor: f64,
-    width: f32,
-    back_length: f64,
-    dot_radius: i32,
-) {
-    let angle = dial.start_angle + dial.arc_span * needle.pos;
-    let (nx, ny) = (
-        (dial.cx as f64 + angle.cos() * dial.r as f64 * length_factor) as i32,
-        (dial.cy as f64 + angle.sin() * dial.r as f64 * length_factor) as i32,
-    );
-    let (back_x, back_y) = (
-        (dial.cx as f64 - angle.cos() * back_length) as i32,
-        (dial.cy as f64 - angle.sin() * back_length) as i32,
-    );
-    scene.add_command(DrawCommand::NeedleLine {
-        x0: dial.cx,
-        y0: dial.cy,
-        x1: nx,
-        y1: ny,
-        thickness: width,
-        tapered: true,
-        color,
-    });
-    scene.add_command(DrawCommand::NeedleLine {
-        x0: dial.cx,
-        y0: dial.cy,
-        x1: back_x,
-        y1: back_y,
-        thickness: width,
-        tapered: false,
-        color,
-    });
-    scene.add_command(DrawCommand::Circle {
-        cx: dial.cx,
-        cy: dial.cy,
-        radius: dot_radius,
-        color,
-    });
+impl TextShaper for FontdueShaper {
+    fn shape(&self, text: &str, font_size: f32) -> Vec<ShapedGlyph> {
+        use unicode_bidi::BidiInfo;
+
+        let mut cache = self.glyphs.lock().unwrap_or_else(|e| e.into_inner());
+        let mut pen_x = 0.0;
+        let mut shaped = Vec::with_capacity(text.len());
+        let bidi = BidiInfo::new(text, None);
+        for paragraph in &bidi.paragraphs {
+            let (levels, runs) = bidi.visual_runs(paragraph, paragraph.range.clone());
+            for run in runs {
+                let run_text = &text[run.clone()];
+                let rtl = levels[run.start].is_rtl();
+                let mut font_runs = self.font_runs(run_text);
+                if rtl {
+                    font_runs.reverse();
+                }
+                for (range, font_index) in font_runs {
+                    let font = &self.fonts[font_index];
+                    let scale = font_size / font.face.units_per_em() as f32;
+
+                    let mut buffer = rustybuzz::UnicodeBuffer::new();
+                    buffer.push_str(&run_text[range]);
+                    buffer.set_direction(if rtl {
+                        rustybuzz::Direction::RightToLeft
+                    } else {
+                        rustybuzz::Direction::LeftToRight
+                    });
+                    let output = rustybuzz::shape(&font.face, &[], buffer);
+
+                    for (info, position) in
+                        output.glyph_infos().iter().zip(output.glyph_positions())
+                    {
+                        let glyph_id = info.glyph_id as u16;
+                        let key = (font_index, glyph_id, font_size.to_bits());
+                        if cache.len() >= GLYPH_CACHE_CAPACITY && !cache.contains_key(&key) {
+                            cache.clear();
+                        }
+                        let (metrics, coverage) = cache
+                            .entry(key)
+                            .or_insert_with(|| font.raster.rasterize_indexed(glyph_id, font_size));
+                        let glyph_x = pen_x + position.x_offset as f32 * scale;
+                        let glyph_y = position.y_offset as f32 * scale;
+                        let advance = position.x_advance as f32 * scale;
+                        shaped.push(ShapedGlyph {
+                            pen_x: glyph_x,
+                            advance,
+                            left: (glyph_x + metrics.xmin as f32).round() as i32,
+                            top: -(metrics.ymin + metrics.height as i32) - glyph_y.round() as i32,
+                            width: metrics.width,
+                            height: metrics.height,
+                            coverage: coverage.clone(),
+                        });
+                        pen_x += advance;
+                    }
+                }
+            }
+        }
+        shaped
+    }
 }
 
-// ============================================================================
-// HELPER FUNCTIONS
-// ============================================================================
+type ShaperRegistry = Mutex<Vec<(Vec<usize>, &'static FontdueShaper)>>;
+
+// Parsed fonts live for the whole process, keyed by the addresses of their static data
+fn text_shaper(config: &InstrumentConfig) -> &'static FontdueShaper {
+    try_text_shaper(config).expect("Error loading font")
+}
+
+fn try_text_shaper(config: &InstrumentConfig) -> Result<&'static FontdueShaper, InstrumentError> {
+    static SHAPERS: OnceLock<ShaperRegistry> = OnceLock::new();
+    let mut shapers = SHAPERS
+        .get_or_init(|| Mutex::new(Vec::new()))
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    let font_data: Vec<&'static [u8]> = std::iter::once(config.font_data)
+        .chain(config.fallback_fonts.iter().copied())
+        .collect();
+    let key: Vec<usize> = font_data
+        .iter()
+        .map(|data| data.as_ptr() as usize)
+        .collect();
+    if let Some((_, shaper)) = shapers.iter().find(|(k, _)| *k == key) {
+        return Ok(shaper);
+    }
+    let shaper = FontdueShaper::new(&font_data).map_err(InstrumentError::Font)?;
+    let shaper: &'static FontdueShaper = Box::leak(Box::new(shaper));
+    shapers.push((key, shaper));
+    Ok(shaper)
+}
 
-fn calculate_text_width(text: &str, font: &Font, scale: Scale) -> i32 {
-    use rusttype::{point, PositionedGlyph};
-    let glyphs: Vec<PositionedGlyph> = font.layout(text, scale, point(0.0, 0.0)).collect();
-    let (min_x, max_x, _, _) = glyphs.iter().filter_map(|g| g.pixel_bounding_box()).fold(
+// Pixel bounding box (min_x, max_x, min_y, max_y) of the inked glyphs in a line
+fn glyph_bounds(glyphs: &[ShapedGlyph]) -> (i32, i32, i32, i32) {
+    glyphs.iter().filter(|g| g.width > 0 && g.height > 0).fold(
         (i32::MAX, i32::MIN, i32::MAX, i32::MIN),
-        |(min_x, max_x, min_y, max_y), bb| {
+        |(min_x, max_x, min_y, max_y), g| {
             (
-                min_x.min(bb.min.x),
-                max_x.max(bb.max.x),
-                min_y.min(bb.min.y),
-                max_y.max(bb.max.y),
+                min_x.min(g.left),
+                max_x.max(g.left + g.width as i32),
+                min_y.min(g.top),
+                max_y.max(g.top + g.height as i32),
             )
         },
-    );
-    if min_x < max_x {
-        max_x - min_x
-    } else {
-        0
-    }
-}
-
-fn lerp(current: f64, target: f64) -> f64 {
-    current + (target - current) * 0.1 // Default lerp factor for general animations
+    )
 }
 
 // ============================================================================
 // DRAWING PRIMITIVES
 // ============================================================================
 
-fn set_pixel(frame: &mut [u8], width: usize, x: usize, y: usize, r: u8, g: u8, b: u8, alpha: f32) {
-    if x < width && y < frame.len() / (width * 4) {
-        let idx = (y * width + x) * 4;
-        let src = [r as f32, g as f32, b as f32, 255.0 * alpha];
-        let dst = [
-            frame[idx] as f32,
-            frame[idx + 1] as f32,
-            frame[idx + 2] as f32,
-            frame[idx + 3] as f32,
-        ];
-        let a = src[3] / 255.0;
-        let out = [
-            (src[0] * a + dst[0] * (1.0 - a)).round() as u8,
-            (src[1] * a + dst[1] * (1.0 - a)).round() as u8,
-            (src[2] * a + dst[2] * (1.0 - a)).round() as u8,
-            0xff,
-        ];
-        frame[idx..idx + 4].copy_from_slice(&out);
+// Nearest-neighbour scaled, alpha-blended blit centred on (x, y)
+fn draw_bitmap(canvas: &mut Canvas, x: i32, y: i32, width: usize, height: usize, bitmap: &Bitmap) {
+    if bitmap.width == 0
+        || bitmap.height == 0
+        || bitmap.pixels.len() < bitmap.width * bitmap.height * 4
+    {
+        return;
+    }
+    let (left, top) = (x - width as i32 / 2, y - height as i32 / 2);
+    let columns: Vec<Vec<(usize, f32)>> = (0..width)
+        .map(|dx| bitmap_footprint(dx, width, bitmap.width))
+        .collect();
+    for dy in 0..height {
+        let py = top + dy as i32;
+        if py < 0 || py >= canvas.height as i32 {
+            continue;
+        }
+        let rows = bitmap_footprint(dy, height, bitmap.height);
+        for (dx, columns) in columns.iter().enumerate() {
+            let px = left + dx as i32;
+            if px < 0 || px >= canvas.width as i32 {
+                continue;
+            }
+            // Averaged with premultiplied alpha so transparent pixels don't darken edges
+            let mut sum = [0.0f32; 4];
+            for &(sy, wy) in &rows {
+                for &(sx, wx) in columns {
+                    let idx = (sy * bitmap.width + sx) * 4;
+                    let src = &bitmap.pixels[idx..idx + 4];
+                    let weight = wx * wy * src[3] as f32;
+                    for channel in 0..3 {
+                        sum[channel] += src[channel] as f32 * weight;
+                    }
+                    sum[3] += weight;
+                }
+            }
+            if sum[3] > 0.0 {
+                let color = sum.map(|c| (c / sum[3]).round().min(255.0) as u8);
+                set_pixel(
+                    canvas,
+                    px as usize,
+                    py as usize,
+                    color[0],
+                    color[1],
+                    color[2],
+                    sum[3] / 255.0,
+                );
+            }
+        }
+    }
+}
+
+// Source pixels under destination pixel `index` along one axis, with weights summing to
+// one. The footprint is at least a source pixel wide, so enlarging interpolates between
+// neighbours and shrinking averages everything covered.
+fn bitmap_footprint(index: usize, destination: usize, source: usize) -> Vec<(usize, f32)> {
+    let scale = source as f64 / destination as f64;
+    let centre = (index as f64 + 0.5) * scale;
+    let half = scale.max(1.0) / 2.0;
+    let (start, end) = ((centre - half).max(0.0), (centre + half).min(source as f64));
+    let total = end - start;
+    (start.floor() as usize..(end.ceil() as usize).min(source))
+        .filter_map(|i| {
+            let overlap = end.min(i as f64 + 1.0) - start.max(i as f64);
+            (overlap > 0.0).then(|| (i, (overlap / total) as f32))
+        })
+        .collect()
+}
+
+fn set_pixel(canvas: &mut Canvas, x: usize, y: usize, r: u8, g: u8, b: u8, alpha: f32) {
+    if x < canvas.width && y < canvas.height {
+        let size = canvas.format.bytes_per_pixel();
+        let idx = (y * canvas.width + x) * size;
+        let pixel = &mut canvas.frame[idx..idx + size];
+        let dst = canvas.format.load(pixel);
+        let blend =
+            |src: u8, dst: u8| (src as f32 * alpha + dst as f32 * (1.0 - alpha)).round() as u8;
+        let out = (blend(r, dst.0), blend(g, dst.1), blend(b, dst.2));
+        canvas.format.store(pixel, out);
     }
 }
 
 fn draw_thick_line_aa(
-    frame: &mut [u8],
-    width: usize,
+    canvas: &mut Canvas,
     x0: i32,
     y0: i32,
     x1: i32,
@@ -1392,6 +10980,7 @@ fn draw_thick_line_aa(
     r: u8,
     g: u8,
     b: u8,
+    style: LineStyle,
 ) {
     let min_x = x0.min(x1) - thickness.ceil() as i32 - 1;
     let max_x = x0.max(x1) + thickness.ceil() as i32 + 1;
@@ -1408,17 +10997,18 @@ fn draw_thick_line_aa(
             let lx = x0 as f32 + t * dx;
             let ly = y0 as f32 + t * dy;
             let dist = ((lx - x as f32).powi(2) + (ly - y as f32).powi(2)).sqrt();
-            let aa = (1.0 - (dist - thickness / 2.0).clamp(0.0, 1.0)).clamp(0.0, 1.0);
+            let along = (t * len_sq.sqrt()) as f64;
+            let aa = (1.0 - (dist - thickness / 2.0).clamp(0.0, 1.0)).clamp(0.0, 1.0)
+                * style.coverage(along, thickness as f64) as f32;
             if aa > 0.01 {
-                set_pixel(frame, width, x as usize, y as usize, r, g, b, aa);
+                set_pixel(canvas, x as usize, y as usize, r, g, b, aa);
             }
         }
     }
 }
 
 fn draw_thick_line_tapered_aa(
-    frame: &mut [u8],
-    width: usize,
+    canvas: &mut Canvas,
     x0: i32,
     y0: i32,
     x1: i32,
@@ -1446,64 +11036,130 @@ fn draw_thick_line_tapered_aa(
             let local_thickness = thickness * (1.0 - t * 0.95); // 0.05 to avoid vanishing too soon
             let aa = (1.0 - (dist - local_thickness / 2.0).clamp(0.0, 1.0)).clamp(0.0, 1.0);
             if aa > 0.01 {
-                set_pixel(frame, width, x as usize, y as usize, r, g, b, aa);
+                set_pixel(canvas, x as usize, y as usize, r, g, b, aa);
+            }
+        }
+    }
+}
+
+// Like the tapered and plain line rasterisers, but switches style at the tip join so
+// the taper profile runs unbroken from pivot to end
+fn draw_segmented_line_aa(
+    canvas: &mut Canvas,
+    (x0, y0): (i32, i32),
+    (x1, y1): (i32, i32),
+    (thickness, color): (f32, (u8, u8, u8)),
+    tapered: bool,
+    tip: &NeedleTip,
+) {
+    let reach = thickness.max(tip.thickness).ceil() as i32 + 1;
+    let min_x = x0.min(x1) - reach;
+    let max_x = x0.max(x1) + reach;
+    let min_y = y0.min(y1) - reach;
+    let max_y = y0.max(y1) + reach;
+    let dx = (x1 - x0) as f32;
+    let dy = (y1 - y0) as f32;
+    let len_sq = dx * dx + dy * dy;
+    let split = 1.0 - tip.fraction.clamp(0.0, 1.0) as f32;
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            let px = x as f32 - x0 as f32;
+            let py = y as f32 - y0 as f32;
+            let t = ((px * dx + py * dy) / len_sq).clamp(0.0, 1.0);
+            let lx = x0 as f32 + t * dx;
+            let ly = y0 as f32 + t * dy;
+            let dist = ((lx - x as f32).powi(2) + (ly - y as f32).powi(2)).sqrt();
+            let (stroke, (r, g, b)) = if t > split {
+                (tip.thickness, tip.color)
+            } else {
+                (thickness, color)
+            };
+            let local_thickness = if tapered {
+                stroke * (1.0 - t * 0.95)
+            } else {
+                stroke
+            };
+            let aa = (1.0 - (dist - local_thickness / 2.0).clamp(0.0, 1.0)).clamp(0.0, 1.0);
+            if aa > 0.01 {
+                set_pixel(canvas, x as usize, y as usize, r, g, b, aa);
             }
         }
     }
 }
 
 fn draw_text(
-    frame: &mut [u8],
-    width: usize,
-    height: usize,
+    canvas: &mut Canvas,
     x: i32,
     y: i32,
     text: &str,
-    font: &rusttype::Font,
-    scale: rusttype::Scale,
+    shaper: &dyn TextShaper,
+    font_size: f32,
     color: (u8, u8, u8),
 ) {
-    use rusttype::{point, PositionedGlyph};
-    let v_metrics = font.v_metrics(scale);
-    let glyphs: Vec<PositionedGlyph> = font
-        .layout(text, scale, point(0.0, 0.0 + v_metrics.ascent))
-        .collect();
+    let glyphs = shaper.shape(text, font_size);
     // Calculate bounding box for the whole string
-
-    let (min_x, max_x, min_y, max_y) = glyphs.iter().filter_map(|g| g.pixel_bounding_box()).fold(
-        (i32::MAX, i32::MIN, i32::MAX, i32::MIN),
-        |(min_x, max_x, min_y, max_y), bb| {
-            (
-                min_x.min(bb.min.x),
-                max_x.max(bb.max.x),
-                min_y.min(bb.min.y),
-                max_y.max(bb.max.y),
-            )
-        },
-    );
+    let (min_x, max_x, min_y, max_y) = glyph_bounds(&glyphs);
     let width_px = if min_x < max_x { max_x - min_x } else { 0 };
     let height_px = if min_y < max_y { max_y - min_y } else { 0 };
     let offset_x = x - width_px / 2;
     let offset_y = y - height_px / 2;
-    for glyph in glyphs {
-        if let Some(bb) = glyph.pixel_bounding_box() {
-            glyph.draw(|gx, gy, v| {
-                let px = offset_x + gx as i32 + bb.min.x - min_x;
-                let py = offset_y + gy as i32 + bb.min.y - min_y;
-                if px >= 0 && px < width as i32 && py >= 0 && py < height as i32 {
+    for glyph in &glyphs {
+        for gy in 0..glyph.height {
+            for gx in 0..glyph.width {
+                let v = glyph.coverage[gy * glyph.width + gx];
+                if v == 0 {
+                    continue;
+                }
+                let px = offset_x + gx as i32 + glyph.left - min_x;
+                let py = offset_y + gy as i32 + glyph.top - min_y;
+                if px >= 0 && py >= 0 {
                     set_pixel(
-                        frame,
-                        width,
+                        canvas,
                         px as usize,
                         py as usize,
                         color.0,
                         color.1,
                         color.2,
-                        v as f32,
+                        v as f32 / 255.0,
                     );
                 }
-            });
+            }
+        }
+    }
+}
+
+// Resolves the font size and letter spacing that make curved text meet its fit mode
+fn fit_curved_text(
+    shaper: &dyn TextShaper,
+    text: &str,
+    font_size: f32,
+    letter_spacing: f64,
+    fit: CurvedTextFit,
+    target_width: f64,
+) -> (f32, f64) {
+    if fit == CurvedTextFit::Natural {
+        return (font_size, letter_spacing);
+    }
+    let glyphs = shaper.shape(text, font_size);
+    let (Some(first), Some(last)) = (glyphs.first(), glyphs.last()) else {
+        return (font_size, letter_spacing);
+    };
+    let gaps = (glyphs.len() - 1) as f64;
+    let natural_width = (last.pen_x - first.pen_x + last.advance) as f64;
+    match fit {
+        CurvedTextFit::Natural => (font_size, letter_spacing),
+        CurvedTextFit::ScaleFont => {
+            let width = natural_width + letter_spacing * gaps;
+            if width <= 0.0 {
+                return (font_size, letter_spacing);
+            }
+            let factor = target_width / width;
+            (font_size * factor as f32, letter_spacing * factor)
         }
+        CurvedTextFit::ExpandSpacing if gaps > 0.0 => {
+            (font_size, (target_width - natural_width) / gaps)
+        }
+        CurvedTextFit::ExpandSpacing => (font_size, letter_spacing),
     }
 }
 
@@ -1513,19 +11169,19 @@ fn draw_curved_text(
     cy: i32,
     radius: f64,
     text: &str,
-    font: &rusttype::Font,
-    scale: rusttype::Scale,
+    shaper: &dyn TextShaper,
+    font_size: f32,
+    letter_spacing: f64,
     arc_span: f64,
     center_angle: f64,
+    inverted: bool,
     color: (u8, u8, u8),
 ) {
-    use rusttype::{point, PositionedGlyph};
+    // Inverted text runs counter-clockwise so it reads left-to-right below the pivot
+    let direction = if inverted { -1.0 } else { 1.0 };
 
     // Create glyphs for the text to calculate individual character positions
-    let v_metrics = font.v_metrics(scale);
-    let glyphs: Vec<PositionedGlyph> = font
-        .layout(text, scale, point(0.0, 0.0 + v_metrics.ascent))
-        .collect();
+    let glyphs = shaper.shape(text, font_size);
 
     if glyphs.is_empty() {
         return;
@@ -1533,8 +11189,8 @@ fn draw_curved_text(
 
     // Calculate total text width by examining glyph positions
     let total_width = if let (Some(first), Some(last)) = (glyphs.first(), glyphs.last()) {
-        (last.position().x - first.position().x + last.unpositioned().h_metrics().advance_width)
-            as f64
+        (last.pen_x - first.pen_x + last.advance) as f64
+            + letter_spacing * (glyphs.len() - 1) as f64
     } else {
         0.0
     };
@@ -1548,25 +11204,25 @@ fn draw_curved_text(
     let actual_arc_span = chars_arc_span.min(arc_span);
 
     // Start angle for the text (center the text around center_angle)
-    let start_angle = center_angle - actual_arc_span / 2.0;
+    let start_angle = center_angle - direction * actual_arc_span / 2.0;
 
     // Draw each character
-    for glyph in &glyphs {
-        if glyph.pixel_bounding_box().is_some() {
-            let char_advance = glyph.unpositioned().h_metrics().advance_width as f64;
+    for (index, glyph) in glyphs.iter().enumerate() {
+        if glyph.width > 0 && glyph.height > 0 {
+            let char_advance = glyph.advance as f64;
 
             // Calculate the angle for the center of this character
-            let char_position = glyph.position().x as f64;
-            let first_position = glyphs[0].position().x as f64;
-            let relative_position = char_position - first_position + char_advance / 2.0;
-            let char_angle = start_angle + (relative_position / radius);
+            let relative_position = (glyph.pen_x - glyphs[0].pen_x) as f64
+                + letter_spacing * index as f64
+                + char_advance / 2.0;
+            let char_angle = start_angle + direction * (relative_position / radius);
 
             // Position on the arc
             let char_x = cx as f64 + char_angle.cos() * radius;
             let char_y = cy as f64 + char_angle.sin() * radius;
 
-            // Rotation angle (tangent to the circle)
-            let rotation_angle = char_angle + std::f64::consts::FRAC_PI_2;
+            // Rotation angle (tangent to the circle), flipped so inverted glyphs stay upright
+            let rotation_angle = char_angle + direction * std::f64::consts::FRAC_PI_2;
 
             // Draw the character with improved rotation
             draw_rotated_glyph_improved(canvas, glyph, char_x, char_y, rotation_angle, color);
@@ -1576,31 +11232,28 @@ fn draw_curved_text(
 
 fn draw_rotated_glyph_improved(
     canvas: &mut Canvas,
-    glyph: &rusttype::PositionedGlyph,
+    glyph: &ShapedGlyph,
     center_x: f64,
     center_y: f64,
     rotation: f64,
     color: (u8, u8, u8),
 ) {
-    if let Some(bb) = glyph.pixel_bounding_box() {
-        let cos_r = rotation.cos();
-        let sin_r = rotation.sin();
+    let cos_r = rotation.cos();
+    let sin_r = rotation.sin();
 
-        // Calculate glyph center offset
-        let glyph_center_x = (bb.min.x + bb.max.x) as f64 / 2.0;
-        let glyph_center_y = (bb.min.y + bb.max.y) as f64 / 2.0;
+    // Calculate glyph center offset
+    let glyph_center_x = glyph.width as f64 / 2.0;
+    let glyph_center_y = glyph.height as f64 / 2.0;
 
-        // Draw each pixel of the glyph with sub-pixel accuracy
-        glyph.draw(|gx, gy, v| {
+    // Draw each pixel of the glyph with sub-pixel accuracy
+    for gy in 0..glyph.height {
+        for gx in 0..glyph.width {
+            let v = glyph.coverage[gy * glyph.width + gx] as f32 / 255.0;
             if v > 0.001 {
                 // Lower threshold for better coverage
-                // Get pixel position relative to glyph origin
-                let px = gx as f64 + bb.min.x as f64;
-                let py = gy as f64 + bb.min.y as f64;
-
                 // Translate to glyph center
-                let local_x = px - glyph_center_x;
-                let local_y = py - glyph_center_y;
+                let local_x = gx as f64 - glyph_center_x;
+                let local_y = gy as f64 - glyph_center_y;
 
                 // Apply rotation
                 let rotated_x = local_x * cos_r - local_y * sin_r;
@@ -1611,9 +11264,9 @@ fn draw_rotated_glyph_improved(
                 let final_y = center_y + rotated_y;
 
                 // Draw with sub-pixel positioning for smoother rendering
-                draw_antialiased_pixel(canvas, final_x, final_y, color, v as f32);
+                draw_antialiased_pixel(canvas, final_x, final_y, color, v);
             }
-        });
+        }
     }
 }
 
@@ -1640,8 +11293,7 @@ fn draw_antialiased_pixel(canvas: &mut Canvas, x: f64, y: f64, color: (u8, u8, u
             if final_alpha > 0.001 {
                 // Lower threshold for better coverage
                 set_pixel(
-                    canvas.frame,
-                    canvas.width,
+                    canvas,
                     *px as usize,
                     *py as usize,
                     color.0,
@@ -1654,7 +11306,108 @@ fn draw_antialiased_pixel(canvas: &mut Canvas, x: f64, y: f64, color: (u8, u8, u
     }
 }
 
-fn draw_circle(frame: &mut [u8], width: usize, cx: i32, cy: i32, radius: i32, r: u8, g: u8, b: u8) {
+// Scanline fill under the nonzero winding rule. Each pixel row is sampled along a few
+// sub-scanlines, and every inside span adds its exact horizontal overlap with a pixel to
+// that pixel's coverage. Pixel centres sit on whole coordinates, as for the line rasterisers.
+fn fill_polygon_aa(canvas: &mut Canvas, points: &[(f64, f64)], (r, g, b): (u8, u8, u8)) {
+    const SUBSCANLINES: usize = 4;
+    if points.len() < 3 || points.iter().any(|(x, y)| !x.is_finite() || !y.is_finite()) {
+        return;
+    }
+    let (min_x, max_x, min_y, max_y) = points.iter().fold(
+        (f64::MAX, f64::MIN, f64::MAX, f64::MIN),
+        |(x0, x1, y0, y1), &(x, y)| (x0.min(x), x1.max(x), y0.min(y), y1.max(y)),
+    );
+    let left = (min_x + 0.5).floor().max(0.0) as i64;
+    let right = ((max_x + 0.5).floor() as i64).min(canvas.width as i64 - 1);
+    let top = (min_y + 0.5).floor().max(0.0) as i64;
+    let bottom = ((max_y + 0.5).floor() as i64).min(canvas.height as i64 - 1);
+    if left > right || top > bottom {
+        return;
+    }
+    let mut coverage = vec![0.0f32; (right - left + 1) as usize];
+    let mut crossings: Vec<(f64, i32)> = Vec::new();
+    for py in top..=bottom {
+        coverage.fill(0.0);
+        for sub in 0..SUBSCANLINES {
+            let y = py as f64 - 0.5 + (sub as f64 + 0.5) / SUBSCANLINES as f64;
+            crossings.clear();
+            for (i, &(x0, y0)) in points.iter().enumerate() {
+                let (x1, y1) = points[(i + 1) % points.len()];
+                if (y0 <= y) != (y1 <= y) {
+                    let x = x0 + (y - y0) / (y1 - y0) * (x1 - x0);
+                    crossings.push((x, if y1 > y0 { 1 } else { -1 }));
+                }
+            }
+            crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+            let (mut winding, mut start) = (0, 0.0);
+            for &(x, direction) in &crossings {
+                if winding == 0 {
+                    start = x;
+                }
+                winding += direction;
+                if winding == 0 {
+                    // Span in pixel-edge units from the left of column `left`
+                    let (from, to) = (start + 0.5 - left as f64, x + 0.5 - left as f64);
+                    let (from, to) = (from.max(0.0), to.min(coverage.len() as f64));
+                    if from < to {
+                        let first = from.floor() as usize;
+                        let columns = to.ceil() as usize - first;
+                        for (column, cover) in
+                            coverage.iter_mut().enumerate().skip(first).take(columns)
+                        {
+                            let overlap = to.min(column as f64 + 1.0) - from.max(column as f64);
+                            *cover += overlap as f32 / SUBSCANLINES as f32;
+                        }
+                    }
+                }
+            }
+        }
+        for (column, &alpha) in coverage.iter().enumerate() {
+            if alpha > 0.004 {
+                let x = (left as usize) + column;
+                set_pixel(canvas, x, py as usize, r, g, b, alpha.min(1.0));
+            }
+        }
+    }
+}
+
+// Coverage from the signed distance to the box's edge at each pixel centre. `bounds` is
+// the left, top, width and height of the box's edge in canvas coordinates.
+fn draw_rounded_rect_aa(
+    canvas: &mut Canvas,
+    (left, top, width, height): (f64, f64, f64, f64),
+    radius: f64,
+    stroke: Option<f32>,
+    (r, g, b): (u8, u8, u8),
+) {
+    let (half_width, half_height) = (width / 2.0, height / 2.0);
+    let (cx, cy) = (left + half_width, top + half_height);
+    let radius = radius.clamp(0.0, half_width.min(half_height));
+    let reach = stroke.map_or(0.0, |stroke| stroke as f64 / 2.0) + 1.0;
+    let rows = (top - reach).floor().max(0.0) as usize
+        ..((top + height + reach).ceil().max(0.0) as usize).min(canvas.height);
+    let columns = (left - reach).floor().max(0.0) as usize
+        ..((left + width + reach).ceil().max(0.0) as usize).min(canvas.width);
+    for y in rows {
+        for x in columns.clone() {
+            let qx = (x as f64 - cx).abs() - half_width + radius;
+            let qy = (y as f64 - cy).abs() - half_height + radius;
+            let distance = qx.max(0.0).hypot(qy.max(0.0)) + qx.max(qy).min(0.0) - radius;
+            let alpha = match stroke {
+                Some(stroke) => (stroke as f64 / 2.0 + 0.5 - distance.abs())
+                    .clamp(0.0, 1.0)
+                    .min(stroke as f64),
+                None => (0.5 - distance).clamp(0.0, 1.0),
+            };
+            if alpha > 0.004 {
+                set_pixel(canvas, x, y, r, g, b, alpha as f32);
+            }
+        }
+    }
+}
+
+fn draw_circle(canvas: &mut Canvas, cx: i32, cy: i32, radius: i32, r: u8, g: u8, b: u8) {
     for y in -radius..=radius {
         for x in -radius..=radius {
             let dist = ((x * x + y * y) as f64).sqrt();
@@ -1666,18 +11419,16 @@ fn draw_circle(frame: &mut [u8], width: usize, cx: i32, cy: i32, radius: i32, r:
             if dist <= radius as f64 + 1.0 && aa > 0.0 {
                 let px = cx + x;
                 let py = cy + y;
-                if px >= 0
-                    && py >= 0
-                    && (px as usize) < width
-                    && (py as usize) < frame.len() / (width * 4)
-                {
-                    set_pixel(frame, width, px as usize, py as usize, r, g, b, aa as f32);
+                if px >= 0 && py >= 0 {
+                    set_pixel(canvas, px as usize, py as usize, r, g, b, aa as f32);
                 }
             }
         }
     }
 }
 
+// Coverage comes from signed distances to the band's inner and outer edges and to its
+// ends, so the band is exactly `thickness` wide from `r` inwards and its ends are smooth
 fn render_arc_immediate(
     canvas: &mut Canvas,
     cx: i32,
@@ -1687,60 +11438,67 @@ fn render_arc_immediate(
     start_angle: f64,
     arc_span: f64,
     color: (u8, u8, u8),
+    style: LineStyle,
+    cap: ArcCap,
 ) {
-    let end_angle = start_angle + arc_span;
-    let mut start_angle = start_angle;
-    let mut end_angle = end_angle;
-    if start_angle < 0.0 {
-        start_angle += 2.0 * std::f64::consts::PI;
-    }
-    if end_angle >= 2.0 * std::f64::consts::PI {
-        end_angle -= 2.0 * std::f64::consts::PI;
-    }
+    use std::f64::consts::{FRAC_PI_2, TAU};
+    let (start_angle, arc_span) = if arc_span < 0.0 {
+        (start_angle + arc_span, -arc_span)
+    } else {
+        (start_angle, arc_span)
+    };
+    let half_width = thickness as f64 / 2.0;
+    let mid_radius = r as f64 - half_width;
+    let closed = arc_span >= TAU;
+    // Distance from a point `dist` out from the centre to an end's radial line, from
+    // `offset` radians away; beyond a quarter turn the centre is the nearest point
+    let to_end = |dist: f64, offset: f64| {
+        if offset < FRAC_PI_2 {
+            dist * offset.sin()
+        } else {
+            dist
+        }
+    };
+    let cap_centres = [start_angle, start_angle + arc_span].map(|angle| {
+        (
+            cx as f64 + angle.cos() * mid_radius,
+            cy as f64 + angle.sin() * mid_radius,
+        )
+    });
 
-    for y in 0..canvas.height as i32 {
-        for x in 0..canvas.width as i32 {
-            let dx = x - cx;
-            let dy = y - cy;
-            let dist = ((dx * dx + dy * dy) as f64).sqrt();
-            let mut angle = (dy as f64).atan2(dx as f64);
-            if angle < 0.0 {
-                angle += 2.0 * std::f64::consts::PI;
-            }
-            let mut start = start_angle;
-            let mut end = end_angle;
-            if start < 0.0 {
-                start += 2.0 * std::f64::consts::PI;
-            }
-            if end < 0.0 {
-                end += 2.0 * std::f64::consts::PI;
+    let reach = r + 2;
+    for y in (cy - reach).max(0)..(cy + reach + 1).min(canvas.height as i32) {
+        for x in (cx - reach).max(0)..(cx + reach + 1).min(canvas.width as i32) {
+            let (dx, dy) = ((x - cx) as f64, (y - cy) as f64);
+            let dist = dx.hypot(dy);
+            let radial = (dist - mid_radius).abs() - half_width;
+            if radial >= 0.5 && cap == ArcCap::Butt {
+                continue;
             }
-            let in_arc = if start < end {
-                angle >= start && angle <= end
+            let offset = (dy.atan2(dx) - start_angle).rem_euclid(TAU);
+            // Negative inside the span, positive beyond its nearer end
+            let past_end = if closed {
+                f64::NEG_INFINITY
+            } else if offset <= arc_span {
+                -to_end(dist, offset).min(to_end(dist, arc_span - offset))
             } else {
-                angle >= start || angle <= end
+                to_end(dist, offset - arc_span).min(to_end(dist, TAU - offset))
             };
-            if in_arc {
-                let aa = if dist > r as f64 {
-                    1.0 - (dist - r as f64).min(1.0)
-                } else if dist < (r - thickness) as f64 {
-                    1.0 - ((r - thickness) as f64 - dist).min(1.0)
-                } else {
-                    1.0
-                };
-                if dist >= (r - thickness - 1) as f64 && dist <= (r + 1) as f64 && aa > 0.0 {
-                    set_pixel(
-                        canvas.frame,
-                        canvas.width,
-                        x as usize,
-                        y as usize,
-                        color.0,
-                        color.1,
-                        color.2,
-                        aa as f32,
-                    );
+            let along = offset * mid_radius;
+            let mut aa = (0.5 - radial).clamp(0.0, 1.0)
+                * (0.5 - past_end).clamp(0.0, 1.0)
+                * style.coverage(along, thickness as f64);
+            if cap == ArcCap::Round && !closed {
+                for (capx, capy) in cap_centres {
+                    let edge = (x as f64 - capx).hypot(y as f64 - capy) - half_width;
+                    aa = aa.max((0.5 - edge).clamp(0.0, 1.0));
                 }
             }
+            if aa > 0.0 {
+                set_pixel(
+                    canvas, x as usize, y as usize, color.0, color.1, color.2, aa as f32,
+                );
+            }
         }
     }
 }
@@ -1750,10 +11508,9 @@ fn render_highlight_band_immediate(
     cx: i32,
     cy: i32,
     r: i32,
-    start_angle: f64,
-    end_angle: f64,
-    inner_radius: f64,
-    outer_radius: f64,
+    (start_angle, end_angle): (f64, f64),
+    (inner_radius, outer_radius): (f64, f64),
+    alpha: f64,
     config: &InstrumentConfig,
 ) {
     // Draw the highlight band as a thick arc
@@ -1835,14 +11592,13 @@ fn render_highlight_band_immediate(
                 0.0
             };
 
-            let final_alpha =
-                (angular_alpha * radial_alpha * config.highlight_band_alpha).clamp(0.0, 1.0);
+            let final_alpha = (angular_alpha * radial_alpha * config.highlight_band_alpha * alpha)
+                .clamp(0.0, 1.0);
 
             if final_alpha > 0.01 {
                 let color = config.highlight_band_color.as_tuple();
                 set_pixel(
-                    canvas.frame,
-                    canvas.width,
+                    canvas,
                     x as usize,
                     y as usize,
                     color.0,
@@ -1854,3 +11610,280 @@ fn render_highlight_band_immediate(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn evaluate(text: &str, values: &[(&str, f64)]) -> Option<f64> {
+        let values = values
+            .iter()
+            .map(|(key, value)| (key.to_string(), *value))
+            .collect();
+        Expression::parse(text).unwrap().evaluate(&values)
+    }
+
+    #[test]
+    fn expression_precedence() {
+        assert_eq!(evaluate("1 + 2 * 3", &[]), Some(7.0));
+        assert_eq!(evaluate("(1 + 2) * 3", &[]), Some(9.0));
+        assert_eq!(evaluate("2 * 3 ^ 2", &[]), Some(18.0));
+        assert_eq!(evaluate("10 - 4 - 3", &[]), Some(3.0));
+        assert_eq!(evaluate("12 / 3 / 2", &[]), Some(2.0));
+        assert_eq!(evaluate("7 % 4 * 2", &[]), Some(6.0));
+    }
+
+    #[test]
+    fn expression_power_is_right_associative() {
+        assert_eq!(evaluate("2 ^ 3 ^ 2", &[]), Some(512.0));
+        assert_eq!(evaluate("(2 ^ 3) ^ 2", &[]), Some(64.0));
+    }
+
+    #[test]
+    fn expression_unary_minus() {
+        assert_eq!(evaluate("-3 + 5", &[]), Some(2.0));
+        assert_eq!(evaluate("3 - -2", &[]), Some(5.0));
+        assert_eq!(evaluate("--1", &[]), Some(1.0));
+        assert_eq!(evaluate("-(1 + 2)", &[]), Some(-3.0));
+        // As in mathematics, the power is taken before the sign
+        assert_eq!(evaluate("-2 ^ 2", &[]), Some(-4.0));
+        assert_eq!(evaluate("(-2) ^ 2", &[]), Some(4.0));
+        assert_eq!(evaluate("2 ^ -1", &[]), Some(0.5));
+        assert_eq!(evaluate("2 ^ -1 ^ 2", &[]), Some(0.5));
+        assert_eq!(evaluate("3 * -2 ^ 2", &[]), Some(-12.0));
+    }
+
+    #[test]
+    fn expression_numbers() {
+        assert_eq!(evaluate("1e-3", &[]), Some(0.001));
+        assert_eq!(evaluate("2.5E2", &[]), Some(250.0));
+        assert_eq!(evaluate("1e+2 * 2", &[]), Some(200.0));
+        assert_eq!(evaluate(".5", &[]), Some(0.5));
+        assert!(Expression::parse("1e").is_err());
+        assert!(Expression::parse("1.2.3").is_err());
+    }
+
+    #[test]
+    fn expression_functions() {
+        assert_eq!(evaluate("max(abs(-4), sqrt(9))", &[]), Some(4.0));
+        assert_eq!(
+            Expression::parse("min(1)"),
+            Err("min takes 2 argument(s)".to_string())
+        );
+        assert_eq!(
+            Expression::parse("abs(1, 2)"),
+            Err("abs takes 1 argument(s)".to_string())
+        );
+        assert_eq!(
+            Expression::parse("volume(1)"),
+            Err("unknown function \"volume\"".to_string())
+        );
+    }
+
+    #[test]
+    fn expression_inputs() {
+        let values = [("volts", 12.0), ("amps", 2.5)];
+        assert_eq!(evaluate("volts * amps", &values), Some(30.0));
+        assert_eq!(evaluate("volts * watts", &values), None);
+    }
+
+    #[test]
+    fn expression_syntax_errors() {
+        assert_eq!(
+            Expression::parse("1 +"),
+            Err("expression ends early".to_string())
+        );
+        assert_eq!(Expression::parse("(1"), Err("expected ')'".to_string()));
+        assert_eq!(Expression::parse("1 2"), Err("unexpected '2'".to_string()));
+        assert_eq!(
+            Expression::parse("1 $ 2"),
+            Err("unexpected '$'".to_string())
+        );
+    }
+
+    #[test]
+    fn alert_rules_parse_and_print() {
+        let rule = AlertRule::parse("primary > 90 for 5s: flash red, OVERTEMP").unwrap();
+        assert_eq!(rule.needle, NeedleId::Primary);
+        assert_eq!(rule.condition, AlertCondition::Above(90.0));
+        assert_eq!(rule.hold, 5.0);
+        assert_eq!(rule.flash.map(Color::as_tuple), Some((0xff, 0x00, 0x00)));
+        assert_eq!(rule.indicator.as_deref(), Some("OVERTEMP"));
+        assert_eq!(
+            rule.to_string(),
+            "primary > 90 for 5s: flash #ff0000, OVERTEMP"
+        );
+
+        let rule = AlertRule::parse("chrono1 outside -5..2.5: LOW BATT").unwrap();
+        assert_eq!(rule.condition, AlertCondition::Outside(-5.0, 2.5));
+        assert!(rule.flash.is_none());
+        let reparsed = AlertRule::parse(&rule.to_string()).unwrap();
+        assert_eq!(reparsed.to_string(), rule.to_string());
+
+        for bad in [
+            "speed > 90",
+            "primary >= 90",
+            "primary > hot",
+            "primary outside 10",
+            "primary > 90 after 5s",
+            "primary > 90: flash mauve",
+        ] {
+            assert!(AlertRule::parse(bad).is_err(), "{bad}");
+        }
+    }
+
+    #[test]
+    fn derived_channels_skip_non_finite_results() {
+        let config = InstrumentConfig::builder()
+            .derived_channels(vec![DerivedChannel::parse("primary = 100 / x").unwrap()])
+            .build();
+        let mut renderer = Instrument::new(config).renderer();
+        let set = |key: &str, value: f64| {
+            InstrumentCommand::SetMany(HashMap::from([(key.to_string(), value)]))
+        };
+        renderer.apply(set("x", 4.0));
+        renderer.apply(set("x", 0.0));
+        let primary = renderer
+            .state
+            .needle1
+            .as_ref()
+            .map(|needle| needle.target_value);
+        assert_eq!(primary, Some(25.0));
+    }
+
+    #[test]
+    fn dashboard_recording_round_trips() {
+        let many = |pairs: &[(&str, f64)]| {
+            InstrumentCommand::SetMany(
+                pairs
+                    .iter()
+                    .map(|(key, value)| (key.to_string(), *value))
+                    .collect(),
+            )
+        };
+        let recording = DashboardRecording {
+            events: vec![
+                (
+                    Duration::from_millis(250),
+                    (0, InstrumentCommand::SetPrimaryNeedle(42.5)),
+                ),
+                (
+                    Duration::from_millis(500),
+                    (1, many(&[("volts", 12.6), ("amps", -3.0)])),
+                ),
+                (
+                    Duration::from_secs(1),
+                    (1, InstrumentCommand::SetHighlightBounds(20.0, 80.0)),
+                ),
+                (
+                    Duration::from_secs(2),
+                    (0, InstrumentCommand::ClearHighlightBounds),
+                ),
+                (
+                    Duration::from_secs(3),
+                    (2, InstrumentCommand::SetRange(-10.0, 10.0)),
+                ),
+            ],
+        };
+        let path = std::env::temp_dir().join(format!("recording-{}.txt", std::process::id()));
+        recording.save(&path).unwrap();
+        let loaded = DashboardRecording::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.events.len(), recording.events.len());
+        for ((time, (index, command)), (loaded_time, (loaded_index, loaded_command))) in
+            recording.events.iter().zip(&loaded.events)
+        {
+            assert_eq!(time, loaded_time);
+            assert_eq!(index, loaded_index);
+            let mut expected = recorded_values(command).unwrap();
+            let mut actual = recorded_values(loaded_command).unwrap();
+            expected.sort_by(|a, b| a.0.cmp(&b.0));
+            actual.sort_by(|a, b| a.0.cmp(&b.0));
+            assert_eq!(expected, actual);
+        }
+
+        for bad in ["time", "gauge", "two words", "a=b", ""] {
+            let recording = DashboardRecording {
+                events: vec![(Duration::ZERO, (0, many(&[(bad, 1.0)])))],
+            };
+            assert!(recording.save(&path).is_err(), "{bad:?}");
+            assert!(!path.exists());
+        }
+    }
+
+    #[test]
+    fn dashboard_layout_round_trips() {
+        let odd = "tab\there \"quoted\" back\\slash # not a comment\nline\u{1b}[0m é";
+        let mut dashboard = Dashboard::new(odd);
+        let oil = InstrumentConfig::builder()
+            .title(odd.to_string())
+            .range((-10.5, 150.0))
+            .highlight_band((120.0, 150.0, Color::new(0x12, 0xab, 0xef)))
+            .ticks_count(7)
+            .minor_ticks_per_interval(3)
+            .auto_ticks(true)
+            .window_width(320)
+            .window_height(240)
+            .curved_text("OIL °C".to_string())
+            .bottom_curved_text("x = y".to_string())
+            .dial_text("top\nbottom".to_string())
+            .chronograph_range((0.0, 30.0))
+            .ui_scale(1.5)
+            .derived_channels(vec![DerivedChannel::parse("readout = -x ^ 2 + 1").unwrap()])
+            .alert_rules(vec![AlertRule::parse("primary > 90 for 5s: flash red, HOT").unwrap()])
+            .build();
+        let oil = dashboard.add_named("oil \"sump\"", Instrument::new(oil));
+        let water = dashboard.add_named("water", Instrument::new(InstrumentConfig::builder().build()));
+        let rpm = dashboard.add_named("rpm", Instrument::new(InstrumentConfig::builder().build()));
+        dashboard.place(water, 2, 1);
+        dashboard.link_ranges([oil, water]);
+        dashboard.link_ranges([water, rpm]);
+
+        let text = dashboard.to_toml();
+        let loaded = Dashboard::from_toml(&text).unwrap();
+        assert_eq!(loaded.to_toml(), text);
+        assert_eq!(loaded.title, odd);
+        assert_eq!(loaded.names, dashboard.names);
+        assert_eq!(loaded.link_groups, dashboard.link_groups);
+        assert_eq!(dashboard.unsaved_settings().unwrap(), Vec::<String>::new());
+
+        let mut styled = InstrumentConfig::builder().needle_width(9.0).build();
+        styled.title = "styled".to_string();
+        dashboard.add_named("styled", Instrument::new(styled));
+        assert_eq!(dashboard.unsaved_settings().unwrap(), ["styled.needle_width"]);
+        assert!(dashboard.save(std::env::temp_dir().join("unsaved.toml")).is_err());
+    }
+
+    #[test]
+    fn dashboard_layout_comments_and_limits() {
+        let loaded = Dashboard::from_toml(
+            "title = \"Engine\" # the panel\n\
+             [[gauge]] # first\n\
+             name = \"a#b\"  # hash inside the string\n\
+             links = [0, 70] # two groups\n\
+             column = 1 # cell\n\
+             [[gauge]]\n\
+             links = [70]\n",
+        )
+        .unwrap();
+        assert_eq!(loaded.title, "Engine");
+        assert_eq!(loaded.names, ["a#b", "1"]);
+        assert_eq!(loaded.positions[0], Some((1, 0)));
+        assert_eq!(loaded.link_groups, [vec![0, 1]]);
+
+        for bad in [
+            "[[gauge]]\nlinks = [1e12]",
+            "[[gauge]]\nlinks = [-3]",
+            "[[gauge]]\nlinks = [0.5]",
+            "[[gauge]]\ncolumn = 1e12",
+            "[[gauge]]\nrow = 64",
+            "[[gauge]]\nname = \"a\"\n[[gauge]]\nname = \"a\"",
+            "[[gauge]]\ncolumn = 1\n[[gauge]]\ncolumn = 1",
+            "[[gauge]]\nname = \"a\" trailing",
+            "[[gauge]]\nname = \"\\uZZZZ\"",
+        ] {
+            assert!(Dashboard::from_toml(bad).is_err(), "{bad:?}");
+        }
+    }
+}
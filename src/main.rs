@@ -0,0 +1,567 @@
+//! Command-line front end: builds an `Instrument` from flags and drives it with
+//! `InstrumentCommand`s parsed from stdin, one line per update.
+
+use instrument::{
+    AlertRule, Animation, AnimationTarget, Color, CustomLayer, Dashboard, DrawCommand, Easing,
+    FrameHook, Instrument, InstrumentCommand, InstrumentConfig,
+};
+use std::collections::HashMap;
+use std::io::BufRead;
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+
+const USAGE: &str = "usage: instrument [--range <min> <max>] [--title <title>] \
+                     [--highlight <lower> <upper>] [--ticks <count>] [--minor-ticks <count>] \
+                     [--size <width> <height>] [--units <text>] [--curved-text <text>] \
+                     [--chrono-range <min> <max>] [--theme light|dark|night] \
+                     [--gauges <name>,<name>,...] [--layout <file>] [--framebuffer <device>] \
+                     [--strict] [--error-badge] [--on-eof hold|close|demo] [--borderless] \
+                     [--click-through] [--source cpu|memory|temperature|network] \
+                     [--alert <rule>]...";
+
+#[derive(Clone, Copy)]
+enum Theme {
+    Light,
+    Dark,
+    Night,
+}
+
+impl Theme {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "light" => Ok(Theme::Light),
+            "dark" => Ok(Theme::Dark),
+            "night" => Ok(Theme::Night),
+            _ => Err(format!(
+                "unknown theme {name:?}; expected light, dark or night"
+            )),
+        }
+    }
+
+    // The dial is drawn black on white, so the other themes recolour the finished frame:
+    // dark inverts lightness while keeping hues, night then keeps only the red channel
+    fn frame_hook(self) -> Option<FrameHook> {
+        let night = match self {
+            Theme::Light => return None,
+            Theme::Dark => false,
+            Theme::Night => true,
+        };
+        Some(FrameHook::new(move |frame, _, _| {
+            for pixel in frame.chunks_exact_mut(4) {
+                let [r, g, b] = [pixel[0], pixel[1], pixel[2]].map(i32::from);
+                // Maps max to 255 - min and min to 255 - max, so this never leaves 0..=255
+                let shift = 255 - r.max(g).max(b) - r.min(g).min(b);
+                let [r, g, b] = [r, g, b].map(|c| (c + shift) as u8);
+                if night {
+                    pixel[..3].copy_from_slice(&[r.max(g).max(b), 0, 0]);
+                } else {
+                    pixel[..3].copy_from_slice(&[r, g, b]);
+                }
+            }
+        }))
+    }
+}
+
+/// What happens once stdin closes
+#[derive(Clone, Copy)]
+enum OnEof {
+    Hold,
+    Close,
+    Demo,
+}
+
+impl OnEof {
+    fn parse(name: &str) -> Result<Self, String> {
+        match name {
+            "hold" => Ok(OnEof::Hold),
+            "close" => Ok(OnEof::Close),
+            "demo" => Ok(OnEof::Demo),
+            _ => Err(format!(
+                "unknown --on-eof action {name:?}; expected hold, close or demo"
+            )),
+        }
+    }
+
+    /// Runs on the input thread after the last line; `demo` builds the commands that
+    /// hand the display over to a looping sweep
+    fn finish<T>(self, sender: &Sender<T>, demo: impl FnOnce() -> Vec<T>) {
+        match self {
+            OnEof::Hold => eprintln!("instrument: end of input; holding the last values"),
+            OnEof::Close => std::process::exit(0),
+            OnEof::Demo => {
+                eprintln!("instrument: end of input; switching to demo mode");
+                for command in demo() {
+                    if sender.send(command).is_err() {
+                        return;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Sweeps the primary needle and readout across `range` and back every eight seconds
+fn demo_sweep((min, max): (f64, f64)) -> InstrumentCommand {
+    let mut animation = Animation::new().looping(true);
+    for target in [AnimationTarget::Primary, AnimationTarget::Readout] {
+        animation = animation
+            .keyframe(target, 0.0, min)
+            .keyframe_eased(target, 4.0, max, Easing::EaseInOut)
+            .keyframe_eased(target, 8.0, min, Easing::EaseInOut);
+    }
+    InstrumentCommand::PlayAnimation(animation)
+}
+
+struct Args {
+    title: String,
+    range: (f64, f64),
+    highlight: Option<(f64, f64)>,
+    ticks: Option<usize>,
+    minor_ticks: Option<usize>,
+    size: Option<(usize, usize)>,
+    units: Option<String>,
+    curved_text: Option<String>,
+    chrono_range: Option<(f64, f64)>,
+    theme: Theme,
+    /// Dashboard mode: one dial per name, fed by `name.key=value` pairs
+    gauges: Option<Vec<String>>,
+    /// Dashboard mode from a file saved with `Dashboard::save`, routed by its gauge names
+    layout: Option<String>,
+    /// Draw to a Linux fbdev device instead of opening a window
+    framebuffer: Option<String>,
+    /// Exit non-zero on the first malformed input line instead of skipping it
+    strict: bool,
+    /// Flag rejected input lines on the dial itself
+    error_badge: bool,
+    on_eof: OnEof,
+    /// Float above other windows without a title bar
+    borderless: bool,
+    /// Let clicks through to the window underneath
+    click_through: bool,
+    /// Show a figure about this machine instead of reading stdin
+    source: Option<String>,
+    /// Rules such as `primary > 90 for 5s: flash red, OVERTEMP`, one per `--alert`
+    alerts: Vec<AlertRule>,
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut parsed = Args {
+        title: "Instrument".to_string(),
+        range: (0.0, 100.0),
+        highlight: None,
+        ticks: None,
+        minor_ticks: None,
+        size: None,
+        units: None,
+        curved_text: None,
+        chrono_range: None,
+        theme: Theme::Light,
+        gauges: None,
+        layout: None,
+        framebuffer: None,
+        strict: false,
+        error_badge: false,
+        on_eof: OnEof::Hold,
+        borderless: false,
+        click_through: false,
+        source: None,
+        alerts: Vec::new(),
+    };
+    while let Some(flag) = args.next() {
+        match flag.as_str() {
+            "--title" => parsed.title = text(&mut args, &flag)?,
+            "--range" => parsed.range = pair(&mut args, &flag)?,
+            "--highlight" => parsed.highlight = Some(pair(&mut args, &flag)?),
+            "--ticks" => parsed.ticks = Some(count(&mut args, &flag)?),
+            "--minor-ticks" => parsed.minor_ticks = Some(count(&mut args, &flag)?),
+            "--size" => parsed.size = Some((count(&mut args, &flag)?, count(&mut args, &flag)?)),
+            "--units" => parsed.units = Some(text(&mut args, &flag)?),
+            "--curved-text" => parsed.curved_text = Some(text(&mut args, &flag)?),
+            "--chrono-range" => parsed.chrono_range = Some(pair(&mut args, &flag)?),
+            "--theme" => parsed.theme = Theme::parse(&text(&mut args, &flag)?)?,
+            "--layout" => parsed.layout = Some(text(&mut args, &flag)?),
+            "--framebuffer" => parsed.framebuffer = Some(text(&mut args, &flag)?),
+            "--strict" => parsed.strict = true,
+            "--error-badge" => parsed.error_badge = true,
+            "--borderless" => parsed.borderless = true,
+            "--click-through" => parsed.click_through = true,
+            "--on-eof" => parsed.on_eof = OnEof::parse(&text(&mut args, &flag)?)?,
+            "--source" => parsed.source = Some(text(&mut args, &flag)?),
+            "--alert" => parsed.alerts.push(
+                AlertRule::parse(&text(&mut args, &flag)?).map_err(|error| error.to_string())?,
+            ),
+            "--gauges" => {
+                let names = text(&mut args, &flag)?;
+                parsed.gauges = Some(names.split(',').map(str::to_string).collect());
+            }
+            // Lets scripts end their flag list with a bare `--`
+            "--" => {}
+            _ => return Err(format!("unknown flag {flag}")),
+        }
+    }
+    if parsed.gauges.is_some() && parsed.framebuffer.is_some() {
+        return Err("--framebuffer can't be combined with --gauges".to_string());
+    }
+    if parsed.layout.is_some() && (parsed.gauges.is_some() || parsed.framebuffer.is_some()) {
+        return Err("--layout can't be combined with --gauges or --framebuffer".to_string());
+    }
+    if parsed.source.is_some() && (parsed.gauges.is_some() || parsed.layout.is_some()) {
+        return Err("--source can't be combined with --gauges or --layout".to_string());
+    }
+    Ok(parsed)
+}
+
+fn text(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<String, String> {
+    args.next().ok_or_else(|| format!("{flag} expects a value"))
+}
+
+fn number(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<f64, String> {
+    let raw = text(args, flag)?;
+    raw.parse()
+        .map_err(|_| format!("{flag} expects a number, got {raw:?}"))
+}
+
+fn count(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<usize, String> {
+    let raw = text(args, flag)?;
+    raw.parse()
+        .map_err(|_| format!("{flag} expects a whole number, got {raw:?}"))
+}
+
+fn pair(args: &mut impl Iterator<Item = String>, flag: &str) -> Result<(f64, f64), String> {
+    Ok((number(args, flag)?, number(args, flag)?))
+}
+
+/// Numeric `key=value` pairs from one input line, with the legacy names mapped onto the
+/// keys `InstrumentCommand::SetMany` understands. A bare number sets the primary needle
+/// and the readout. Unknown keys with non-numeric values (labels) are skipped; anything
+/// else that isn't a number is an error, and the whole line is rejected.
+fn parse_line(line: &str) -> Result<HashMap<String, f64>, String> {
+    if let Ok(value) = line.trim().parse::<f64>() {
+        return Ok(HashMap::from([
+            ("primary".to_string(), value),
+            ("readout".to_string(), value),
+        ]));
+    }
+    Ok(pairs(line)?
+        .into_iter()
+        .map(|(key, value)| (canonical_key(key), value))
+        .collect())
+}
+
+/// Values parsed for one dashboard gauge, by gauge index
+type GaugeValues = (usize, HashMap<String, f64>);
+
+/// Dashboard lines address each value to a gauge as `name.key=value`. Returns the values
+/// per gauge index, or an error if any pair lacks a gauge name or names an unknown gauge.
+fn parse_dashboard_line(line: &str, names: &[String]) -> Result<Vec<GaugeValues>, String> {
+    let mut gauges: Vec<GaugeValues> = Vec::new();
+    for (key, value) in pairs(line)? {
+        let Some((name, key)) = key.split_once('.') else {
+            return Err(format!("{key:?} has no gauge name; expected <gauge>.{key}"));
+        };
+        let Some(index) = names.iter().position(|known| known == name) else {
+            return Err(format!("unknown gauge {name:?}"));
+        };
+        let slot = match gauges.iter().position(|(i, _)| *i == index) {
+            Some(slot) => slot,
+            None => {
+                gauges.push((index, HashMap::new()));
+                gauges.len() - 1
+            }
+        };
+        gauges[slot].1.insert(canonical_key(key), value);
+    }
+    Ok(gauges)
+}
+
+fn pairs(line: &str) -> Result<Vec<(&str, f64)>, String> {
+    let mut pairs = Vec::new();
+    for token in line.split_whitespace() {
+        let Some((key, raw)) = token.split_once('=') else {
+            return Err(format!("expected key=value, got {token:?}"));
+        };
+        match raw.parse() {
+            Ok(value) => pairs.push((key, value)),
+            Err(_) if is_numeric_key(key) => {
+                return Err(format!("{key} expects a number, got {raw:?}"));
+            }
+            Err(_) => {}
+        }
+    }
+    Ok(pairs)
+}
+
+// Keys that drive the gauge; any other key may carry free text
+fn is_numeric_key(key: &str) -> bool {
+    let key = key.rsplit_once('.').map_or(key, |(_, key)| key);
+    matches!(
+        canonical_key(key).as_str(),
+        "primary"
+            | "secondary"
+            | "chrono1"
+            | "chrono2"
+            | "readout"
+            | "highlightlower"
+            | "highlightupper"
+    )
+}
+
+fn canonical_key(key: &str) -> String {
+    match key {
+        "needle1" => "primary",
+        "needle2" => "secondary",
+        key => key,
+    }
+    .to_string()
+}
+
+/// Turns one parsed line into commands. Keys missing from the line hide their element,
+/// so a producer can drop a needle just by no longer sending it.
+fn commands_for(values: HashMap<String, f64>, static_highlight: bool) -> Vec<InstrumentCommand> {
+    let mut commands = Vec::new();
+    if !static_highlight {
+        let bounds = (values.get("highlightlower"), values.get("highlightupper"));
+        commands.push(match bounds {
+            (Some(&lower), Some(&upper)) => InstrumentCommand::SetHighlightBounds(lower, upper),
+            _ => InstrumentCommand::ClearHighlightBounds,
+        });
+    }
+    let clears = [
+        ("secondary", InstrumentCommand::ClearSecondaryNeedle),
+        ("chrono1", InstrumentCommand::ClearChronograph),
+        ("chrono2", InstrumentCommand::ClearSecondaryChronograph),
+        ("readout", InstrumentCommand::ClearReadout),
+    ];
+    for (key, clear) in clears {
+        if !values.contains_key(key) {
+            commands.push(clear);
+        }
+    }
+    commands.push(InstrumentCommand::SetMany(values));
+    commands
+}
+
+/// How malformed input lines are handled: each one is reported on stderr with its line
+/// number and counted, and in strict mode the first one ends the process
+struct InputErrors {
+    count: AtomicUsize,
+    strict: bool,
+}
+
+impl InputErrors {
+    fn report(&self, line_number: usize, message: &str) {
+        eprintln!("instrument: line {line_number}: {message}");
+        self.count.fetch_add(1, Ordering::Relaxed);
+        if self.strict {
+            std::process::exit(1);
+        }
+    }
+
+    /// Draws a small red "input error" badge above the pivot once any line was rejected
+    fn badge(errors: Arc<Self>) -> CustomLayer {
+        CustomLayer::new(move |scene, dial| {
+            let count = errors.count.load(Ordering::Relaxed);
+            if count == 0 {
+                return;
+            }
+            let text = match count {
+                1 => "INPUT ERROR".to_string(),
+                count => format!("INPUT ERROR x{count}"),
+            };
+            scene.add_command(DrawCommand::Text {
+                x: dial.cx,
+                y: dial.cy - dial.radius / 3,
+                text,
+                font_size: dial.radius as f32 / 8.0,
+                color: (0xff, 0x00, 0x00),
+            });
+        })
+    }
+}
+
+/// Polls the named system metric once a second onto the primary needle and readout,
+/// returning the commands and the metric's unit
+#[cfg(feature = "system-metrics")]
+fn system_source(name: &str) -> Result<(Receiver<InstrumentCommand>, &'static str), String> {
+    use instrument::{SystemMetric, SystemSource};
+    let metric = SystemMetric::from_name(name).ok_or_else(|| {
+        format!("unknown --source {name:?}; expected cpu, memory, temperature or network")
+    })?;
+    let source = SystemSource::new(&[(metric, "primary"), (metric, "readout")]);
+    Ok((
+        source.spawn(std::time::Duration::from_secs(1)),
+        metric.unit(),
+    ))
+}
+
+#[cfg(not(feature = "system-metrics"))]
+fn system_source(_: &str) -> Result<(Receiver<InstrumentCommand>, &'static str), String> {
+    Err("--source needs instrument built with the system-metrics feature".to_string())
+}
+
+fn forward_stdin<T>(
+    sender: &Sender<T>,
+    errors: &InputErrors,
+    commands: impl Fn(&str) -> Result<Vec<T>, String>,
+) {
+    for (index, line) in std::io::stdin().lock().lines().enumerate() {
+        let Ok(line) = line else {
+            break;
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let commands = match commands(&line) {
+            Ok(commands) => commands,
+            Err(message) => {
+                errors.report(index + 1, &message);
+                continue;
+            }
+        };
+        for command in commands {
+            // The window has closed
+            if sender.send(command).is_err() {
+                return;
+            }
+        }
+    }
+    let count = errors.count.load(Ordering::Relaxed);
+    if count > 0 {
+        eprintln!("instrument: ignored {count} malformed input line(s)");
+    }
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("{message}\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+
+    let source = match args.source.as_deref().map(system_source).transpose() {
+        Ok(source) => source,
+        Err(message) => {
+            eprintln!("{message}\n{USAGE}");
+            return ExitCode::from(2);
+        }
+    };
+    let named_dials = args.curved_text.is_none();
+    let errors = Arc::new(InputErrors {
+        count: AtomicUsize::new(0),
+        strict: args.strict,
+    });
+    let config = InstrumentConfig::builder()
+        .title(args.title.clone())
+        .range(args.range)
+        .maybe_highlight_band(
+            args.highlight
+                .map(|(lower, upper)| (lower, upper, Color::new(0xff, 0x00, 0x00))),
+        )
+        .maybe_ticks_count(args.ticks)
+        .maybe_minor_ticks_per_interval(args.minor_ticks)
+        .maybe_window_width(args.size.map(|(width, _)| width))
+        .maybe_window_height(args.size.map(|(_, height)| height))
+        .maybe_bottom_curved_text(
+            args.units
+                .or(source.as_ref().map(|(_, unit)| unit.to_string())),
+        )
+        .maybe_curved_text(args.curved_text)
+        .maybe_chronograph_range(args.chrono_range)
+        .borderless(args.borderless)
+        .click_through(args.click_through)
+        .alert_rules(args.alerts)
+        .maybe_frame_hook(args.theme.frame_hook())
+        .maybe_custom_layer(args.error_badge.then(|| InputErrors::badge(errors.clone())))
+        .build();
+    let static_highlight = args.highlight.is_some();
+    let (on_eof, range) = (args.on_eof, args.range);
+
+    let dashboard = match (args.layout, args.gauges) {
+        (Some(path), _) => match Dashboard::load(&path) {
+            Ok(dashboard) => Some(dashboard),
+            Err(error) => {
+                eprintln!("instrument: {path}: {error}");
+                return ExitCode::FAILURE;
+            }
+        },
+        (None, Some(names)) => {
+            let mut dashboard = Dashboard::new(args.title);
+            for name in names {
+                let mut config = config.clone();
+                if named_dials {
+                    config.curved_text = name.clone();
+                }
+                dashboard.add_named(name, Instrument::new(config));
+            }
+            Some(dashboard)
+        }
+        (None, None) => None,
+    };
+
+    let result = match dashboard {
+        Some(mut dashboard) => {
+            let names = dashboard.names().to_vec();
+            let (sender, receiver) = mpsc::channel();
+            std::thread::spawn(move || {
+                forward_stdin(&sender, &errors, |line| {
+                    Ok(parse_dashboard_line(line, &names)?
+                        .into_iter()
+                        .flat_map(|(index, values)| {
+                            commands_for(values, static_highlight)
+                                .into_iter()
+                                .map(move |command| (index, command))
+                        })
+                        .collect())
+                });
+                on_eof.finish(&sender, || {
+                    (0..names.len())
+                        .map(|index| (index, demo_sweep(range)))
+                        .collect()
+                });
+            });
+            dashboard.show_with_commands(receiver)
+        }
+        None => {
+            let receiver = match source {
+                Some((receiver, _)) => receiver,
+                None => {
+                    let (sender, receiver) = mpsc::channel();
+                    std::thread::spawn(move || {
+                        forward_stdin(&sender, &errors, |line| {
+                            let values = parse_line(line)?;
+                            Ok(if values.is_empty() {
+                                Vec::new()
+                            } else {
+                                commands_for(values, static_highlight)
+                            })
+                        });
+                        on_eof.finish(&sender, || vec![demo_sweep(range)]);
+                    });
+                    receiver
+                }
+            };
+            let mut instrument = Instrument::new(config);
+            match args.framebuffer {
+                #[cfg(target_os = "linux")]
+                Some(device) => instrument.show_on_framebuffer_with_commands(device, receiver),
+                #[cfg(not(target_os = "linux"))]
+                Some(_) => {
+                    eprintln!("--framebuffer is only supported on Linux");
+                    return ExitCode::from(2);
+                }
+                None => instrument.show_with_commands(receiver),
+            }
+        }
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(error) => {
+            eprintln!("instrument: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
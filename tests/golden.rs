@@ -0,0 +1,210 @@
+//! Golden image tests. Each case renders offscreen and compares the RGBA output
+//! byte-for-byte against a PPM in `tests/golden/`.
+//!
+//! After an intentional visual change, regenerate the images with
+//! `UPDATE_GOLDEN=1 cargo test --test golden` and review the diff before committing.
+
+use instrument::{
+    frame_hash, Animation, AnimationTarget, CustomLayer, DrawCommand, Instrument,
+    InstrumentCommand, InstrumentConfig, PixelFormat, Rotation,
+};
+use std::path::PathBuf;
+
+// The default dimensions are tuned for a 2x framebuffer, so cases render at half scale
+const SIZE: (usize, usize) = (300, 300);
+const UI_SCALE: f64 = 0.5;
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{name}.ppm"))
+}
+
+fn encode_ppm(frame: &[u8], width: usize, height: usize) -> Vec<u8> {
+    let mut out = format!("P6\n{width} {height}\n255\n").into_bytes();
+    for pixel in frame.chunks_exact(4) {
+        out.extend_from_slice(&pixel[..3]);
+    }
+    out
+}
+
+fn assert_matches_golden(name: &str, instrument: &Instrument, (width, height): (usize, usize)) {
+    let frame = instrument.render_offscreen(width, height);
+    let actual = encode_ppm(&frame, width, height);
+    let path = golden_path(name);
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, &actual).unwrap();
+        return;
+    }
+
+    let expected = std::fs::read(&path).unwrap_or_else(|_| {
+        panic!(
+            "missing golden image {}; run with UPDATE_GOLDEN=1",
+            path.display()
+        )
+    });
+    if expected != actual {
+        let dump = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("{name}.actual.ppm"));
+        std::fs::write(&dump, &actual).unwrap();
+        let differing = expected
+            .iter()
+            .zip(&actual)
+            .filter(|(a, b)| a != b)
+            .count();
+        panic!(
+            "{name} differs from golden ({differing} bytes, hash {:016x} vs {:016x}); actual written to {}",
+            frame_hash(&expected),
+            frame_hash(&actual),
+            dump.display()
+        );
+    }
+}
+
+#[test]
+fn offscreen_render_is_deterministic() {
+    let mut instrument = Instrument::new(InstrumentConfig::builder().ui_scale(UI_SCALE).build());
+    instrument.set_value(42.0);
+    let first = instrument.render_offscreen(SIZE.0, SIZE.1);
+    let second = instrument.render_offscreen(SIZE.0, SIZE.1);
+    assert_eq!(frame_hash(&first), frame_hash(&second));
+}
+
+#[test]
+fn over_range_value_shows_warning() {
+    let mut instrument = Instrument::new(InstrumentConfig::builder().ui_scale(UI_SCALE).build());
+    instrument.set_primary_value(100.0);
+    let at_limit = instrument.render_offscreen(SIZE.0, SIZE.1);
+    instrument.set_primary_value(150.0);
+    let over_range = instrument.render_offscreen(SIZE.0, SIZE.1);
+    assert_ne!(frame_hash(&at_limit), frame_hash(&over_range));
+}
+
+#[test]
+fn default_gauge() {
+    let mut instrument = Instrument::new(InstrumentConfig::builder().ui_scale(UI_SCALE).build());
+    instrument.set_value(42.0);
+    assert_matches_golden("default_gauge", &instrument, SIZE);
+}
+
+#[test]
+fn all_complications() {
+    let config = InstrumentConfig::builder()
+        .curved_text("instrument".to_string())
+        .highlight_band((60.0, 80.0, instrument::Color::new(0xff, 0x00, 0x00)))
+        .ui_scale(UI_SCALE)
+        .build();
+    let mut instrument = Instrument::new(config);
+    instrument.set_value(73.5);
+    instrument.set_secondary_value(20.0);
+    instrument.set_chronograph_value(15.0);
+    instrument.set_secondary_chronograph_value(50.0);
+    assert_matches_golden("all_complications", &instrument, SIZE);
+}
+
+#[test]
+fn letterboxed_and_rotated() {
+    let config = InstrumentConfig::builder()
+        .rotation(Rotation::Cw90)
+        .mirror_horizontal(true)
+        .letterbox_color(instrument::Color::new(0x20, 0x20, 0x20))
+        .ui_scale(UI_SCALE)
+        .build();
+    let mut instrument = Instrument::new(config);
+    instrument.set_value(10.0);
+    assert_matches_golden("letterboxed_and_rotated", &instrument, (360, 240));
+}
+
+#[test]
+fn custom_layer() {
+    let config = InstrumentConfig::builder()
+        .ui_scale(UI_SCALE)
+        .custom_layer(CustomLayer::new(|scene, dial| {
+            scene.add_command(DrawCommand::Circle {
+                cx: dial.cx + dial.radius / 2,
+                cy: dial.cy,
+                radius: 6,
+                color: (0x00, 0xa0, 0x00),
+            });
+        }))
+        .build();
+    let mut instrument = Instrument::new(config);
+    instrument.set_value(42.0);
+    assert_matches_golden("custom_layer", &instrument, SIZE);
+}
+
+#[test]
+fn fixed_timestep_animation_is_reproducible() {
+    let frames = || {
+        let config = InstrumentConfig::builder()
+            .ui_scale(UI_SCALE)
+            .fixed_timestep(1.0 / 30.0)
+            .build();
+        let mut renderer = Instrument::new(config).renderer();
+        renderer.apply(InstrumentCommand::PlayAnimation(
+            Animation::new()
+                .keyframe(AnimationTarget::Primary, 0.0, 0.0)
+                .keyframe(AnimationTarget::Primary, 1.0, 100.0),
+        ));
+        let mut frame = vec![0; SIZE.0 * SIZE.1 * 4];
+        (0..10)
+            .map(|_| {
+                renderer.render_into(&mut frame, SIZE.0, SIZE.1);
+                frame_hash(&frame)
+            })
+            .collect::<Vec<_>>()
+    };
+    let first = frames();
+    assert_eq!(first, frames());
+    assert_ne!(first[0], first[9]);
+}
+
+#[test]
+fn cached_background_matches_full_redraw() {
+    let frames = |cached_background: bool| {
+        let config = InstrumentConfig::builder()
+            .ui_scale(UI_SCALE)
+            .fixed_timestep(1.0 / 30.0)
+            .cached_background(cached_background)
+            .build();
+        let mut renderer = Instrument::new(config).renderer();
+        renderer.apply(InstrumentCommand::SetChronograph(10.0));
+        renderer.apply(InstrumentCommand::PlayAnimation(
+            Animation::new()
+                .keyframe(AnimationTarget::Primary, 0.0, 0.0)
+                .keyframe(AnimationTarget::Primary, 1.0, 100.0)
+                .keyframe(AnimationTarget::Readout, 0.0, 5.0)
+                .keyframe(AnimationTarget::Readout, 1.0, 95.0),
+        ));
+        let mut frame = vec![0; SIZE.0 * SIZE.1 * 4];
+        (0..12)
+            .map(|i| {
+                if i == 6 {
+                    renderer.apply(InstrumentCommand::SetRange(0.0, 200.0));
+                }
+                renderer.render_into(&mut frame, SIZE.0, SIZE.1);
+                frame_hash(&frame)
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(frames(true), frames(false));
+}
+
+#[test]
+fn bgra_frame_matches_rgba() {
+    let render = |pixel_format| {
+        let config = InstrumentConfig::builder()
+            .ui_scale(UI_SCALE)
+            .pixel_format(pixel_format)
+            .build();
+        let mut instrument = Instrument::new(config);
+        instrument.set_value(42.0);
+        instrument.render_offscreen(SIZE.0, SIZE.1)
+    };
+    let mut bgra = render(PixelFormat::Bgra8);
+    for pixel in bgra.chunks_exact_mut(4) {
+        pixel.swap(0, 2);
+    }
+    assert_eq!(frame_hash(&bgra), frame_hash(&render(PixelFormat::Rgba8)));
+}